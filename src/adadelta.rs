@@ -0,0 +1,85 @@
+use itertools::izip;
+use crate::error::BMLSError;
+use crate::error;
+use crate::WeightDecay;
+
+/// # AdaDelta Optimizer
+/// - G: Gradient w.r.t. W.
+/// - S: Exponentially weighted average of Past squares of gradients
+/// - D: Exponentially weighted average of Past squares of updates
+/// - W: Weight Tensor
+/// - LR: Learning Rate, usually 1.0
+/// - Rho: Hyperparameter
+/// - Epsilon: Added inside the square roots for numerical stability
+/// - Decay: Weight Decay
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn adadelta(
+    g: &[f32],
+    s: &mut [f32],
+    d: &mut [f32],
+    w: &mut [f32],
+    lr: f32,
+    rho: f32,
+    epsilon: f32,
+    decay: WeightDecay,
+) -> Result<(), BMLSError> {
+    if g.len() != s.len() {
+        return error::length_mismatch("G", g.len(), "S", s.len())
+    }
+
+    if s.len() != d.len() {
+        return error::length_mismatch("S", s.len(), "D", d.len())
+    }
+
+    if d.len() != w.len() {
+        return error::length_mismatch("D", d.len(), "W", w.len())
+    }
+
+    let (l2, wd) = decay.coefficients();
+
+    for (g, s, d, w) in izip!(g, s, d, w) {
+        let g = *g + l2 * *w;
+        // s = Ps + (1 - P)g^2
+        *s = *s * rho + (1. - rho) * g * g;
+        // dx = sqrt(d + e) / sqrt(s + e) * g
+        let dx = f32::sqrt(*d + epsilon) / f32::sqrt(*s + epsilon) * g;
+        // d = Pd + (1 - P)dx^2
+        *d = *d * rho + (1. - rho) * dx * dx;
+
+        *w -= lr * (dx + wd * *w);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_adadelta() {
+        let g = [1.0, -2.0];
+        let mut s = [0.0; 2];
+        let mut d = [0.0; 2];
+        let mut w = [1.0, 1.0];
+
+        adadelta(&g, &mut s, &mut d, &mut w, 1.0, 0.9, 0.01, WeightDecay::None).unwrap();
+
+        // s = 0.1 * g^2, dx = sqrt(0.01) / sqrt(s + 0.01) * g, d = 0.1 * dx^2
+        for (s, e) in izip!(s, [0.1, 0.4]) {
+            assert!((s - e).abs() < 1e-6);
+        }
+        for (d, e) in izip!(d, [0.0090909, 0.0097561]) {
+            assert!((d - e).abs() < 1e-6);
+        }
+        for (w, e) in izip!(w, [0.6984887, 1.3123475]) {
+            assert!((w - e).abs() < 1e-6);
+        }
+
+        assert!(adadelta(&g, &mut [0.0; 3], &mut d, &mut w, 1.0, 0.9, 0.01, WeightDecay::None).is_err());
+        assert!(adadelta(&g, &mut s, &mut [0.0; 3], &mut w, 1.0, 0.9, 0.01, WeightDecay::None).is_err());
+        assert!(adadelta(&g, &mut s, &mut d, &mut [0.0; 3], 1.0, 0.9, 0.01, WeightDecay::None).is_err());
+    }
+}
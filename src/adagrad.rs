@@ -0,0 +1,63 @@
+use itertools::izip;
+use crate::error::BMLSError;
+use crate::error;
+use crate::WeightDecay;
+
+/// # AdaGrad Optimizer
+/// - G: Gradient w.r.t. W.
+/// - S: Sum of Past squares of gradients
+/// - W: Weight Tensor
+/// - LR: Learning Rate
+/// - Epsilon: Added to the denominator for numerical stability
+/// - Decay: Weight Decay
+#[inline]
+pub fn adagrad(
+    g: &[f32],
+    s: &mut [f32],
+    w: &mut [f32],
+    lr: f32,
+    epsilon: f32,
+    decay: WeightDecay,
+) -> Result<(), BMLSError> {
+    if g.len() != s.len() {
+        return error::length_mismatch("G", g.len(), "S", s.len())
+    }
+
+    if s.len() != w.len() {
+        return error::length_mismatch("S", s.len(), "W", w.len())
+    }
+
+    let (l2, wd) = decay.coefficients();
+
+    for (g, s, w) in izip!(g, s, w) {
+        let g = *g + l2 * *w;
+        // s += g^2
+        *s += g * g;
+        // w -= lr * g / sqrt(s)
+        *w -= lr * (g / (f32::sqrt(*s) + epsilon) + wd * *w);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_adagrad() {
+        let g = [4.0, -1.0];
+        let mut s = [0.0; 2];
+        let mut w = [0.0; 2];
+
+        adagrad(&g, &mut s, &mut w, 0.5, 1e-10, WeightDecay::None).unwrap();
+        adagrad(&g, &mut s, &mut w, 0.5, 1e-10, WeightDecay::None).unwrap();
+
+        // the first step is lr * sign(g), the second is lr * sign(g) / sqrt(2)
+        let e = 0.5 + 0.5 / f32::sqrt(2.);
+        assert_eq!(s, [32.0, 2.0]);
+        assert!((w[0] + e).abs() < 1e-6);
+        assert!((w[1] - e).abs() < 1e-6);
+    }
+}
@@ -1,7 +1,9 @@
-
 use itertools::izip;
 use crate::error::BMLSError;
 use crate::error;
+use crate::WeightDecay;
+use crate::Checkpoint;
+use crate::fused::{AdamGroup, fused_adam};
 
 /// # Adam Optimizer
 /// - G: Gradient w.r.t. W
@@ -11,7 +13,14 @@ use crate::error;
 /// - Lr: Learning Rate
 /// - Beta1: Hyperparameter,
 /// - Beta2: Hyperparameter,
+/// - Epsilon: Added to the denominator for numerical stability
+/// - Decay: Weight Decay. `WeightDecay::Decoupled` gives AdamW.
+/// - T: Timestep of this update, starting at 1.
+/// 
+/// V and S store the raw moments. The bias correction (1 - beta^t)
+/// is only applied to the update, so the stored state is never corrupted.
 #[inline]
+#[allow(clippy::too_many_arguments)]
 pub fn adam(
     g: &[f32],
     v: &mut [f32],
@@ -20,6 +29,9 @@ pub fn adam(
     lr: f32,
     beta1: f32,
     beta2: f32,
+    epsilon: f32,
+    decay: WeightDecay,
+    t: usize,
 ) -> Result<(), BMLSError> {
     if g.len() != v.len() {
         return error::length_mismatch("G", g.len(), "V", v.len())
@@ -33,19 +45,331 @@ pub fn adam(
         return error::length_mismatch("S", s.len(), "W", w.len())
     }
 
+    if t == 0 {
+        return error::invalid_timestep(t)
+    }
+
+    let step = AdamStep::new(lr, beta1, beta2, epsilon, t);
+    let (l2, wd) = decay.coefficients();
+
     for (g, v, s, w) in izip!(g, v, s, w) {
+        step.update(*g, v, s, w, l2, wd);
+    }
+
+    Ok(())   
+}
+
+/// Hyperparameters of an Adam update, with the bias corrections for its timestep.
+#[derive(Copy, Clone)]
+pub(crate) struct AdamStep {
+    lr: f32,
+    beta1: f32,
+    beta2: f32,
+    epsilon: f32,
+    c1: f32,
+    c2: f32,
+}
+
+impl AdamStep {
+    /// T must not be zero.
+    #[inline]
+    pub(crate) fn new(lr: f32, beta1: f32, beta2: f32, epsilon: f32, t: usize) -> Self {
+        Self {
+            lr,
+            beta1,
+            beta2,
+            epsilon,
+            // bias corrections for timestep t
+            c1: 1. - beta1.powf(t as f32),
+            c2: 1. - beta2.powf(t as f32),
+        }
+    }
+
+    /// Adam update of a single element of V, S and W.
+    /// - L2, WD: coefficients of the Weight Decay
+    #[inline(always)]
+    pub(crate) fn update(&self, g: f32, v: &mut f32, s: &mut f32, w: &mut f32, l2: f32, wd: f32) {
+        let g = g + l2 * *w;
+
         // update V and S
-        *v = (*v * beta1) + (1. - beta1) * g;
-        *s = (*s * beta2) + (1. - beta2) * (g * g);
+        *v = (*v * self.beta1) + (1. - self.beta1) * g;
+        *s = (*s * self.beta2) + (1. - self.beta2) * (g * g);
 
         // correct V and S
-        *v /= 1. - beta1;
-        *s /= 1. - beta2;
+        let vc = *v / self.c1;
+        let sc = *s / self.c2;
 
         // update the weights
-        // w -= lr * (v / (sqrt(s) + e))
-        *w -= lr * (*v / (f32::sqrt(*s) + 0.000000000000001));
+        // w -= lr * (v / (sqrt(s) + e) + wd * w)
+        *w -= self.lr * (vc / (f32::sqrt(sc) + self.epsilon) + wd * *w);
     }
+}
 
-    Ok(())   
-}
\ No newline at end of file
+/// # Adam Optimizer State
+/// Owns V and S for every parameter tensor and tracks the timestep T.
+/// 
+/// Parameters must be passed to `step` in the same order as the 
+/// lengths given to `new`.
+/// 
+/// Decay is applied to every parameter unless excluded with `set_decay_mask`.
+#[derive(Clone, Debug)]
+pub struct Adam {
+    pub lr: f32,
+    pub beta1: f32,
+    pub beta2: f32,
+    pub epsilon: f32,
+    pub decay: WeightDecay,
+    t: usize,
+    v: Vec<Vec<f32>>,
+    s: Vec<Vec<f32>>,
+    mask: Vec<bool>,
+}
+
+impl Adam {
+    /// - Lens: Length of each parameter tensor
+    /// - Lr: Learning Rate
+    /// - Beta1: Hyperparameter
+    /// - Beta2: Hyperparameter
+    /// - Epsilon: Added to the denominator for numerical stability
+    pub fn new(
+        lens: &[usize],
+        lr: f32,
+        beta1: f32,
+        beta2: f32,
+        epsilon: f32,
+    ) -> Self {
+        Self {
+            lr,
+            beta1,
+            beta2,
+            epsilon,
+            decay: WeightDecay::None,
+            t: 0,
+            v: lens.iter().map(|len| vec![0.0; *len]).collect(),
+            s: lens.iter().map(|len| vec![0.0; *len]).collect(),
+            mask: vec![true; lens.len()],
+        }
+    }
+
+    /// # Set Decay Mask
+    /// - Mask: One entry per parameter. False excludes the parameter from weight decay.
+    pub fn set_decay_mask(&mut self, mask: &[bool]) -> Result<(), BMLSError> {
+        if mask.len() != self.mask.len() {
+            return error::length_mismatch("Mask", mask.len(), "V", self.v.len())
+        }
+
+        self.mask.copy_from_slice(mask);
+
+        Ok(())
+    }
+
+    /// Number of steps taken so far.
+    pub fn t(&self) -> usize {
+        self.t
+    }
+
+    /// Exponentially weighted averages of past gradients, one per parameter.
+    pub fn v(&self) -> &[Vec<f32>] {
+        &self.v
+    }
+
+    /// Exponentially weighted averages of past squared gradients, one per parameter.
+    pub fn s(&self) -> &[Vec<f32>] {
+        &self.s
+    }
+
+    /// # Checkpoint
+    /// - W: Weight Tensors
+    /// 
+    /// The state is every V followed by every S.
+    pub fn checkpoint(&self, w: &[&[f32]]) -> Checkpoint {
+        let state = self.v.iter()
+            .chain(self.s.iter())
+            .map(|x| x.as_slice())
+            .collect::<Vec<_>>();
+
+        Checkpoint::new(self.t as u64, w, &state)
+    }
+
+    /// # Restore
+    /// - Ckpt: Checkpoint made by `Adam::checkpoint`
+    /// - W: Weight Tensors to overwrite
+    /// 
+    /// Restores W, V, S and T. Nothing is modified if the
+    /// checkpoint does not match the shape of this optimizer.
+    pub fn restore(
+        &mut self,
+        ckpt: &Checkpoint,
+        w: &mut [&mut [f32]],
+    ) -> Result<(), BMLSError> {
+        let mut state = self.v.iter_mut()
+            .chain(self.s.iter_mut())
+            .map(|x| x.as_mut_slice())
+            .collect::<Vec<_>>();
+
+        self.t = ckpt.restore(w, &mut state)? as usize;
+
+        Ok(())
+    }
+
+    /// # Adam Step
+    /// - G: Gradients w.r.t. each W
+    /// - W: Weight Tensors
+    /// 
+    /// Advances T by one and updates every parameter in one parallel pass. 
+    /// Nothing is modified if any of the lengths do not match. 
+    pub fn step(
+        &mut self,
+        g: &[&[f32]],
+        w: &mut [&mut [f32]],
+    ) -> Result<(), BMLSError> {
+        if g.len() != self.v.len() {
+            return error::length_mismatch("G", g.len(), "V", self.v.len())
+        }
+
+        if w.len() != self.v.len() {
+            return error::length_mismatch("W", w.len(), "V", self.v.len())
+        }
+
+        let mut groups = izip!(g, &mut self.v, &mut self.s, w, &self.mask)
+            .map(|(g, v, s, w, mask)| AdamGroup { 
+                g, 
+                v, 
+                s, 
+                w, 
+                decay: self.decay.masked(*mask),
+            })
+            .collect::<Vec<_>>();
+
+        fused_adam(&mut groups, self.lr, self.beta1, self.beta2, self.epsilon, self.t + 1)?;
+
+        self.t += 1;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_adam_bias_correction() {
+        let g = [0.5, -2.0, 0.01];
+        let mut v = [0.0; 3];
+        let mut s = [0.0; 3];
+        let mut w = [1.0; 3];
+
+        adam(&g, &mut v, &mut s, &mut w, 0.1, 0.9, 0.999, 1e-8, WeightDecay::None, 1).unwrap();
+
+        // at t = 1 the corrected update is lr * sign(g)
+        for (g, w) in izip!(g, w) {
+            assert!((w - (1.0 - 0.1 * g.signum())).abs() < 1e-4);
+        }
+
+        // the stored moments are not corrected
+        for (g, v, s) in izip!(g, v, s) {
+            assert!((v - 0.1 * g).abs() < 1e-6);
+            assert!((s - 0.001 * g * g).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_adam_large_timestep() {
+        let g = [0.5, -2.0];
+        let mut v = [0.0; 2];
+        let mut s = [0.0; 2];
+        let mut w = [1.0; 2];
+
+        // a timestep past i32::MAX must not wrap, so both corrections are 1
+        adam(&g, &mut v, &mut s, &mut w, 0.1, 0.9, 0.999, 1e-8, WeightDecay::None, i32::MAX as usize + 2).unwrap();
+
+        // 0.1 * g / sqrt(0.001 * g^2) = sqrt(10) * sign(g)
+        for (g, w) in izip!(g, w) {
+            assert!((w - (1.0 - 0.1 * f32::sqrt(10.0) * g.signum())).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_adam_struct() {
+        let g1 = vec![0.3; 4];
+        let g2 = vec![-1.0; 2];
+        let mut w1 = vec![0.0; 4];
+        let mut w2 = vec![0.0; 2];
+
+        let mut v = vec![0.0; 4];
+        let mut s = vec![0.0; 4];
+        let mut w = vec![0.0; 4];
+
+        let mut opt = Adam::new(&[4, 2], 0.01, 0.9, 0.999, 1e-8);
+
+        for t in 1..=500 {
+            opt.step(&[&g1, &g2], &mut [&mut w1, &mut w2]).unwrap();
+            adam(&g1, &mut v, &mut s, &mut w, 0.01, 0.9, 0.999, 1e-8, WeightDecay::None, t).unwrap();
+        }
+
+        assert_eq!(opt.t(), 500);
+        assert_eq!(w, w1);
+        assert_eq!(opt.v()[0], v);
+        assert_eq!(opt.s()[0], s);
+
+        // a constant gradient moves each weight by lr per step
+        assert!((w2[0] - 5.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_adam_step_mismatch() {
+        let mut opt = Adam::new(&[4, 2], 0.01, 0.9, 0.999, 1e-8);
+        let mut w1 = vec![0.0; 4];
+        let mut w2 = vec![0.0; 3];
+
+        assert!(opt.step(&[&[0.0; 4], &[0.0; 3]], &mut [&mut w1, &mut w2]).is_err());
+        assert_eq!(opt.t(), 0);
+    }
+
+    #[test]
+    fn test_adamw_mask() {
+        let g = vec![0.0; 2];
+        let mut w1 = vec![1.0; 2];
+        let mut w2 = vec![1.0; 2];
+
+        let mut opt = Adam::new(&[2, 2], 0.1, 0.9, 0.999, 1e-8);
+        opt.decay = WeightDecay::Decoupled(0.5);
+        opt.set_decay_mask(&[true, false]).unwrap();
+
+        opt.step(&[&g, &g], &mut [&mut w1, &mut w2]).unwrap();
+
+        // decoupled decay shrinks w by lr * decay * w, even with a zero gradient
+        assert!((w1[0] - 0.95).abs() < 1e-6);
+        assert_eq!(w2, [1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_adam_resume() {
+        let g = vec![0.5, -0.25, 1.0];
+        let mut w1 = vec![1.0; 3];
+        let mut opt1 = Adam::new(&[3], 0.01, 0.9, 0.999, 1e-8);
+
+        for _ in 0..10 {
+            opt1.step(&[&g], &mut [&mut w1]).unwrap();
+        }
+
+        let mut bytes = Vec::new();
+        opt1.checkpoint(&[&w1]).write(&mut bytes).unwrap();
+
+        let mut w2 = vec![0.0; 3];
+        let mut opt2 = Adam::new(&[3], 0.01, 0.9, 0.999, 1e-8);
+        opt2.restore(&Checkpoint::read(&bytes[..]).unwrap(), &mut [&mut w2]).unwrap();
+
+        for _ in 0..10 {
+            opt1.step(&[&g], &mut [&mut w1]).unwrap();
+            opt2.step(&[&g], &mut [&mut w2]).unwrap();
+        }
+
+        assert_eq!(opt2.t(), 20);
+        assert_eq!(w1, w2);
+        assert_eq!(opt1.v(), opt2.v());
+        assert_eq!(opt1.s(), opt2.s());
+    }
+}
@@ -0,0 +1,100 @@
+use itertools::izip;
+use crate::error::BMLSError;
+use crate::error;
+use crate::WeightDecay;
+
+/// # Adamax Optimizer
+/// - G: Gradient w.r.t. W
+/// - V: Exponentialy weighted average of past gradients
+/// - U: Exponentialy weighted infinity norm of past gradients
+/// - W: Weight Tensor
+/// - Lr: Learning Rate
+/// - Beta1: Hyperparameter,
+/// - Beta2: Hyperparameter,
+/// - Epsilon: Added to the denominator for numerical stability
+/// - Decay: Weight Decay
+/// - T: Timestep of this update, starting at 1.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn adamax(
+    g: &[f32],
+    v: &mut [f32],
+    u: &mut [f32],
+    w: &mut [f32],
+    lr: f32,
+    beta1: f32,
+    beta2: f32,
+    epsilon: f32,
+    decay: WeightDecay,
+    t: usize,
+) -> Result<(), BMLSError> {
+    if g.len() != v.len() {
+        return error::length_mismatch("G", g.len(), "V", v.len())
+    }
+
+    if v.len() != u.len() {
+        return error::length_mismatch("V", v.len(), "U", u.len())
+    }
+
+    if u.len() != w.len() {
+        return error::length_mismatch("U", u.len(), "W", w.len())
+    }
+
+    if t == 0 {
+        return error::invalid_timestep(t)
+    }
+
+    // only V needs a bias correction
+    let c1 = 1. - beta1.powf(t as f32);
+
+    let (l2, wd) = decay.coefficients();
+
+    for (g, v, u, w) in izip!(g, v, u, w) {
+        let g = *g + l2 * *w;
+
+        // update V and U
+        *v = (*v * beta1) + (1. - beta1) * g;
+        *u = f32::max(*u * beta2, g.abs());
+
+        // w -= lr * (v / (u + e) + wd * w)
+        *w -= lr * ((*v / c1) / (*u + epsilon) + wd * *w);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_adamax() {
+        let g = [0.5, -3.0];
+        let mut v = [0.0; 2];
+        let mut u = [0.0; 2];
+        let mut w = [0.0; 2];
+
+        adamax(&g, &mut v, &mut u, &mut w, 0.1, 0.9, 0.999, 1e-8, WeightDecay::None, 1).unwrap();
+
+        // at t = 1 the update is lr * sign(g)
+        assert_eq!(u, [0.5, 3.0]);
+        assert!((w[0] + 0.1).abs() < 1e-6);
+        assert!((w[1] - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_adamax_large_timestep() {
+        let g = [0.5, -3.0];
+        let mut v = [0.0; 2];
+        let mut u = [0.0; 2];
+        let mut w = [0.0; 2];
+
+        // a timestep past i32::MAX must not wrap, so the correction is 1
+        adamax(&g, &mut v, &mut u, &mut w, 0.1, 0.9, 0.999, 1e-8, WeightDecay::None, i32::MAX as usize + 2).unwrap();
+
+        // lr * 0.1 * g / |g|
+        assert!((w[0] + 0.01).abs() < 1e-6);
+        assert!((w[1] - 0.01).abs() < 1e-6);
+    }
+}
@@ -5,16 +5,22 @@ use crate::error;
 use crate::Ptr;
 
 /// # Avg Pooling Operation
-/// - A: Input
-/// - B: Output
-/// - Dim: Dimensions of A. 
-/// - Strides: Distance between patches
+/// - X: Input
+/// - Y: Output
+/// - X_dim: Dimensions of X
+/// - Stride: Distance between patches
 /// - Kernel: Size of the Kernel
-/// - Dilations: Amount to dilate. Cannot be Zero. 
+/// - Padh: Height Padding
+/// - Padw: Width Padding
+/// - Dilation: Spacing between the elements of the Kernel, 1 for none. Cannot be Zero.
+/// 
+/// The dilated kernel covers dilation_rows * (kernel_rows - 1) + 1 rows, and likewise for columns.
+/// Padding counts towards the average.
 /// 
-/// B Should have the height: ((input_rows - kernel_rows + (padh0 + padh1)) / stride_rows) + 1
-/// B should have the width: ((input_cols - kernel_cols + (padw0 + padw1)) / stride_cols) + 1
+/// Y should have the height: ((input_rows - dilated_kernel_rows + (padh0 + padh1)) / stride_rows) + 1
+/// Y should have the width: ((input_cols - dilated_kernel_cols + (padw0 + padw1)) / stride_cols) + 1
 #[inline]
+#[allow(clippy::too_many_arguments)]
 pub fn avg_pool(
     x: &[f32],
     y: &mut [f32],
@@ -23,13 +29,35 @@ pub fn avg_pool(
     kernel: [usize; 2],
     padh: [usize; 2],
     padw: [usize; 2],
+    dilation: [usize; 2],
 ) -> Result<(), BMLSError> {
     let (strideh, stridew) = (stride[0], stride[1]);
     let (kernelh, kernelw) = (kernel[0], kernel[1]);
+    let (dilh, dilw) = (dilation[0], dilation[1]);
     let (xn, xc, xh, xw) = (x_dim[0], x_dim[1], x_dim[2], x_dim[3]);
+    // the extent of the dilated kernel
+    let (hk, wk) = (dilh * kernelh.saturating_sub(1) + 1, dilw * kernelw.saturating_sub(1) + 1);
 
-    let hstart = ((x_dim[2] - kernelh + (padh[0] + padh[1])) / strideh) + 1;
-    let wstart = ((x_dim[3] - kernelw + (padw[0] + padw[1])) / stridew) + 1;
+    // the kernel dimensions cannot be 0 and the dilated kernel cannot
+    // be larger than the dimensions of the input + the padding.
+    if kernelh == 0 || hk > (xh+padh[0]+padh[1]) || 
+       kernelw == 0 || wk > (xw+padw[0]+padw[1]) 
+    {
+        return error::invalid_kernel_dim([1, 1, kernelh, kernelw])
+    }
+
+    // strides must not be 0
+    if strideh == 0 || stridew == 0 {
+        return error::invalid_strides(strideh, stridew)
+    }
+
+    // dilations must not be 0
+    if dilh == 0 || dilw == 0 {
+        return error::invalid_dilation(dilh, dilw)
+    }
+
+    let hstart = ((xh + (padh[0] + padh[1]) - hk) / strideh) + 1;
+    let wstart = ((xw + (padw[0] + padw[1]) - wk) / stridew) + 1;
 
     let (_, yc, yh, yw) = (x_dim[0], x_dim[1], hstart, wstart);
 
@@ -47,19 +75,6 @@ pub fn avg_pool(
         return error::length_mismatch("Y", y.len(), "Y_dim", ylen);
     }
 
-    // the kernel dimensions cannot be 0 or greater than 
-    // the dimensions of the input + the padding.
-    if kernel[0] == 0 || kernel[0] >= (xh+padh[0]+padh[1]) || 
-       kernel[1] == 0 || kernel[1] >= (xw+padw[0]+padw[1]) 
-    {
-        return error::invalid_kernel_dim([1, 1, kernelh, kernelw])
-    }
-
-    // strides must not be 0
-    if strideh == 0 || stridew == 0 {
-        return error::invalid_strides(strideh, stridew)
-    }
-
     let x = Ptr::new(x);
     let y = Ptr::new(y);
 
@@ -73,9 +88,9 @@ pub fn avg_pool(
                     let xcol = w * stridew;
                     let mut sum = 0.0;
                     for kh in 0..kernelh {
-                        let xrow = (xrow + kh) as isize - padh[0] as isize;
+                        let xrow = (xrow + kh * dilh) as isize - padh[0] as isize;
                         for kw in 0..kernelw {
-                            let xcol = (xcol + kw) as isize - padw[0] as isize;
+                            let xcol = (xcol + kw * dilw) as isize - padw[0] as isize;
                             if xrow >= xh as isize || xrow < 0 || xcol >= xw as isize || xcol < 0 {
                                 continue;
                             }
@@ -98,10 +113,14 @@ pub fn avg_pool(
 /// - XDim: Dimensions of X
 /// - Strides: Distance between patches
 /// - Kernel: Size of the Kernel
+/// - Padh: Height Padding
+/// - Padw: Width Padding
+/// - Dilation: Spacing between the elements of the Kernel, 1 for none. Cannot be Zero.
 /// 
-/// GY Should have the height: ((input_rows - kernel_rows + (padh0 + padh1)) / stride_rows) + 1
-/// GY should have the width: ((input_cols - kernel_cols + (padw0 + padw1)) / stride_cols) + 1
+/// GY Should have the height: ((input_rows - dilated_kernel_rows + (padh0 + padh1)) / stride_rows) + 1
+/// GY should have the width: ((input_cols - dilated_kernel_cols + (padw0 + padw1)) / stride_cols) + 1
 #[inline]
+#[allow(clippy::too_many_arguments)]
 pub fn avg_pool_wrt_x(
     gy: &[f32],
     gx: &mut [f32],
@@ -110,13 +129,35 @@ pub fn avg_pool_wrt_x(
     kernel: [usize; 2],
     padh: [usize; 2],
     padw: [usize; 2],
+    dilation: [usize; 2],
 ) -> Result<(), BMLSError> {
     let (strideh, stridew) = (stride[0], stride[1]);
     let (kernelh, kernelw) = (kernel[0], kernel[1]);
+    let (dilh, dilw) = (dilation[0], dilation[1]);
     let (xn, xc, xh, xw) = (x_dim[0], x_dim[1], x_dim[2], x_dim[3]);
+    // the extent of the dilated kernel
+    let (hk, wk) = (dilh * kernelh.saturating_sub(1) + 1, dilw * kernelw.saturating_sub(1) + 1);
 
-    let hstart = ((x_dim[2] - kernelh + (padh[0] + padh[1])) / strideh) + 1;
-    let wstart = ((x_dim[3] - kernelw + (padw[0] + padw[1])) / stridew) + 1;
+    // the kernel dimensions cannot be 0 and the dilated kernel cannot
+    // be larger than the dimensions of the input + the padding.
+    if kernelh == 0 || hk > (xh+padh[0]+padh[1]) || 
+       kernelw == 0 || wk > (xw+padw[0]+padw[1]) 
+    {
+        return error::invalid_kernel_dim([1, 1, kernelh, kernelw])
+    }
+
+    // strides must not be 0
+    if strideh == 0 || stridew == 0 {
+        return error::invalid_strides(strideh, stridew)
+    }
+
+    // dilations must not be 0
+    if dilh == 0 || dilw == 0 {
+        return error::invalid_dilation(dilh, dilw)
+    }
+
+    let hstart = ((xh + (padh[0] + padh[1]) - hk) / strideh) + 1;
+    let wstart = ((xw + (padw[0] + padw[1]) - wk) / stridew) + 1;
 
     let (_, yc, yh, yw) = (x_dim[0], x_dim[1], hstart, wstart);
 
@@ -134,19 +175,6 @@ pub fn avg_pool_wrt_x(
         return error::length_mismatch("GY", gy.len(), "GY_dim", ylen);
     }
 
-    // the kernel dimensions cannot be 0 or greater than 
-    // the dimensions of the input + the padding.
-    if kernel[0] == 0 || kernel[0] >= (xh+padh[0]+padh[1]) || 
-       kernel[1] == 0 || kernel[1] >= (xw+padw[0]+padw[1]) 
-    {
-        return error::invalid_kernel_dim([1, 1, kernelh, kernelw])
-    }
-
-    // strides must not be 0
-    if strideh == 0 || stridew == 0 {
-        return error::invalid_strides(strideh, stridew)
-    }
-
     let gy = Ptr::new(gy);
     let gx = Ptr::new(gx);
 
@@ -159,9 +187,9 @@ pub fn avg_pool_wrt_x(
                     let yi = n * yc * yh * yw + c * yh * yw + h * yw + w;
                     let xcol = w * stridew;
                     for kh in 0..kernelh {
-                        let xrow = (xrow + kh) as isize - padh[0] as isize;
+                        let xrow = (xrow + kh * dilh) as isize - padh[0] as isize;
                         for kw in 0..kernelw {
-                            let xcol = (xcol + kw) as isize - padw[0] as isize;
+                            let xcol = (xcol + kw * dilw) as isize - padw[0] as isize;
                             if xrow >= xh as isize || xrow < 0 || xcol >= xw as isize || xcol < 0 {
                                 continue;
                             }
@@ -203,6 +231,7 @@ mod tests {
                 [2, 2],
                 [1, 1],
                 [1, 1],
+                [1, 1],
             ).unwrap();
     
         for i in 0..5 {
@@ -232,6 +261,7 @@ mod tests {
                 [1, 1],
                 [0, 0],
                 [0, 0],
+                [1, 1],
             ).unwrap();
     
         for i in 0..4 {
@@ -244,4 +274,75 @@ mod tests {
 
         //panic!("");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_avg_pool_dilation() {
+        // 4x5, x[r, c] = 5r + c
+        let x: Vec<f32> = (0..20).map(|i| i as f32).collect();
+        // a 2x2 kernel dilated by (2, 1) covers 3x2, so Y is 2x4
+        let mut y = [0.0; 8];
+
+        avg_pool(&x, &mut y, [1, 1, 4, 5], [1, 1], [2, 2], [0, 0], [0, 0], [2, 1]).unwrap();
+
+        // the mean of x[h, w], x[h, w + 1], x[h + 2, w] and x[h + 2, w + 1]
+        for h in 0..2 {
+            for w in 0..4 {
+                assert_eq!(y[h * 4 + w], (5 * h + w) as f32 + 5.5);
+            }
+        }
+
+        // rows are each in one window, the inner columns in two
+        let mut gx = [0.0; 20];
+        avg_pool_wrt_x(&[1.0; 8], &mut gx, [1, 1, 4, 5], [1, 1], [2, 2], [0, 0], [0, 0], [2, 1]).unwrap();
+        for row in gx.chunks(5) {
+            assert_eq!(row, [0.25, 0.5, 0.5, 0.5, 0.25]);
+        }
+
+        assert!(matches!(
+            avg_pool(&x, &mut y, [1, 1, 4, 5], [1, 1], [2, 2], [0, 0], [0, 0], [1, 0]),
+            Err(BMLSError::InvalidDilation(1, 0))
+        ));
+        assert!(matches!(
+            avg_pool_wrt_x(&[1.0; 8], &mut gx, [1, 1, 4, 5], [1, 1], [2, 2], [0, 0], [0, 0], [0, 2]),
+            Err(BMLSError::InvalidDilation(0, 2))
+        ));
+    }
+
+    #[test]
+    fn test_avg_pool_dilation_gradient() {
+        // avg_pool is linear, so the gradient is its adjoint:
+        // <avg_pool(x), gy> == <x, avg_pool_wrt_x(gy)>
+        let x_dim = [2, 2, 6, 5];
+        let (stride, kernel, padh, padw, dilation) = ([2, 1], [2, 3], [1, 0], [1, 2], [3, 2]);
+        // ((6 + 1 - 4) / 2 + 1), ((5 + 3 - 5) / 1 + 1)
+        let ylen = 2 * 2 * 2 * 4;
+
+        let x: Vec<f32> = (0..120).map(|i| f32::sin(i as f32 * 0.7)).collect();
+        let gy: Vec<f32> = (0..ylen).map(|i| f32::cos(i as f32 * 1.3)).collect();
+
+        let mut y = vec![0.0; ylen];
+        let mut gx = vec![0.0; 120];
+        avg_pool(&x, &mut y, x_dim, stride, kernel, padh, padw, dilation).unwrap();
+        avg_pool_wrt_x(&gy, &mut gx, x_dim, stride, kernel, padh, padw, dilation).unwrap();
+
+        let a = y.iter().zip(&gy).map(|(a, b)| a * b).sum::<f32>();
+        let b = x.iter().zip(&gx).map(|(a, b)| a * b).sum::<f32>();
+        assert!((a - b).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_avg_pool_full_kernel() {
+        // a kernel as large as the padded input gives a 1x1 output
+        let x: Vec<f32> = (0..9).map(|i| i as f32).collect();
+        let mut y = [0.0];
+
+        avg_pool(&x, &mut y, [1, 1, 3, 3], [1, 1], [3, 3], [0, 0], [0, 0], [1, 1]).unwrap();
+        assert_eq!(y[0], 4.0);
+
+        // 2x2 dilated by 2 covers the corners of 3x3
+        avg_pool(&x, &mut y, [1, 1, 3, 3], [1, 1], [2, 2], [0, 0], [0, 0], [2, 2]).unwrap();
+        assert_eq!(y[0], 4.0);
+
+        assert!(avg_pool(&x, &mut y, [1, 1, 3, 3], [1, 1], [2, 2], [0, 0], [0, 0], [3, 3]).is_err());
+    }
+}
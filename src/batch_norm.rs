@@ -0,0 +1,357 @@
+use rayon::prelude::*;
+use crate::error::BMLSError;
+use crate::error;
+use crate::Ptr;
+
+/// # Batch Normalization
+/// - X: Input (N x C x H x W)
+/// - Y: Output (N x C x H x W)
+/// - Gamma: Scale (C)
+/// - Beta: Shift (C)
+/// - Mean: Mean used for each channel, saved for the backward pass (C)
+/// - Inv_Std: 1 / sqrt(var + epsilon) used for each channel, saved for the backward pass (C)
+/// - Running_Mean: Running average of the batch means (C)
+/// - Running_Var: Running average of the batch variances (C)
+/// - X_dim: Dimensions of X.
+/// - Momentum: Weight of the current batch in the running averages, usually 0.1
+/// - Epsilon: Added to the variance for numerical stability
+/// - Training: Normalize with the batch statistics and update the running
+///   averages (true), or normalize with the running averages (false)
+///
+/// y = gamma * (x - mean) * inv_std + beta
+///
+/// The running variance uses the unbiased batch variance.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn batch_norm(
+    x: &[f32],
+    y: &mut [f32],
+    gamma: &[f32],
+    beta: &[f32],
+    mean: &mut [f32],
+    inv_std: &mut [f32],
+    running_mean: &mut [f32],
+    running_var: &mut [f32],
+    x_dim: [usize; 4],
+    momentum: f32,
+    epsilon: f32,
+    training: bool,
+) -> Result<(), BMLSError> {
+    let (nx, cx, hx, wx) = (x_dim[0], x_dim[1], x_dim[2], x_dim[3]);
+
+    let len = nx*cx*hx*wx;
+    if x.len() != len {
+        return error::length_mismatch("X", x.len(), "X_Dim", len);
+    }
+
+    if y.len() != x.len() {
+        return error::length_mismatch("Y", y.len(), "X", x.len());
+    }
+
+    for (name, v) in [
+        ("Gamma", gamma.len()),
+        ("Beta", beta.len()),
+        ("Mean", mean.len()),
+        ("Inv_Std", inv_std.len()),
+        ("Running_Mean", running_mean.len()),
+        ("Running_Var", running_var.len()),
+    ] {
+        if v != cx {
+            return error::length_mismatch(name, v, "X_Dim[1]", cx);
+        }
+    }
+
+    let size = hx * wx;
+    let count = (nx * size) as f32;
+
+    let y = Ptr::new(y);
+    let mean = Ptr::new(mean);
+    let inv_std = Ptr::new(inv_std);
+    let running_mean = Ptr::new(running_mean);
+    let running_var = Ptr::new(running_var);
+
+    (0..cx).into_par_iter().for_each(|c| {
+        let planes = (0..nx).map(|n| (n * cx + c) * size..(n * cx + c + 1) * size);
+
+        let (mu, inv) = if training {
+            let mut sum = 0.0;
+            for plane in planes.clone() {
+                sum += x[plane].iter().sum::<f32>();
+            }
+            let mu = sum / count;
+
+            let mut sq = 0.0;
+            for plane in planes.clone() {
+                sq += x[plane].iter().map(|x| (x - mu) * (x - mu)).sum::<f32>();
+            }
+            let var = sq / count;
+
+            let unbiased = if count > 1.0 { sq / (count - 1.) } else { var };
+            let rm = &mut running_mean.get_mut()[c];
+            *rm = (1. - momentum) * *rm + momentum * mu;
+            let rv = &mut running_var.get_mut()[c];
+            *rv = (1. - momentum) * *rv + momentum * unbiased;
+
+            (mu, 1. / f32::sqrt(var + epsilon))
+        } else {
+            let mu = running_mean.get_mut()[c];
+            (mu, 1. / f32::sqrt(running_var.get_mut()[c] + epsilon))
+        };
+
+        mean.get_mut()[c] = mu;
+        inv_std.get_mut()[c] = inv;
+
+        let scale = gamma[c] * inv;
+        for plane in planes {
+            for (x, y) in x[plane.clone()].iter().zip(&mut y.get_mut()[plane]) {
+                *y = (x - mu) * scale + beta[c];
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// # Batch Normalization w.r.t. X
+/// - X: Input (N x C x H x W)
+/// - GY: Gradient w.r.t. Y
+/// - GX: Gradient w.r.t. X
+/// - Gamma: Scale (C)
+/// - Mean: Mean saved by `batch_norm` (C)
+/// - Inv_Std: Inv_Std saved by `batch_norm` (C)
+/// - X_dim: Dimensions of X.
+/// - Training: The mode `batch_norm` was run in. In inference the
+///   statistics are constants, so they are not differentiated.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn batch_norm_wrt_x(
+    x: &[f32],
+    gy: &[f32],
+    gx: &mut [f32],
+    gamma: &[f32],
+    mean: &[f32],
+    inv_std: &[f32],
+    x_dim: [usize; 4],
+    training: bool,
+) -> Result<(), BMLSError> {
+    let (nx, cx, hx, wx) = (x_dim[0], x_dim[1], x_dim[2], x_dim[3]);
+
+    let len = nx*cx*hx*wx;
+    if x.len() != len {
+        return error::length_mismatch("X", x.len(), "X_Dim", len);
+    }
+
+    if gy.len() != x.len() {
+        return error::length_mismatch("GY", gy.len(), "X", x.len());
+    }
+
+    if gx.len() != x.len() {
+        return error::length_mismatch("GX", gx.len(), "X", x.len());
+    }
+
+    for (name, v) in [("Gamma", gamma.len()), ("Mean", mean.len()), ("Inv_Std", inv_std.len())] {
+        if v != cx {
+            return error::length_mismatch(name, v, "X_Dim[1]", cx);
+        }
+    }
+
+    let size = hx * wx;
+    let count = (nx * size) as f32;
+
+    let gx = Ptr::new(gx);
+
+    (0..cx).into_par_iter().for_each(|c| {
+        let planes = (0..nx).map(|n| (n * cx + c) * size..(n * cx + c + 1) * size);
+        let (mu, inv) = (mean[c], inv_std[c]);
+
+        // sum(gy) and sum(gy * x_hat)
+        let mut sum = 0.0;
+        let mut dot = 0.0;
+        if training {
+            for plane in planes.clone() {
+                for (x, gy) in x[plane.clone()].iter().zip(&gy[plane]) {
+                    sum += gy;
+                    dot += gy * (x - mu) * inv;
+                }
+            }
+        }
+
+        // gx = gamma * inv_std * (gy - (sum + x_hat * dot) / count)
+        let scale = gamma[c] * inv;
+        for plane in planes {
+            let gx = &mut gx.get_mut()[plane.clone()];
+            for ((x, gy), gx) in x[plane.clone()].iter().zip(&gy[plane]).zip(gx) {
+                let x_hat = (x - mu) * inv;
+                *gx += scale * (gy - (sum + x_hat * dot) / count);
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// # Batch Normalization w.r.t. Gamma
+/// - X: Input (N x C x H x W)
+/// - GY: Gradient w.r.t. Y
+/// - G_Gamma: Gradient w.r.t. Gamma (C)
+/// - Mean: Mean saved by `batch_norm` (C)
+/// - Inv_Std: Inv_Std saved by `batch_norm` (C)
+/// - X_dim: Dimensions of X.
+#[inline]
+pub fn batch_norm_wrt_gamma(
+    x: &[f32],
+    gy: &[f32],
+    g_gamma: &mut [f32],
+    mean: &[f32],
+    inv_std: &[f32],
+    x_dim: [usize; 4],
+) -> Result<(), BMLSError> {
+    let (nx, cx, hx, wx) = (x_dim[0], x_dim[1], x_dim[2], x_dim[3]);
+
+    let len = nx*cx*hx*wx;
+    if x.len() != len {
+        return error::length_mismatch("X", x.len(), "X_Dim", len);
+    }
+
+    if gy.len() != x.len() {
+        return error::length_mismatch("GY", gy.len(), "X", x.len());
+    }
+
+    for (name, v) in [("G_Gamma", g_gamma.len()), ("Mean", mean.len()), ("Inv_Std", inv_std.len())] {
+        if v != cx {
+            return error::length_mismatch(name, v, "X_Dim[1]", cx);
+        }
+    }
+
+    let size = hx * wx;
+
+    g_gamma.par_iter_mut().enumerate().for_each(|(c, gg)| {
+        let (mu, inv) = (mean[c], inv_std[c]);
+        for n in 0..nx {
+            let plane = (n * cx + c) * size..(n * cx + c + 1) * size;
+            for (x, gy) in x[plane.clone()].iter().zip(&gy[plane]) {
+                *gg += gy * (x - mu) * inv;
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// # Batch Normalization w.r.t. Beta
+/// - GY: Gradient w.r.t. Y (N x C x H x W)
+/// - G_Beta: Gradient w.r.t. Beta (C)
+/// - X_dim: Dimensions of X.
+#[inline]
+pub fn batch_norm_wrt_beta(
+    gy: &[f32],
+    g_beta: &mut [f32],
+    x_dim: [usize; 4],
+) -> Result<(), BMLSError> {
+    let (nx, cx, hx, wx) = (x_dim[0], x_dim[1], x_dim[2], x_dim[3]);
+
+    let len = nx*cx*hx*wx;
+    if gy.len() != len {
+        return error::length_mismatch("GY", gy.len(), "X_Dim", len);
+    }
+
+    if g_beta.len() != cx {
+        return error::length_mismatch("G_Beta", g_beta.len(), "X_Dim[1]", cx);
+    }
+
+    let size = hx * wx;
+
+    g_beta.par_iter_mut().enumerate().for_each(|(c, gb)| {
+        for n in 0..nx {
+            *gb += gy[(n * cx + c) * size..(n * cx + c + 1) * size].iter().sum::<f32>();
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const DIM: [usize; 4] = [3, 2, 2, 2];
+
+    fn input() -> Vec<f32> {
+        (0..24).map(|i| f32::sin(i as f32 * 1.3) * 3.0 + i as f32 * 0.1).collect()
+    }
+
+    // sum(y * r), so that gy = r
+    fn objective(x: &[f32], gamma: &[f32], beta: &[f32], r: &[f32]) -> f32 {
+        let mut y = vec![0.0; 24];
+        let (mut m, mut s, mut rm, mut rv) = ([0.0; 2], [0.0; 2], [0.0; 2], [1.0; 2]);
+        batch_norm(x, &mut y, gamma, beta, &mut m, &mut s, &mut rm, &mut rv, DIM, 0.1, 1e-5, true).unwrap();
+        y.iter().zip(r).map(|(y, r)| y * r).sum()
+    }
+
+    #[test]
+    fn test_batch_norm_training() {
+        let x = input();
+        let mut y = vec![0.0; 24];
+        let (mut m, mut s, mut rm, mut rv) = ([0.0; 2], [0.0; 2], [0.0; 2], [1.0; 2]);
+
+        batch_norm(&x, &mut y, &[1.0, 2.0], &[0.0, 0.5], &mut m, &mut s, &mut rm, &mut rv, DIM, 0.1, 1e-5, true).unwrap();
+
+        for c in 0..2 {
+            let values: Vec<f32> = (0..3).flat_map(|n| y[(n * 2 + c) * 4..(n * 2 + c + 1) * 4].to_vec()).collect();
+            let mean = values.iter().sum::<f32>() / 12.;
+            let var = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / 12.;
+            assert!((mean - [0.0, 0.5][c]).abs() < 1e-4);
+            assert!((var - [1.0, 4.0][c]).abs() < 1e-3);
+            assert!((rm[c] - 0.1 * m[c]).abs() < 1e-6);
+        }
+
+        // inference uses the running statistics
+        let mut y2 = vec![0.0; 24];
+        batch_norm(&x, &mut y2, &[1.0, 1.0], &[0.0, 0.0], &mut m, &mut s, &mut rm, &mut rv, DIM, 0.1, 1e-5, false).unwrap();
+        assert_eq!(m, rm);
+        assert!((y2[0] - (x[0] - rm[0]) / f32::sqrt(rv[0] + 1e-5)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_batch_norm_gradient() {
+        let x = input();
+        let gamma = [1.5, -0.5];
+        let beta = [0.2, 0.1];
+        let r: Vec<f32> = (0..24).map(|i| f32::cos(i as f32)).collect();
+
+        let mut y = vec![0.0; 24];
+        let (mut m, mut s, mut rm, mut rv) = ([0.0; 2], [0.0; 2], [0.0; 2], [1.0; 2]);
+        batch_norm(&x, &mut y, &gamma, &beta, &mut m, &mut s, &mut rm, &mut rv, DIM, 0.1, 1e-5, true).unwrap();
+
+        let mut gx = vec![0.0; 24];
+        let mut gg = [0.0; 2];
+        let mut gb = [0.0; 2];
+        batch_norm_wrt_x(&x, &r, &mut gx, &gamma, &m, &s, DIM, true).unwrap();
+        batch_norm_wrt_gamma(&x, &r, &mut gg, &m, &s, DIM).unwrap();
+        batch_norm_wrt_beta(&r, &mut gb, DIM).unwrap();
+
+        for i in 0..24 {
+            let mut xp = x.clone();
+            let mut xm = x.clone();
+            xp[i] += 1e-2;
+            xm[i] -= 1e-2;
+            let fd = (objective(&xp, &gamma, &beta, &r) - objective(&xm, &gamma, &beta, &r)) / 2e-2;
+            assert!((fd - gx[i]).abs() < 1e-2);
+        }
+
+        for c in 0..2 {
+            let (mut gp, mut gm) = (gamma, gamma);
+            gp[c] += 1e-2;
+            gm[c] -= 1e-2;
+            let fd = (objective(&x, &gp, &beta, &r) - objective(&x, &gm, &beta, &r)) / 2e-2;
+            assert!((fd - gg[c]).abs() < 1e-2);
+
+            let (mut bp, mut bm) = (beta, beta);
+            bp[c] += 1e-2;
+            bm[c] -= 1e-2;
+            let fd = (objective(&x, &gamma, &bp, &r) - objective(&x, &gamma, &bm, &r)) / 2e-2;
+            assert!((fd - gb[c]).abs() < 1e-2);
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::error::BMLSError;
+use crate::error;
+
+/// Identifies a BMLS checkpoint file.
+const MAGIC: [u8; 4] = *b"BMLS";
+
+/// Version of the checkpoint format written by this crate.
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+/// # Checkpoint
+/// Weights and optimizer state, for resuming training.
+///
+/// - Step: Optimizer step count (T for the Adam family)
+/// - Weights: Weight Tensors
+/// - State: Optimizer state tensors, like the V of `momentum`
+///   or the S of `rms_prop`, in whatever order the caller chooses.
+///
+/// ## Format
+/// All values are little endian.
+/// - Magic: "BMLS"
+/// - Version: u32
+/// - Payload Length: u64
+/// - Payload: Step (u64), then Weights and State, each written as a
+///   tensor count (u64) followed by each tensor's length (u64) and values (f32).
+/// - Checksum: CRC-32 of the Payload (u32)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Checkpoint {
+    pub step: u64,
+    pub weights: Vec<Vec<f32>>,
+    pub state: Vec<Vec<f32>>,
+}
+
+impl Checkpoint {
+    /// Copies the Weights and State into a new Checkpoint.
+    pub fn new(
+        step: u64,
+        weights: &[&[f32]],
+        state: &[&[f32]],
+    ) -> Self {
+        Self {
+            step,
+            weights: weights.iter().map(|w| w.to_vec()).collect(),
+            state: state.iter().map(|s| s.to_vec()).collect(),
+        }
+    }
+
+    /// # Restore
+    /// - Weights: Weight Tensors to overwrite
+    /// - State: Optimizer state tensors to overwrite
+    ///
+    /// Returns the step count. Nothing is written unless the number
+    /// and lengths of the tensors all match the Checkpoint.
+    pub fn restore(
+        &self,
+        weights: &mut [&mut [f32]],
+        state: &mut [&mut [f32]],
+    ) -> Result<u64, BMLSError> {
+        check_shapes("Weights", &self.weights, weights)?;
+        check_shapes("State", &self.state, state)?;
+
+        for (src, dst) in self.weights.iter().zip(weights.iter_mut()) {
+            dst.copy_from_slice(src);
+        }
+
+        for (src, dst) in self.state.iter().zip(state.iter_mut()) {
+            dst.copy_from_slice(src);
+        }
+
+        Ok(self.step)
+    }
+
+    /// Writes the Checkpoint in the format described above.
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), BMLSError> {
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&self.step.to_le_bytes());
+        write_tensors(&mut payload, &self.weights);
+        write_tensors(&mut payload, &self.state);
+
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&CHECKPOINT_VERSION.to_le_bytes())?;
+        writer.write_all(&(payload.len() as u64).to_le_bytes())?;
+        writer.write_all(&payload)?;
+        writer.write_all(&crc32(&payload).to_le_bytes())?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Reads a Checkpoint, validating the version and checksum.
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, BMLSError> {
+        let mut header = [0; 16];
+        read_exact(&mut reader, &mut header, "header is truncated")?;
+
+        if header[0..4] != MAGIC {
+            return error::checkpoint_corrupt("missing magic bytes")
+        }
+
+        let version = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        if version != CHECKPOINT_VERSION {
+            return error::checkpoint_version(version)
+        }
+
+        let len = u64::from_le_bytes(header[8..16].try_into().unwrap());
+
+        // read through `take` so a corrupt length cannot allocate the world.
+        let mut payload = Vec::new();
+        (&mut reader).take(len).read_to_end(&mut payload)?;
+        if payload.len() as u64 != len {
+            return error::checkpoint_corrupt("payload is truncated")
+        }
+
+        let mut checksum = [0; 4];
+        read_exact(&mut reader, &mut checksum, "checksum is truncated")?;
+        let expected = u32::from_le_bytes(checksum);
+        let found = crc32(&payload);
+        if expected != found {
+            return error::checkpoint_checksum(expected, found)
+        }
+
+        let mut cursor = Cursor { bytes: &payload, pos: 0 };
+        let step = cursor.u64()?;
+        let weights = cursor.tensors()?;
+        let state = cursor.tensors()?;
+
+        if cursor.pos != payload.len() {
+            return error::checkpoint_corrupt("trailing bytes in payload")
+        }
+
+        Ok(Self { step, weights, state })
+    }
+
+    /// Writes the Checkpoint to a file at Path.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), BMLSError> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+
+    /// Reads a Checkpoint from a file at Path.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, BMLSError> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+}
+
+/// Fills Buf, reporting a stream that ends early as corrupt
+/// rather than as an IO error.
+fn read_exact<R: Read>(reader: &mut R, buf: &mut [u8], reason: &str) -> Result<(), BMLSError> {
+    match reader.read_exact(buf) {
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => error::checkpoint_corrupt(reason),
+        r => Ok(r?),
+    }
+}
+
+fn check_shapes(
+    name: &str,
+    src: &[Vec<f32>],
+    dst: &[&mut [f32]],
+) -> Result<(), BMLSError> {
+    if src.len() != dst.len() {
+        return error::checkpoint_mismatch(name, src.len(), dst.len())
+    }
+
+    for (src, dst) in src.iter().zip(dst) {
+        if src.len() != dst.len() {
+            return error::checkpoint_mismatch(name, src.len(), dst.len())
+        }
+    }
+
+    Ok(())
+}
+
+fn write_tensors(out: &mut Vec<u8>, tensors: &[Vec<f32>]) {
+    out.extend_from_slice(&(tensors.len() as u64).to_le_bytes());
+    for tensor in tensors {
+        out.extend_from_slice(&(tensor.len() as u64).to_le_bytes());
+        for x in tensor {
+            out.extend_from_slice(&x.to_le_bytes());
+        }
+    }
+}
+
+/// Reads values out of a validated payload.
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], BMLSError> {
+        match self.pos.checked_add(n) {
+            Some(end) if end <= self.bytes.len() => {
+                let bytes = &self.bytes[self.pos..end];
+                self.pos = end;
+                Ok(bytes)
+            }
+            _ => error::checkpoint_corrupt("payload ends early"),
+        }
+    }
+
+    fn u64(&mut self) -> Result<u64, BMLSError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn tensors(&mut self) -> Result<Vec<Vec<f32>>, BMLSError> {
+        let count = self.u64()? as usize;
+
+        // every tensor needs at least its length.
+        if count > (self.bytes.len() - self.pos) / 8 {
+            return error::checkpoint_corrupt("tensor count is too large")
+        }
+
+        let mut tensors = Vec::with_capacity(count);
+        for _ in 0..count {
+            let len = self.u64()? as usize;
+            let bytes = self.take(len.saturating_mul(4))?;
+            tensors.push(
+                bytes.chunks_exact(4)
+                    .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+                    .collect()
+            );
+        }
+
+        Ok(tensors)
+    }
+}
+
+/// CRC-32 (IEEE) lookup table.
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xEDB88320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for b in bytes {
+        crc = CRC_TABLE[((crc ^ *b as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn sample() -> Checkpoint {
+        Checkpoint::new(
+            42,
+            &[&[1.0, -2.5, f32::MIN_POSITIVE], &[]],
+            &[&[0.1, 1e-30, -0.0]],
+        )
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let ckpt = sample();
+        let mut bytes = Vec::new();
+        ckpt.write(&mut bytes).unwrap();
+
+        let read = Checkpoint::read(&bytes[..]).unwrap();
+        assert_eq!(read, ckpt);
+
+        // compare bits as well, -0.0 == 0.0
+        for (a, b) in read.state[0].iter().zip(&ckpt.state[0]) {
+            assert_eq!(a.to_bits(), b.to_bits());
+        }
+    }
+
+    #[test]
+    fn test_corrupt() {
+        let mut bytes = Vec::new();
+        sample().write(&mut bytes).unwrap();
+
+        let mut flipped = bytes.clone();
+        flipped[30] ^= 1;
+        assert!(matches!(Checkpoint::read(&flipped[..]), Err(BMLSError::CheckpointChecksum(..))));
+
+        let mut version = bytes.clone();
+        version[4] = 9;
+        assert!(matches!(Checkpoint::read(&version[..]), Err(BMLSError::CheckpointVersion(9, _))));
+
+        let mut magic = bytes.clone();
+        magic[0] = b'X';
+        assert!(matches!(Checkpoint::read(&magic[..]), Err(BMLSError::CheckpointCorrupt(_))));
+
+        assert!(matches!(Checkpoint::read(&bytes[..20]), Err(BMLSError::CheckpointCorrupt(_))));
+
+        // a header or checksum cut short is corrupt, not an IO error
+        assert!(matches!(Checkpoint::read(&bytes[..10]), Err(BMLSError::CheckpointCorrupt(_))));
+        assert!(matches!(Checkpoint::read(&bytes[..0]), Err(BMLSError::CheckpointCorrupt(_))));
+        assert!(matches!(Checkpoint::read(&bytes[..bytes.len() - 2]), Err(BMLSError::CheckpointCorrupt(_))));
+    }
+
+    #[test]
+    fn test_restore_mismatch() {
+        let ckpt = sample();
+        let mut w1 = vec![0.0; 3];
+        let mut w2 = vec![0.0; 1];
+        let mut s1 = vec![0.0; 3];
+
+        let res = ckpt.restore(&mut [&mut w1, &mut w2], &mut [&mut s1]);
+        assert!(matches!(res, Err(BMLSError::CheckpointMismatch(..))));
+        assert_eq!(w1, [0.0; 3]);
+
+        let mut w2 = vec![];
+        let step = ckpt.restore(&mut [&mut w1, &mut w2], &mut [&mut s1]).unwrap();
+        assert_eq!(step, 42);
+        assert_eq!(w1, ckpt.weights[0]);
+        assert_eq!(s1, ckpt.state[0]);
+    }
+}
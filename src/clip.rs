@@ -0,0 +1,116 @@
+use crate::error::BMLSError;
+use crate::error;
+
+/// # Clip By Value
+/// - G: Gradient to clip in place
+/// - Min: Lower bound
+/// - Max: Upper bound
+#[inline]
+pub fn clip_by_value(
+    g: &mut [f32],
+    min: f32,
+    max: f32,
+) -> Result<(), BMLSError> {
+    if min > max || min.is_nan() || max.is_nan() {
+        return error::invalid_clip_range(min, max)
+    }
+
+    for g in g.iter_mut() {
+        *g = g.clamp(min, max);
+    }
+
+    Ok(())
+}
+
+/// # Clip By Norm
+/// - G: Gradient to clip in place
+/// - Max_Norm: Maximum L2 norm of G
+/// 
+/// If the L2 norm of G exceeds Max_Norm, G is scaled down so its
+/// norm is Max_Norm. Returns the norm of G before clipping. 
+#[inline]
+pub fn clip_by_norm(
+    g: &mut [f32],
+    max_norm: f32,
+) -> Result<f32, BMLSError> {
+    if max_norm <= 0.0 || max_norm.is_nan() {
+        return error::invalid_clip_norm(max_norm)
+    }
+
+    let norm = f32::sqrt(g.iter().map(|g| g * g).sum::<f32>());
+
+    if norm > max_norm {
+        let scale = max_norm / norm;
+        for g in g.iter_mut() {
+            *g *= scale;
+        }
+    }
+
+    Ok(norm)
+}
+
+/// # Clip By Global Norm
+/// - G: Gradient tensors to clip in place
+/// - Max_Norm: Maximum L2 norm of all of G together
+/// 
+/// The norm is taken over every element of every tensor in G. If it 
+/// exceeds Max_Norm, all tensors are scaled by the same factor so the 
+/// global norm is Max_Norm. Returns the global norm before clipping, 
+/// which may be infinite or NaN if the gradients overflowed.
+#[inline]
+pub fn clip_by_global_norm(
+    g: &mut [&mut [f32]],
+    max_norm: f32,
+) -> Result<f32, BMLSError> {
+    if max_norm <= 0.0 || max_norm.is_nan() {
+        return error::invalid_clip_norm(max_norm)
+    }
+
+    let norm = f32::sqrt(
+        g.iter()
+            .map(|g| g.iter().map(|g| g * g).sum::<f32>())
+            .sum::<f32>()
+    );
+
+    if norm > max_norm {
+        let scale = max_norm / norm;
+        for g in g.iter_mut() {
+            for g in g.iter_mut() {
+                *g *= scale;
+            }
+        }
+    }
+
+    Ok(norm)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_clip_by_value() {
+        let mut g = [-3.0, 0.5, 2.0];
+        clip_by_value(&mut g, -1.0, 1.0).unwrap();
+        assert_eq!(g, [-1.0, 0.5, 1.0]);
+        assert!(clip_by_value(&mut g, 1.0, -1.0).is_err());
+    }
+
+    #[test]
+    fn test_clip_by_global_norm() {
+        let mut g1 = vec![3.0, 0.0];
+        let mut g2 = vec![0.0, 4.0];
+
+        let norm = clip_by_global_norm(&mut [&mut g1, &mut g2], 1.0).unwrap();
+
+        assert_eq!(norm, 5.0);
+        assert!((g1[0] - 0.6).abs() < 1e-6);
+        assert!((g2[1] - 0.8).abs() < 1e-6);
+
+        // already within the norm, nothing changes
+        let norm = clip_by_norm(&mut g1, 1.0).unwrap();
+        assert!((norm - 0.6).abs() < 1e-6);
+        assert!((g1[0] - 0.6).abs() < 1e-6);
+    }
+}
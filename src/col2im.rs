@@ -1,6 +1,7 @@
 
 use crate::error::BMLSError;
 use crate::error;
+use crate::im2col::im2col_wrt_x;
 
 /// # Col2Im 
 /// 
@@ -48,7 +49,7 @@ pub fn col2im(
             // the index within HxW is wrapped by the width of a batch
             let i = col % (yh * yw);
 
-            let h = i / yh;
+            let h = i / yw;
             let w = i % yw;
 
             y[n * yc * yh * yw + row * yh * yw + h * yw + w] = x[row * xcols + col];
@@ -97,7 +98,7 @@ pub fn col2im_wrt_x(
             // the index within HxW is wrapped by the width of a batch
             let i = col % (yh * yw);
 
-            let h = i / yh;
+            let h = i / yw;
             let w = i % yw;
 
             gx[row * xcols + col] = gy[n * yc * yh * yw + row * yh * yw + h * yw + w];
@@ -105,4 +106,113 @@ pub fn col2im_wrt_x(
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// # Fold
+/// - X: Columns, in the layout of the output of `im2col`
+/// - Y: Image (N x C x H x W)
+/// - Y_dim: Dimensions of Y
+/// - F_dim: Dimensions of Filter F
+/// - Stride: h, w, strides of the filter
+/// - Padh: height padding
+/// - Padw: width padding
+/// - Dilation: h, w, spacing between the elements of the filter, 1 for none
+///
+/// X Height: fc * fh * fw
+///
+/// X Width: (((yh - (dh * (fh - 1) + 1) + (padh.0 + padh.1)) / strideh) + 1) * (((yw - (dw * (fw - 1) + 1) + (padw.0 + padw.1)) / stridew) + 1) * yn
+///
+/// Each column is a patch that is added back to where `im2col` took it from, so
+/// overlapping patches are summed and padding is dropped. This is the adjoint of `im2col`:
+/// Y is zeroed and then summed into by `im2col_wrt_x`.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn fold(
+    x: &[f32],
+    y: &mut [f32],
+    y_dim: [usize; 4],
+    f_dim: [usize; 4],
+    stride: [usize; 2],
+    padh: [usize; 2],
+    padw: [usize; 2],
+    dilation: [usize; 2],
+) -> Result<(), BMLSError> {
+    let ylen = y_dim[0] * y_dim[1] * y_dim[2] * y_dim[3];
+    if y.len() != ylen {
+        return error::length_mismatch("Y", y.len(), "Y_dim", ylen)
+    }
+
+    y.fill(0.0);
+    im2col_wrt_x(x, y, y_dim, f_dim, stride, padh, padw, dilation)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::im2col::im2col;
+
+    #[test]
+    fn test_col2im_non_square() {
+        // N = 2, C = 2, H = 2, W = 3, so the row within HxW is i / W, not i / H
+        let (n, c, h, w) = (2, 2, 2, 3);
+        let x: Vec<f32> = (0..24).map(|i| i as f32).collect();
+        let mut y = vec![0.0; 24];
+        col2im(&x, &mut y, [c, n * h * w], [n, c, h, w]).unwrap();
+
+        for ni in 0..n {
+            for ci in 0..c {
+                for hi in 0..h {
+                    for wi in 0..w {
+                        let col = (ni * h + hi) * w + wi;
+                        assert_eq!(y[((ni * c + ci) * h + hi) * w + wi], x[ci * n * h * w + col]);
+                    }
+                }
+            }
+        }
+
+        // the gradient moves every value of GY back to where it came from in X
+        let mut gx = vec![0.0; 24];
+        col2im_wrt_x(&y, &mut gx, [c, n * h * w], [n, c, h, w]).unwrap();
+        assert_eq!(gx, x);
+    }
+
+    #[test]
+    fn test_fold_adjoint() {
+        let x_dim = [2, 3, 5, 6];
+        let f_dim = [1, 3, 3, 2];
+        let (stride, padh, padw, dilation) = ([2, 1], [1, 2], [0, 1], [1, 2]);
+        // rows: 3 * 3 * 2, cols: ((5 + 3 - 3) / 2 + 1) * ((6 + 1 - 3) / 1 + 1) * 2
+        let (rows, cols) = (18, 3 * 5 * 2);
+
+        let x: Vec<f32> = (0..180).map(|i| f32::sin(i as f32 * 0.7)).collect();
+        let y: Vec<f32> = (0..rows * cols).map(|i| f32::cos(i as f32 * 1.3)).collect();
+
+        let mut cx = vec![0.0; rows * cols];
+        let mut fy = vec![0.0; 180];
+        im2col(&x, &mut cx, x_dim, f_dim, stride, padh, padw, dilation).unwrap();
+        fold(&y, &mut fy, x_dim, f_dim, stride, padh, padw, dilation).unwrap();
+
+        // <im2col(x), y> == <x, fold(y)>
+        let a = cx.iter().zip(&y).map(|(a, b)| a * b).sum::<f32>();
+        let b = x.iter().zip(&fy).map(|(a, b)| a * b).sum::<f32>();
+        assert!((a - b).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_fold_overlap() {
+        // every pixel of a 3x3 image is summed once for each 2x2 patch it is in,
+        // whatever Y held before
+        let mut y = [5.0; 9];
+        fold(&[1.0; 16], &mut y, [1, 1, 3, 3], [1, 1, 2, 2], [1, 1], [0, 0], [0, 0], [1, 1]).unwrap();
+        assert_eq!(y, [1.0, 2.0, 1.0, 2.0, 4.0, 2.0, 1.0, 2.0, 1.0]);
+
+        // with a 1x1 filter, folding the columns gives back the batch
+        let x: Vec<f32> = (0..16).map(|i| i as f32).collect();
+        let mut cols = [0.0; 16];
+        im2col(&x, &mut cols, [2, 2, 2, 2], [1, 2, 1, 1], [1, 1], [0, 0], [0, 0], [1, 1]).unwrap();
+        let mut back = [0.0; 16];
+        fold(&cols, &mut back, [2, 2, 2, 2], [1, 2, 1, 1], [1, 1], [0, 0], [0, 0], [1, 1]).unwrap();
+        assert_eq!(back.to_vec(), x);
+    }
+}
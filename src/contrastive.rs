@@ -0,0 +1,121 @@
+use itertools::izip;
+use crate::error::BMLSError;
+use crate::error;
+use crate::reduction::{Reduction, reduce_rows_multi};
+
+/// # Contrastive Loss
+/// - X1: First Embeddings (N x D)
+/// - X2: Second Embeddings (N x D)
+/// - T: 1 for similar pairs, 0 for dissimilar pairs (N)
+/// - E: Error, (N x 1) vector for `Reduction::None`, otherwise (1)
+/// - G1: Gradient of E w.r.t. X1 (N x D)
+/// - G2: Gradient of E w.r.t. X2 (N x D)
+/// - Dim: [N, D]
+/// - Margin: Distance beyond which dissimilar pairs have no error
+/// - Sample_Weights: Optional weight for each row (N)
+/// - Reduction: How the rows are combined into E
+///
+/// With d the euclidean distance between the pair: \
+/// e = t * d^2 + (1 - t) * max(0, margin - d)^2
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn contrastive(
+    x1: &[f32],
+    x2: &[f32],
+    t: &[f32],
+    e: &mut [f32],
+    g1: &mut [f32],
+    g2: &mut [f32],
+    dim: [usize; 2],
+    margin: f32,
+    sample_weights: Option<&[f32]>,
+    reduction: Reduction,
+) -> Result<(), BMLSError> {
+    let len = dim[0] * dim[1];
+    if x1.len() != len {
+        return error::length_mismatch("X1", x1.len(), "Dim", len)
+    }
+
+    if x2.len() != len {
+        return error::length_mismatch("X2", x2.len(), "Dim", len)
+    }
+
+    if t.len() != dim[0] {
+        return error::length_mismatch("T", t.len(), "Dim[0]", dim[0])
+    }
+
+    if g1.len() != len {
+        return error::length_mismatch("G1", g1.len(), "Dim", len)
+    }
+
+    if g2.len() != len {
+        return error::length_mismatch("G2", g2.len(), "Dim", len)
+    }
+
+    let cols = dim[1];
+
+    reduce_rows_multi(e, &mut [g1, g2], dim[0], sample_weights, reduction, |i, g| {
+        let a = &x1[i * cols..(i + 1) * cols];
+        let b = &x2[i * cols..(i + 1) * cols];
+        let t = t[i];
+
+        let d = izip!(a, b).map(|(a, b)| (a - b) * (a - b)).sum::<f32>().sqrt();
+        let gap = f32::max(margin - d, 0.0);
+        let loss = t * d * d + (1. - t) * gap * gap;
+
+        // d(loss) / d(a - b)
+        let scale = if d > 0.0 {
+            2. * t - 2. * (1. - t) * gap / d
+        } else {
+            2. * t
+        };
+
+        for (a, b, g) in izip!(a, b, g[0].iter_mut()) {
+            *g = scale * (a - b);
+        }
+
+        for (a, b, g) in izip!(a, b, g[1].iter_mut()) {
+            *g = scale * (b - a);
+        }
+
+        Some(loss)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_contrastive_gradient() {
+        let dim = [3, 2];
+        let x1 = [1.0, 0.5, -0.2, 0.3, 0.8, 0.1];
+        let x2 = [0.4, -1.0, 0.7, 0.5, 3.0, -3.0];
+        let t = [1.0, 0.0, 0.0];
+
+        let mut e = [0.0; 3];
+        let mut g1 = [0.0; 6];
+        let mut g2 = [0.0; 6];
+        contrastive(&x1, &x2, &t, &mut e, &mut g1, &mut g2, dim, 1.0, None, Reduction::None).unwrap();
+
+        // the last pair is dissimilar and beyond the margin
+        assert_eq!(e[2], 0.0);
+
+        let f = |x1: &[f32], x2: &[f32]| {
+            let mut e = [0.0];
+            contrastive(x1, x2, &t, &mut e, &mut [0.0; 6], &mut [0.0; 6], dim, 1.0, None, Reduction::Sum).unwrap();
+            e[0]
+        };
+
+        for i in 0..6 {
+            let (mut ap, mut am, mut bp, mut bm) = (x1, x1, x2, x2);
+            ap[i] += 1e-2;
+            am[i] -= 1e-2;
+            bp[i] += 1e-2;
+            bm[i] -= 1e-2;
+            assert!(((f(&ap, &x2) - f(&am, &x2)) / 2e-2 - g1[i]).abs() < 1e-3);
+            assert!(((f(&x1, &bp) - f(&x1, &bm)) / 2e-2 - g2[i]).abs() < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,594 @@
+use rayon::prelude::*;
+use crate::error::BMLSError;
+use crate::error;
+use crate::im2col::{im2col, im2col_wrt_x};
+use crate::matmul::{matmul, matmul_wrt_a, matmul_wrt_b};
+
+/// # 2D Convolution
+/// - X: Input (N x C x H x W)
+/// - W: Filters (O x C / Groups x FH x FW)
+/// - B: Bias (O)
+/// - Y: Output (N x O x YH x YW)
+/// - X_dim: Dimensions of X
+/// - W_dim: Dimensions of W
+/// - Stride: h, w, strides of the filter
+/// - Padh: height padding
+/// - Padw: width padding
+/// - Dilation: h, w, spacing between the elements of the filter, 1 for none
+/// - Groups: Number of groups the channels are split into, 1 for a full convolution.
+///   Must divide C and O.
+///
+/// YH: ((xh - (dh * (fh - 1) + 1) + (padh.0 + padh.1)) / strideh) + 1
+///
+/// YW: ((xw - (dw * (fw - 1) + 1) + (padw.0 + padw.1)) / stridew) + 1
+///
+/// The output channels of each group only see the input channels of the same group,
+/// so Groups == C == O is a depthwise convolution.
+///
+/// Each sample is converted with `im2col` and multiplied by W, in parallel.
+/// With groups, only one group's columns are held at a time.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn conv2d(
+    x: &[f32],
+    w: &[f32],
+    b: &[f32],
+    y: &mut [f32],
+    x_dim: [usize; 4],
+    w_dim: [usize; 4],
+    stride: [usize; 2],
+    padh: [usize; 2],
+    padw: [usize; 2],
+    dilation: [usize; 2],
+    groups: usize,
+) -> Result<(), BMLSError> {
+    let shape = Shape::new(x_dim, w_dim, stride, padh, padw, dilation, groups)?;
+
+    if x.len() != shape.x_len() {
+        return error::length_mismatch("X", x.len(), "X_dim", shape.x_len())
+    }
+
+    if w.len() != shape.w_len() {
+        return error::length_mismatch("W", w.len(), "W_dim", shape.w_len())
+    }
+
+    if b.len() != shape.o {
+        return error::length_mismatch("B", b.len(), "W_dim[0]", shape.o)
+    }
+
+    if y.len() != shape.y_len() {
+        return error::length_mismatch("Y", y.len(), "Y_dim", shape.y_len())
+    }
+
+    y.par_chunks_mut(shape.o * shape.p)
+        .zip(x.par_chunks(shape.c * shape.hx * shape.wx))
+        .try_for_each(|(y, x)| {
+            let mut col = vec![0.0; shape.k * shape.p];
+            let groups = y.chunks_mut(shape.y_group())
+                .zip(x.chunks(shape.x_group()))
+                .zip(w.chunks(shape.w_group()));
+
+            for ((y, x), w) in groups {
+                im2col(x, &mut col, shape.group_x_dim(), shape.group_w_dim(), stride, padh, padw, dilation)?;
+                matmul(w, &col, y, [shape.og(), shape.k], [shape.k, shape.p])?;
+            }
+
+            for (y, b) in y.chunks_mut(shape.p).zip(b) {
+                for y in y {
+                    *y += b;
+                }
+            }
+
+            Ok(())
+        })
+}
+
+/// # 2D Convolution w.r.t. X
+/// - W: Filters (O x C / Groups x FH x FW)
+/// - GY: Gradient w.r.t. Y (N x O x YH x YW)
+/// - GX: Gradient w.r.t. X (N x C x H x W)
+/// - X_dim: Dimensions of X
+/// - W_dim: Dimensions of W
+/// - Stride: h, w, strides of the filter
+/// - Padh: height padding
+/// - Padw: width padding
+/// - Dilation: h, w, spacing between the elements of the filter
+/// - Groups: Number of groups the channels are split into
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn conv2d_wrt_x(
+    w: &[f32],
+    gy: &[f32],
+    gx: &mut [f32],
+    x_dim: [usize; 4],
+    w_dim: [usize; 4],
+    stride: [usize; 2],
+    padh: [usize; 2],
+    padw: [usize; 2],
+    dilation: [usize; 2],
+    groups: usize,
+) -> Result<(), BMLSError> {
+    let shape = Shape::new(x_dim, w_dim, stride, padh, padw, dilation, groups)?;
+
+    if w.len() != shape.w_len() {
+        return error::length_mismatch("W", w.len(), "W_dim", shape.w_len())
+    }
+
+    if gy.len() != shape.y_len() {
+        return error::length_mismatch("GY", gy.len(), "Y_dim", shape.y_len())
+    }
+
+    if gx.len() != shape.x_len() {
+        return error::length_mismatch("GX", gx.len(), "X_dim", shape.x_len())
+    }
+
+    gx.par_chunks_mut(shape.c * shape.hx * shape.wx)
+        .zip(gy.par_chunks(shape.o * shape.p))
+        .try_for_each(|(gx, gy)| {
+            let mut gcol = vec![0.0; shape.k * shape.p];
+            let groups = gy.chunks(shape.y_group())
+                .zip(gx.chunks_mut(shape.x_group()))
+                .zip(w.chunks(shape.w_group()));
+
+            for ((gy, gx), w) in groups {
+                // the gradient w.r.t. the columns, W^T * GY
+                gcol.fill(0.0);
+                matmul_wrt_b(w, gy, &mut gcol, [shape.og(), shape.k], [shape.k, shape.p])?;
+                im2col_wrt_x(&gcol, gx, shape.group_x_dim(), shape.group_w_dim(), stride, padh, padw, dilation)?;
+            }
+
+            Ok(())
+        })
+}
+
+/// # 2D Convolution w.r.t. W
+/// - X: Input (N x C x H x W)
+/// - GY: Gradient w.r.t. Y (N x O x YH x YW)
+/// - GW: Gradient w.r.t. W (O x C / Groups x FH x FW)
+/// - X_dim: Dimensions of X
+/// - W_dim: Dimensions of W
+/// - Stride: h, w, strides of the filter
+/// - Padh: height padding
+/// - Padw: width padding
+/// - Dilation: h, w, spacing between the elements of the filter
+/// - Groups: Number of groups the channels are split into
+///
+/// Each thread sums the gradients of its samples, and the sums
+/// are added into GW.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn conv2d_wrt_w(
+    x: &[f32],
+    gy: &[f32],
+    gw: &mut [f32],
+    x_dim: [usize; 4],
+    w_dim: [usize; 4],
+    stride: [usize; 2],
+    padh: [usize; 2],
+    padw: [usize; 2],
+    dilation: [usize; 2],
+    groups: usize,
+) -> Result<(), BMLSError> {
+    let shape = Shape::new(x_dim, w_dim, stride, padh, padw, dilation, groups)?;
+
+    if x.len() != shape.x_len() {
+        return error::length_mismatch("X", x.len(), "X_dim", shape.x_len())
+    }
+
+    if gy.len() != shape.y_len() {
+        return error::length_mismatch("GY", gy.len(), "Y_dim", shape.y_len())
+    }
+
+    if gw.len() != shape.w_len() {
+        return error::length_mismatch("GW", gw.len(), "W_dim", shape.w_len())
+    }
+
+    let sum = x.par_chunks(shape.c * shape.hx * shape.wx)
+        .zip(gy.par_chunks(shape.o * shape.p))
+        .try_fold(
+            || (vec![0.0; shape.w_len()], vec![0.0; shape.k * shape.p]),
+            |(mut gw, mut col), (x, gy)| {
+                let groups = gy.chunks(shape.y_group())
+                    .zip(x.chunks(shape.x_group()))
+                    .zip(gw.chunks_mut(shape.w_group()));
+
+                for ((gy, x), gw) in groups {
+                    // GY * col^T
+                    im2col(x, &mut col, shape.group_x_dim(), shape.group_w_dim(), stride, padh, padw, dilation)?;
+                    matmul_wrt_a(gy, &col, gw, [shape.og(), shape.k], [shape.k, shape.p])?;
+                }
+                Ok::<_, BMLSError>((gw, col))
+            },
+        )
+        .map(|acc| acc.map(|(gw, _)| gw))
+        .try_reduce(
+            || vec![0.0; shape.w_len()],
+            |mut a, b| {
+                for (a, b) in a.iter_mut().zip(b) {
+                    *a += b;
+                }
+                Ok(a)
+            },
+        )?;
+
+    for (gw, s) in gw.iter_mut().zip(sum) {
+        *gw += s;
+    }
+
+    Ok(())
+}
+
+/// # 2D Convolution w.r.t. B
+/// - GY: Gradient w.r.t. Y (N x O x YH x YW)
+/// - GB: Gradient w.r.t. B (O)
+/// - Y_dim: Dimensions of Y
+#[inline]
+pub fn conv2d_wrt_b(
+    gy: &[f32],
+    gb: &mut [f32],
+    y_dim: [usize; 4],
+) -> Result<(), BMLSError> {
+    let (ny, cy, hy, wy) = (y_dim[0], y_dim[1], y_dim[2], y_dim[3]);
+
+    let len = ny*cy*hy*wy;
+    if gy.len() != len {
+        return error::length_mismatch("GY", gy.len(), "Y_dim", len)
+    }
+
+    if gb.len() != cy {
+        return error::length_mismatch("GB", gb.len(), "Y_dim[1]", cy)
+    }
+
+    let plane = hy * wy;
+
+    gb.par_iter_mut().enumerate().for_each(|(o, gb)| {
+        for n in 0..ny {
+            *gb += gy[(n * cy + o) * plane..(n * cy + o + 1) * plane].iter().sum::<f32>();
+        }
+    });
+
+    Ok(())
+}
+
+/// Dimensions of a convolution.
+#[derive(Copy, Clone)]
+struct Shape {
+    n: usize,
+    c: usize,
+    hx: usize,
+    wx: usize,
+    o: usize,
+    hf: usize,
+    wf: usize,
+    groups: usize,
+    /// rows of the column matrix of a group, C / Groups * FH * FW
+    k: usize,
+    /// columns of the column matrix, YH * YW
+    p: usize,
+}
+
+impl Shape {
+    fn new(
+        x_dim: [usize; 4],
+        w_dim: [usize; 4],
+        stride: [usize; 2],
+        padh: [usize; 2],
+        padw: [usize; 2],
+        dilation: [usize; 2],
+        groups: usize,
+    ) -> Result<Self, BMLSError> {
+        let (n, c, hx, wx) = (x_dim[0], x_dim[1], x_dim[2], x_dim[3]);
+        let (o, cf, hf, wf) = (w_dim[0], w_dim[1], w_dim[2], w_dim[3]);
+
+        if groups == 0 || c / groups * groups != c {
+            return error::invalid_groups(groups, c)
+        }
+
+        if o / groups * groups != o {
+            return error::invalid_groups(groups, o)
+        }
+
+        // the extent of the dilated filter
+        let hk = dilation[0] * hf.saturating_sub(1) + 1;
+        let wk = dilation[1] * wf.saturating_sub(1) + 1;
+
+        // im2col validates the same, but the output
+        // size must be computed without underflow first.
+        if hf == 0 || hk > hx + padh[0] + padh[1] ||
+           wf == 0 || wk > wx + padw[0] + padw[1] ||
+           cf != c / groups || o == 0
+        {
+            return Err(BMLSError::InvalidKernelDim(o, cf, hf, wf))
+        }
+
+        if stride[0] == 0 || stride[1] == 0 {
+            return Err(BMLSError::InvalidStrides(stride[0], stride[1]))
+        }
+
+        if dilation[0] == 0 || dilation[1] == 0 {
+            return Err(BMLSError::InvalidDilation(dilation[0], dilation[1]))
+        }
+
+        let hy = (hx + padh[0] + padh[1] - hk) / stride[0] + 1;
+        let wy = (wx + padw[0] + padw[1] - wk) / stride[1] + 1;
+
+        Ok(Self { n, c, hx, wx, o, hf, wf, groups, k: cf * hf * wf, p: hy * wy })
+    }
+
+    /// output channels of a group
+    fn og(&self) -> usize {
+        self.o / self.groups
+    }
+
+    /// dimensions of one group of one sample of X
+    fn group_x_dim(&self) -> [usize; 4] {
+        [1, self.c / self.groups, self.hx, self.wx]
+    }
+
+    /// dimensions of the filters of one group
+    fn group_w_dim(&self) -> [usize; 4] {
+        [self.og(), self.c / self.groups, self.hf, self.wf]
+    }
+
+    /// length of one group of one sample of X
+    fn x_group(&self) -> usize {
+        self.c / self.groups * self.hx * self.wx
+    }
+
+    /// length of the filters of one group
+    fn w_group(&self) -> usize {
+        self.og() * self.k
+    }
+
+    /// length of one group of one sample of Y
+    fn y_group(&self) -> usize {
+        self.og() * self.p
+    }
+
+    fn x_len(&self) -> usize {
+        self.n * self.c * self.hx * self.wx
+    }
+
+    fn w_len(&self) -> usize {
+        self.o * self.k
+    }
+
+    fn y_len(&self) -> usize {
+        self.n * self.o * self.p
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const X_DIM: [usize; 4] = [2, 2, 5, 6];
+    const W_DIM: [usize; 4] = [3, 2, 3, 2];
+    const STRIDE: [usize; 2] = [2, 1];
+    const PADH: [usize; 2] = [1, 1];
+    const PADW: [usize; 2] = [0, 1];
+    const DILATION: [usize; 2] = [2, 1];
+    // (5 + 2 - 5) / 2 + 1, (6 + 1 - 2) / 1 + 1
+    const Y_DIM: [usize; 4] = [2, 3, 2, 6];
+
+    fn values(len: usize, seed: f32) -> Vec<f32> {
+        (0..len).map(|i| f32::sin(i as f32 * seed)).collect()
+    }
+
+    // direct convolution to compare against
+    fn naive(x: &[f32], w: &[f32], b: &[f32]) -> Vec<f32> {
+        let [n, c, h, wd] = X_DIM;
+        let [o, _, fh, fw] = W_DIM;
+        let [_, _, yh, yw] = Y_DIM;
+        let mut y = vec![0.0; n * o * yh * yw];
+
+        for n in 0..n {
+            for o in 0..o {
+                for i in 0..yh {
+                    for j in 0..yw {
+                        let mut sum = b[o];
+                        for c in 0..c {
+                            for kh in 0..fh {
+                                for kw in 0..fw {
+                                    let xh = (i * STRIDE[0] + kh * DILATION[0]) as isize - PADH[0] as isize;
+                                    let xw = (j * STRIDE[1] + kw * DILATION[1]) as isize - PADW[0] as isize;
+                                    if xh < 0 || xw < 0 || xh >= h as isize || xw >= wd as isize {
+                                        continue;
+                                    }
+                                    let xi = ((n * X_DIM[1] + c) * h + xh as usize) * wd + xw as usize;
+                                    let wi = ((o * X_DIM[1] + c) * fh + kh) * fw + kw;
+                                    sum += x[xi] * w[wi];
+                                }
+                            }
+                        }
+                        y[((n * W_DIM[0] + o) * yh + i) * yw + j] = sum;
+                    }
+                }
+            }
+        }
+
+        y
+    }
+
+    #[test]
+    fn test_conv2d() {
+        let x = values(120, 0.7);
+        let w = values(36, 1.3);
+        let b = [0.1, -0.2, 0.3];
+        let mut y = vec![0.0; 72];
+
+        conv2d(&x, &w, &b, &mut y, X_DIM, W_DIM, STRIDE, PADH, PADW, DILATION, 1).unwrap();
+
+        for (y, e) in y.iter().zip(naive(&x, &w, &b)) {
+            assert!((y - e).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_conv2d_gradients() {
+        let x = values(120, 0.7);
+        let w = values(36, 1.3);
+        let gy = values(72, 0.9);
+
+        let mut gx = vec![0.0; 120];
+        let mut gw = vec![0.0; 36];
+        let mut gb = vec![0.0; 3];
+        conv2d_wrt_x(&w, &gy, &mut gx, X_DIM, W_DIM, STRIDE, PADH, PADW, DILATION, 1).unwrap();
+        conv2d_wrt_w(&x, &gy, &mut gw, X_DIM, W_DIM, STRIDE, PADH, PADW, DILATION, 1).unwrap();
+        conv2d_wrt_b(&gy, &mut gb, Y_DIM).unwrap();
+
+        // the convolution is linear in each input, so
+        // <gy, conv(e_i)> is exactly the gradient for element i.
+        let dot = |x: &[f32], w: &[f32], b: &[f32]| {
+            naive(x, w, b).iter().zip(&gy).map(|(y, g)| y * g).sum::<f32>()
+        };
+
+        for (i, gx) in gx.iter().enumerate() {
+            let mut e = vec![0.0; 120];
+            e[i] = 1.0;
+            assert!((dot(&e, &w, &[0.0; 3]) - gx).abs() < 1e-4);
+        }
+
+        for (i, gw) in gw.iter().enumerate() {
+            let mut e = vec![0.0; 36];
+            e[i] = 1.0;
+            assert!((dot(&x, &e, &[0.0; 3]) - gw).abs() < 1e-4);
+        }
+
+        for (i, gb) in gb.iter().enumerate() {
+            let mut e = [0.0; 3];
+            e[i] = 1.0;
+            assert!((dot(&x, &[0.0; 36], &e) - gb).abs() < 1e-4);
+        }
+    }
+
+    // direct grouped convolution with the same stride, padding and dilation
+    fn naive_grouped(x: &[f32], w: &[f32], b: &[f32], x_dim: [usize; 4], w_dim: [usize; 4], groups: usize) -> Vec<f32> {
+        let [n, c, h, wd] = x_dim;
+        let [o, cg, fh, fw] = w_dim;
+        let yh = (h + PADH[0] + PADH[1] - (DILATION[0] * (fh - 1) + 1)) / STRIDE[0] + 1;
+        let yw = (wd + PADW[0] + PADW[1] - (DILATION[1] * (fw - 1) + 1)) / STRIDE[1] + 1;
+        let og = o / groups;
+        let mut y = vec![0.0; n * o * yh * yw];
+
+        for n in 0..n {
+            for o in 0..o {
+                for i in 0..yh {
+                    for j in 0..yw {
+                        let mut sum = b[o];
+                        for k in 0..cg {
+                            // the input channel of the group of O
+                            let ci = o / og * cg + k;
+                            for kh in 0..fh {
+                                for kw in 0..fw {
+                                    let xh = (i * STRIDE[0] + kh * DILATION[0]) as isize - PADH[0] as isize;
+                                    let xw = (j * STRIDE[1] + kw * DILATION[1]) as isize - PADW[0] as isize;
+                                    if xh < 0 || xw < 0 || xh >= h as isize || xw >= wd as isize {
+                                        continue;
+                                    }
+                                    let xi = ((n * c + ci) * h + xh as usize) * wd + xw as usize;
+                                    let wi = ((o * cg + k) * fh + kh) * fw + kw;
+                                    sum += x[xi] * w[wi];
+                                }
+                            }
+                        }
+                        y[((n * w_dim[0] + o) * yh + i) * yw + j] = sum;
+                    }
+                }
+            }
+        }
+
+        y
+    }
+
+    // checks the output and every gradient of a grouped convolution against `naive_grouped`
+    fn check_grouped(x_dim: [usize; 4], w_dim: [usize; 4], groups: usize) {
+        let x_len = x_dim.iter().product();
+        let w_len = w_dim.iter().product();
+        let o = w_dim[0];
+        let x = values(x_len, 0.7);
+        let w = values(w_len, 1.3);
+        let b = values(o, 2.1);
+
+        let expected = naive_grouped(&x, &w, &b, x_dim, w_dim, groups);
+        let y_len = expected.len();
+        let y_dim = [x_dim[0], o, Y_DIM[2], Y_DIM[3]];
+
+        let mut y = vec![0.0; y_len];
+        conv2d(&x, &w, &b, &mut y, x_dim, w_dim, STRIDE, PADH, PADW, DILATION, groups).unwrap();
+        for (y, e) in y.iter().zip(&expected) {
+            assert!((y - e).abs() < 1e-5);
+        }
+
+        let gy = values(y_len, 0.9);
+        let mut gx = vec![0.0; x_len];
+        let mut gw = vec![0.0; w_len];
+        let mut gb = vec![0.0; o];
+        conv2d_wrt_x(&w, &gy, &mut gx, x_dim, w_dim, STRIDE, PADH, PADW, DILATION, groups).unwrap();
+        conv2d_wrt_w(&x, &gy, &mut gw, x_dim, w_dim, STRIDE, PADH, PADW, DILATION, groups).unwrap();
+        conv2d_wrt_b(&gy, &mut gb, y_dim).unwrap();
+
+        // linear in each input, so <gy, conv(e_i)> is the gradient for element i
+        let dot = |x: &[f32], w: &[f32], b: &[f32]| {
+            naive_grouped(x, w, b, x_dim, w_dim, groups).iter().zip(&gy).map(|(y, g)| y * g).sum::<f32>()
+        };
+        let zeros_b = vec![0.0; o];
+
+        for (i, gx) in gx.iter().enumerate() {
+            let mut e = vec![0.0; x_len];
+            e[i] = 1.0;
+            assert!((dot(&e, &w, &zeros_b) - gx).abs() < 1e-4);
+        }
+
+        for (i, gw) in gw.iter().enumerate() {
+            let mut e = vec![0.0; w_len];
+            e[i] = 1.0;
+            assert!((dot(&x, &e, &zeros_b) - gw).abs() < 1e-4);
+        }
+
+        for (i, gb) in gb.iter().enumerate() {
+            let mut e = vec![0.0; o];
+            e[i] = 1.0;
+            assert!((dot(&x, &vec![0.0; w_len], &e) - gb).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_conv2d_grouped() {
+        // 4 input and 6 output channels in 2 groups
+        check_grouped([2, 4, 5, 6], [6, 2, 3, 2], 2);
+    }
+
+    #[test]
+    fn test_conv2d_depthwise() {
+        // groups == C == O
+        check_grouped([2, 3, 5, 6], [3, 1, 3, 2], 3);
+        // groups == C, with 2 filters per channel
+        check_grouped([2, 3, 5, 6], [6, 1, 3, 2], 3);
+    }
+
+    #[test]
+    fn test_conv2d_invalid_groups() {
+        let x = values(240, 0.7);
+        let mut y = vec![0.0; 2 * 6 * 12];
+        let conv = |w_dim: [usize; 4], groups: usize, y: &mut [f32]| {
+            let w = vec![0.0; w_dim.iter().product()];
+            conv2d(&x, &w, &vec![0.0; w_dim[0]], y, [2, 4, 5, 6], w_dim, STRIDE, PADH, PADW, DILATION, groups)
+        };
+
+        // C = 4 is not divisible by 3
+        assert!(matches!(conv([6, 1, 3, 2], 3, &mut y), Err(BMLSError::InvalidGroups(3, 4))));
+        // O = 3 is not divisible by 2
+        assert!(matches!(conv([3, 2, 3, 2], 2, &mut y[..36]), Err(BMLSError::InvalidGroups(2, 3))));
+        // W_dim[1] must be C / groups
+        assert!(matches!(conv([6, 4, 3, 2], 2, &mut y), Err(BMLSError::InvalidKernelDim(6, 4, 3, 2))));
+        assert!(matches!(conv([6, 2, 3, 2], 0, &mut y), Err(BMLSError::InvalidGroups(0, 4))));
+
+        let mut gx = vec![0.0; 240];
+        assert!(conv2d_wrt_x(&[0.0; 72], &y, &mut gx, [2, 4, 5, 6], [6, 1, 3, 2], STRIDE, PADH, PADW, DILATION, 3).is_err());
+        let mut gw = vec![0.0; 72];
+        assert!(conv2d_wrt_w(&x, &y, &mut gw, [2, 4, 5, 6], [6, 1, 3, 2], STRIDE, PADH, PADW, DILATION, 3).is_err());
+    }
+}
+
@@ -0,0 +1,416 @@
+use rayon::prelude::*;
+use crate::error::BMLSError;
+use crate::error;
+use crate::col2im::fold;
+use crate::conv2d::{conv2d_wrt_w, conv2d_wrt_b};
+use crate::im2col::im2col;
+use crate::matmul::{matmul, matmul_wrt_b};
+
+/// # 2D Transposed Convolution
+/// - X: Input (N x C x H x W)
+/// - W: Filters (C x O / Groups x FH x FW)
+/// - B: Bias (O)
+/// - Y: Output (N x O x YH x YW)
+/// - X_dim: Dimensions of X
+/// - W_dim: Dimensions of W
+/// - Stride: h, w, strides of the filter
+/// - Padh: height padding, removed from the top and bottom of Y
+/// - Padw: width padding, removed from the left and right of Y
+/// - Output_Padding: h, w, extra rows and columns added to the bottom and right of Y.
+///   Must be smaller than the stride.
+/// - Dilation: h, w, spacing between the elements of the filter, 1 for none
+/// - Groups: Number of groups the channels are split into. Must divide C.
+///
+/// YH: (xh - 1) * strideh - (padh.0 + padh.1) + dh * (fh - 1) + 1 + output_padding.0
+///
+/// YW: (xw - 1) * stridew - (padw.0 + padw.1) + dw * (fw - 1) + 1 + output_padding.1
+///
+/// Each element of X scatters W onto Y, and overlapping patches are summed.
+/// This is the gradient of `conv2d` w.r.t. its input, so a `conv2d` with the same
+/// W and arguments maps Y back to the size of X.
+///
+/// Each sample is multiplied by W into columns, which `fold` sums onto Y, in parallel.
+/// With groups, only one group's columns are held at a time.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn conv_transpose2d(
+    x: &[f32],
+    w: &[f32],
+    b: &[f32],
+    y: &mut [f32],
+    x_dim: [usize; 4],
+    w_dim: [usize; 4],
+    stride: [usize; 2],
+    padh: [usize; 2],
+    padw: [usize; 2],
+    output_padding: [usize; 2],
+    dilation: [usize; 2],
+    groups: usize,
+) -> Result<(), BMLSError> {
+    let y_dim = output_dim(x_dim, w_dim, stride, padh, padw, output_padding, dilation, groups)?;
+
+    let xlen = x_dim.iter().product::<usize>();
+    if x.len() != xlen {
+        return error::length_mismatch("X", x.len(), "X_dim", xlen)
+    }
+
+    let wlen = w_dim.iter().product::<usize>();
+    if w.len() != wlen {
+        return error::length_mismatch("W", w.len(), "W_dim", wlen)
+    }
+
+    if b.len() != y_dim[1] {
+        return error::length_mismatch("B", b.len(), "Y_dim[1]", y_dim[1])
+    }
+
+    let ylen = y_dim.iter().product::<usize>();
+    if y.len() != ylen {
+        return error::length_mismatch("Y", y.len(), "Y_dim", ylen)
+    }
+
+    let (cg, og) = (x_dim[1] / groups, w_dim[1]);
+    // rows of the columns of a group, and the positions of X
+    let (k, p) = (og * w_dim[2] * w_dim[3], x_dim[2] * x_dim[3]);
+    let plane = y_dim[2] * y_dim[3];
+    let g_y_dim = [1, og, y_dim[2], y_dim[3]];
+    let g_w_dim = [1, og, w_dim[2], w_dim[3]];
+
+    y.par_chunks_mut(y_dim[1] * plane)
+        .zip(x.par_chunks(x_dim[1] * p))
+        .try_for_each(|(y, x)| {
+            let mut col = vec![0.0; k * p];
+
+            let groups = y.chunks_mut(og * plane)
+                .zip(x.chunks(cg * p))
+                .zip(w.chunks(cg * k));
+
+            for ((y, x), w) in groups {
+                // W^T * X, then each column is summed onto its patch of Y
+                col.fill(0.0);
+                matmul_wrt_b(w, x, &mut col, [cg, k], [k, p])?;
+                fold(&col, y, g_y_dim, g_w_dim, stride, padh, padw, dilation)?;
+            }
+
+            for (y, b) in y.chunks_mut(plane).zip(b) {
+                for y in y {
+                    *y += b;
+                }
+            }
+
+            Ok(())
+        })
+}
+
+/// # 2D Transposed Convolution w.r.t. X
+/// - W: Filters (C x O / Groups x FH x FW)
+/// - GY: Gradient w.r.t. Y (N x O x YH x YW)
+/// - GX: Gradient w.r.t. X (N x C x H x W)
+/// - X_dim: Dimensions of X
+/// - W_dim: Dimensions of W
+/// - Stride: h, w, strides of the filter
+/// - Padh: height padding
+/// - Padw: width padding
+/// - Output_Padding: h, w, extra rows and columns of Y
+/// - Dilation: h, w, spacing between the elements of the filter
+/// - Groups: Number of groups the channels are split into
+///
+/// The gradient is a `conv2d` of GY with W: the `im2col` of GY multiplied by W.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn conv_transpose2d_wrt_x(
+    w: &[f32],
+    gy: &[f32],
+    gx: &mut [f32],
+    x_dim: [usize; 4],
+    w_dim: [usize; 4],
+    stride: [usize; 2],
+    padh: [usize; 2],
+    padw: [usize; 2],
+    output_padding: [usize; 2],
+    dilation: [usize; 2],
+    groups: usize,
+) -> Result<(), BMLSError> {
+    let y_dim = output_dim(x_dim, w_dim, stride, padh, padw, output_padding, dilation, groups)?;
+
+    let wlen = w_dim.iter().product::<usize>();
+    if w.len() != wlen {
+        return error::length_mismatch("W", w.len(), "W_dim", wlen)
+    }
+
+    let ylen = y_dim.iter().product::<usize>();
+    if gy.len() != ylen {
+        return error::length_mismatch("GY", gy.len(), "Y_dim", ylen)
+    }
+
+    let xlen = x_dim.iter().product::<usize>();
+    if gx.len() != xlen {
+        return error::length_mismatch("GX", gx.len(), "X_dim", xlen)
+    }
+
+    let (cg, og) = (x_dim[1] / groups, w_dim[1]);
+    let (k, p) = (og * w_dim[2] * w_dim[3], x_dim[2] * x_dim[3]);
+    let plane = y_dim[2] * y_dim[3];
+    let g_y_dim = [1, og, y_dim[2], y_dim[3]];
+    let g_w_dim = [1, og, w_dim[2], w_dim[3]];
+
+    gx.par_chunks_mut(x_dim[1] * p)
+        .zip(gy.par_chunks(y_dim[1] * plane))
+        .try_for_each(|(gx, gy)| {
+            let mut col = vec![0.0; k * p];
+            // matmul overwrites, so each group is added into GX from here
+            let mut gxg = vec![0.0; cg * p];
+
+            let groups = gx.chunks_mut(cg * p)
+                .zip(gy.chunks(og * plane))
+                .zip(w.chunks(cg * k));
+
+            for ((gx, gy), w) in groups {
+                im2col(gy, &mut col, g_y_dim, g_w_dim, stride, padh, padw, dilation)?;
+                matmul(w, &col, &mut gxg, [cg, k], [k, p])?;
+                for (gx, g) in gx.iter_mut().zip(&gxg) {
+                    *gx += g;
+                }
+            }
+
+            Ok(())
+        })
+}
+
+/// # 2D Transposed Convolution w.r.t. W
+/// - X: Input (N x C x H x W)
+/// - GY: Gradient w.r.t. Y (N x O x YH x YW)
+/// - GW: Gradient w.r.t. W (C x O / Groups x FH x FW)
+/// - X_dim: Dimensions of X
+/// - W_dim: Dimensions of W
+/// - Stride: h, w, strides of the filter
+/// - Padh: height padding
+/// - Padw: width padding
+/// - Output_Padding: h, w, extra rows and columns of Y
+/// - Dilation: h, w, spacing between the elements of the filter
+/// - Groups: Number of groups the channels are split into
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn conv_transpose2d_wrt_w(
+    x: &[f32],
+    gy: &[f32],
+    gw: &mut [f32],
+    x_dim: [usize; 4],
+    w_dim: [usize; 4],
+    stride: [usize; 2],
+    padh: [usize; 2],
+    padw: [usize; 2],
+    output_padding: [usize; 2],
+    dilation: [usize; 2],
+    groups: usize,
+) -> Result<(), BMLSError> {
+    let y_dim = output_dim(x_dim, w_dim, stride, padh, padw, output_padding, dilation, groups)?;
+
+    let xlen = x_dim.iter().product::<usize>();
+    if x.len() != xlen {
+        return error::length_mismatch("X", x.len(), "X_dim", xlen)
+    }
+
+    let ylen = y_dim.iter().product::<usize>();
+    if gy.len() != ylen {
+        return error::length_mismatch("GY", gy.len(), "Y_dim", ylen)
+    }
+
+    // the roles of X and Y are swapped from conv2d
+    conv2d_wrt_w(gy, x, gw, y_dim, w_dim, stride, padh, padw, dilation, groups)
+}
+
+/// # 2D Transposed Convolution w.r.t. B
+/// - GY: Gradient w.r.t. Y (N x O x YH x YW)
+/// - GB: Gradient w.r.t. B (O)
+/// - Y_dim: Dimensions of Y
+#[inline]
+pub fn conv_transpose2d_wrt_b(
+    gy: &[f32],
+    gb: &mut [f32],
+    y_dim: [usize; 4],
+) -> Result<(), BMLSError> {
+    conv2d_wrt_b(gy, gb, y_dim)
+}
+
+/// Returns the dimensions of Y, after checking the arguments
+/// that `conv2d` cannot check from the side of Y.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+fn output_dim(
+    x_dim: [usize; 4],
+    w_dim: [usize; 4],
+    stride: [usize; 2],
+    padh: [usize; 2],
+    padw: [usize; 2],
+    output_padding: [usize; 2],
+    dilation: [usize; 2],
+    groups: usize,
+) -> Result<[usize; 4], BMLSError> {
+    let (n, c, hx, wx) = (x_dim[0], x_dim[1], x_dim[2], x_dim[3]);
+    let (cf, og, hf, wf) = (w_dim[0], w_dim[1], w_dim[2], w_dim[3]);
+
+    if groups == 0 || c / groups * groups != c {
+        return error::invalid_groups(groups, c)
+    }
+
+    if cf != c || og == 0 || hf == 0 || wf == 0 || hx == 0 || wx == 0 {
+        return Err(BMLSError::InvalidKernelDim(cf, og, hf, wf))
+    }
+
+    if stride[0] == 0 || stride[1] == 0 {
+        return Err(BMLSError::InvalidStrides(stride[0], stride[1]))
+    }
+
+    if dilation[0] == 0 || dilation[1] == 0 {
+        return Err(BMLSError::InvalidDilation(dilation[0], dilation[1]))
+    }
+
+    // a larger output padding would be a different size
+    // of X for the conv2d that Y is the gradient of.
+    if output_padding[0] >= stride[0] || output_padding[1] >= stride[1] {
+        return error::invalid_output_padding(output_padding[0], output_padding[1])
+    }
+
+    // the size of Y before the padding is removed
+    let hy = (hx - 1) * stride[0] + dilation[0] * (hf - 1) + 1 + output_padding[0];
+    let wy = (wx - 1) * stride[1] + dilation[1] * (wf - 1) + 1 + output_padding[1];
+
+    if padh[0] + padh[1] >= hy || padw[0] + padw[1] >= wy {
+        return error::invalid_padding(padh[0] + padh[1], padw[0] + padw[1])
+    }
+
+    // W holds the output channels of one group
+    Ok([n, og * groups, hy - padh[0] - padh[1], wy - padw[0] - padw[1]])
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const X_DIM: [usize; 4] = [2, 4, 3, 2];
+    const STRIDE: [usize; 2] = [2, 3];
+    const PADH: [usize; 2] = [1, 0];
+    const PADW: [usize; 2] = [0, 2];
+    const OUTPUT_PADDING: [usize; 2] = [1, 2];
+    const DILATION: [usize; 2] = [1, 2];
+    // (3 - 1) * 2 - 1 + 3 + 1, (2 - 1) * 3 - 2 + 3 + 2
+    const Y_DIM: [usize; 4] = [2, 2, 7, 6];
+
+    fn w_dim(groups: usize) -> [usize; 4] {
+        [4, 2 / groups, 3, 2]
+    }
+
+    fn values(len: usize, seed: f32) -> Vec<f32> {
+        (0..len).map(|i| f32::sin(i as f32 * seed)).collect()
+    }
+
+    // scatters each element of X onto Y
+    fn naive(x: &[f32], w: &[f32], b: &[f32], groups: usize) -> Vec<f32> {
+        let [n, cx, h, wd] = X_DIM;
+        let [_, og, fh, fw] = w_dim(groups);
+        let [_, o, yh, yw] = Y_DIM;
+        let cg = cx / groups;
+
+        let mut y = vec![0.0; n * o * yh * yw];
+        for (i, y) in y.iter_mut().enumerate() {
+            *y = b[i / (yh * yw) % o];
+        }
+
+        for n in 0..n {
+            for c in 0..cx {
+                for i in 0..h {
+                    for j in 0..wd {
+                        for k in 0..og {
+                            let o_out = c / cg * og + k;
+                            for kh in 0..fh {
+                                for kw in 0..fw {
+                                    let yh_i = (i * STRIDE[0] + kh * DILATION[0]) as isize - PADH[0] as isize;
+                                    let yw_i = (j * STRIDE[1] + kw * DILATION[1]) as isize - PADW[0] as isize;
+                                    if yh_i < 0 || yw_i < 0 || yh_i >= yh as isize || yw_i >= yw as isize {
+                                        continue;
+                                    }
+                                    let yi = ((n * o + o_out) * yh + yh_i as usize) * yw + yw_i as usize;
+                                    let xi = ((n * cx + c) * h + i) * wd + j;
+                                    let wi = ((c * og + k) * fh + kh) * fw + kw;
+                                    y[yi] += x[xi] * w[wi];
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        y
+    }
+
+    #[test]
+    fn test_conv_transpose2d() {
+        for groups in [1, 2] {
+            let w_len = w_dim(groups).iter().product();
+            let x = values(48, 0.7);
+            let w = values(w_len, 1.3);
+            let b = [0.1, -0.2];
+            let mut y = vec![1.0; 168];
+
+            conv_transpose2d(&x, &w, &b, &mut y, X_DIM, w_dim(groups), STRIDE, PADH, PADW, OUTPUT_PADDING, DILATION, groups).unwrap();
+
+            for (y, e) in y.iter().zip(naive(&x, &w, &b, groups)) {
+                assert!((y - e).abs() < 1e-5);
+            }
+        }
+
+        let x = values(48, 0.7);
+        let mut y = vec![0.0; 168];
+        // the output padding must be smaller than the stride
+        assert!(conv_transpose2d(&x, &[0.0; 48], &[0.0; 2], &mut y, X_DIM, w_dim(1), STRIDE, PADH, PADW, [2, 0], DILATION, 1).is_err());
+    }
+
+    #[test]
+    fn test_conv_transpose2d_gradients() {
+        for groups in [1, 2] {
+            let w_len = w_dim(groups).iter().product();
+            let x = values(48, 0.7);
+            let w = values(w_len, 1.3);
+            let gy = values(168, 0.9);
+
+            let mut gx = vec![0.0; 48];
+            let mut gw = vec![0.0; w_len];
+            let mut gb = vec![0.0; 2];
+            conv_transpose2d_wrt_x(&w, &gy, &mut gx, X_DIM, w_dim(groups), STRIDE, PADH, PADW, OUTPUT_PADDING, DILATION, groups).unwrap();
+            conv_transpose2d_wrt_w(&x, &gy, &mut gw, X_DIM, w_dim(groups), STRIDE, PADH, PADW, OUTPUT_PADDING, DILATION, groups).unwrap();
+            conv_transpose2d_wrt_b(&gy, &mut gb, Y_DIM).unwrap();
+
+            // linear in each input, so <gy, y(e_i)> is the gradient for element i
+            let dot = |x: &[f32], w: &[f32], b: &[f32]| {
+                naive(x, w, b, groups).iter().zip(&gy).map(|(y, g)| y * g).sum::<f32>()
+            };
+
+            for (i, gx) in gx.iter().enumerate() {
+                let mut e = vec![0.0; 48];
+                e[i] = 1.0;
+                assert!((dot(&e, &w, &[0.0; 2]) - gx).abs() < 1e-4);
+            }
+
+            for (i, gw) in gw.iter().enumerate() {
+                let mut e = vec![0.0; w_len];
+                e[i] = 1.0;
+                assert!((dot(&x, &e, &[0.0; 2]) - gw).abs() < 1e-4);
+            }
+
+            for (i, gb) in gb.iter().enumerate() {
+                let mut e = [0.0; 2];
+                e[i] = 1.0;
+                assert!((dot(&x, &vec![0.0; w_len], &e) - gb).abs() < 1e-4);
+            }
+
+            // the gradient is added to GX
+            let mut twice = gx.clone();
+            conv_transpose2d_wrt_x(&w, &gy, &mut twice, X_DIM, w_dim(groups), STRIDE, PADH, PADW, OUTPUT_PADDING, DILATION, groups).unwrap();
+            for (t, gx) in twice.iter().zip(&gx) {
+                assert!((t - 2.0 * gx).abs() < 1e-5);
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use itertools::izip;
+use crate::error::BMLSError;
+use crate::error;
+use crate::reduction::{Reduction, reduce_rows_multi};
+
+/// Norms are clamped to at least this to avoid dividing by zero.
+const EPSILON: f32 = 1e-8;
+
+/// # Cosine Embedding Loss
+/// - X1: First Embeddings (N x D)
+/// - X2: Second Embeddings (N x D)
+/// - T: Whether each pair is similar (> 0) or dissimilar (<= 0), usually 1 or -1 (N)
+/// - E: Error, (N x 1) vector for `Reduction::None`, otherwise (1)
+/// - G1: Gradient of E w.r.t. X1 (N x D)
+/// - G2: Gradient of E w.r.t. X2 (N x D)
+/// - Dim: [N, D]
+/// - Margin: Similarity below which dissimilar pairs have no error, usually 0 to 0.5
+/// - Sample_Weights: Optional weight for each row (N)
+/// - Reduction: How the rows are combined into E
+///
+/// With cos the cosine similarity of the pair: \
+/// similar: 1 - cos \
+/// dissimilar: max(0, cos - margin)
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn cosine_embedding(
+    x1: &[f32],
+    x2: &[f32],
+    t: &[f32],
+    e: &mut [f32],
+    g1: &mut [f32],
+    g2: &mut [f32],
+    dim: [usize; 2],
+    margin: f32,
+    sample_weights: Option<&[f32]>,
+    reduction: Reduction,
+) -> Result<(), BMLSError> {
+    let len = dim[0] * dim[1];
+    if x1.len() != len {
+        return error::length_mismatch("X1", x1.len(), "Dim", len)
+    }
+
+    if x2.len() != len {
+        return error::length_mismatch("X2", x2.len(), "Dim", len)
+    }
+
+    if t.len() != dim[0] {
+        return error::length_mismatch("T", t.len(), "Dim[0]", dim[0])
+    }
+
+    if g1.len() != len {
+        return error::length_mismatch("G1", g1.len(), "Dim", len)
+    }
+
+    if g2.len() != len {
+        return error::length_mismatch("G2", g2.len(), "Dim", len)
+    }
+
+    let cols = dim[1];
+
+    reduce_rows_multi(e, &mut [g1, g2], dim[0], sample_weights, reduction, |i, g| {
+        let a = &x1[i * cols..(i + 1) * cols];
+        let b = &x2[i * cols..(i + 1) * cols];
+
+        let mut ab = 0.0;
+        let mut aa = 0.0;
+        let mut bb = 0.0;
+        for (a, b) in izip!(a, b) {
+            ab += a * b;
+            aa += a * a;
+            bb += b * b;
+        }
+
+        let na = f32::max(aa.sqrt(), EPSILON);
+        let nb = f32::max(bb.sqrt(), EPSILON);
+        let cos = ab / (na * nb);
+
+        // d(loss) / d(cos)
+        let (loss, scale) = if t[i] > 0.0 {
+            (1. - cos, -1.)
+        } else if cos > margin {
+            (cos - margin, 1.)
+        } else {
+            (0.0, 0.0)
+        };
+
+        // d(cos) / d(a) = b / (|a| |b|) - cos * a / |a|^2
+        for (a, b, g) in izip!(a, b, g[0].iter_mut()) {
+            *g = scale * (b / (na * nb) - cos * a / (na * na));
+        }
+
+        for (a, b, g) in izip!(a, b, g[1].iter_mut()) {
+            *g = scale * (a / (na * nb) - cos * b / (nb * nb));
+        }
+
+        Some(loss)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_cosine_embedding_gradient() {
+        let dim = [2, 3];
+        let x1 = [1.0, 0.5, -0.2, 0.3, 0.8, 0.1];
+        let x2 = [0.4, -1.0, 0.7, 0.5, 0.9, -0.3];
+        let t = [1.0, -1.0];
+
+        let mut e = [0.0];
+        let mut g1 = [0.0; 6];
+        let mut g2 = [0.0; 6];
+        cosine_embedding(&x1, &x2, &t, &mut e, &mut g1, &mut g2, dim, 0.1, None, Reduction::Mean).unwrap();
+
+        let f = |x1: &[f32], x2: &[f32]| {
+            let mut e = [0.0];
+            cosine_embedding(x1, x2, &t, &mut e, &mut [0.0; 6], &mut [0.0; 6], dim, 0.1, None, Reduction::Mean).unwrap();
+            e[0]
+        };
+
+        for i in 0..6 {
+            let (mut ap, mut am, mut bp, mut bm) = (x1, x1, x2, x2);
+            ap[i] += 1e-2;
+            am[i] -= 1e-2;
+            bp[i] += 1e-2;
+            bm[i] -= 1e-2;
+            assert!(((f(&ap, &x2) - f(&am, &x2)) / 2e-2 - g1[i]).abs() < 1e-3);
+            assert!(((f(&x1, &bp) - f(&x1, &bm)) / 2e-2 - g2[i]).abs() < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use itertools::izip;
+use crate::error::BMLSError;
+use crate::error;
+use crate::reduction::{Reduction, reduce_rows};
+
+/// # Categorical Cross Entropy (from logits)
+/// - T: Target probabilities, usually one-hot (N x C)
+/// - X: Logits (N x C)
+/// - E: Error, (N x 1) vector for `Reduction::None`, otherwise (1)
+/// - G: Gradient of E w.r.t. X (N x C)
+/// - Dim: [N, C]
+/// - Weights: Optional weight for each class (C)
+/// - Smoothing: Label smoothing, between 0 and 1
+/// - Sample_Weights: Optional weight for each row (N)
+/// - Reduction: How the rows are combined into E
+///
+/// Log softmax is fused into the loss, so X should not be passed
+/// through `softmax` first. With smoothed targets t' = (1 - s) * t + s / C: \
+/// e = -sum(w * t' * log_softmax(x)) \
+/// g = softmax(x) * sum(w * t') - w * t'
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn cross_entropy(
+    t: &[f32],
+    x: &[f32],
+    e: &mut [f32],
+    g: &mut [f32],
+    dim: [usize; 2],
+    weights: Option<&[f32]>,
+    smoothing: f32,
+    sample_weights: Option<&[f32]>,
+    reduction: Reduction,
+) -> Result<(), BMLSError> {
+    let len = dim[0] * dim[1];
+    if t.len() != len {
+        return error::length_mismatch("T", t.len(), "Dim", len)
+    }
+
+    if x.len() != len {
+        return error::length_mismatch("X", x.len(), "Dim", len)
+    }
+
+    if g.len() != len {
+        return error::length_mismatch("G", g.len(), "Dim", len)
+    }
+
+    check_options(dim, weights, smoothing)?;
+
+    let cols = dim[1];
+    let uniform = smoothing / cols as f32;
+
+    reduce_rows(e, g, dim, sample_weights, reduction, |i, g| {
+        let t = &t[i * cols..(i + 1) * cols];
+        let x = &x[i * cols..(i + 1) * cols];
+
+        let lse = log_sum_exp(x);
+
+        // sum(w * t'), the total weight of the targets
+        let mut total = 0.0;
+        let mut e = 0.0;
+        for (j, (t, x, g)) in izip!(t, x, g.iter_mut()).enumerate() {
+            let wt = class_weight(weights, j) * ((1. - smoothing) * t + uniform);
+            total += wt;
+            e -= wt * (x - lse);
+            *g = -wt;
+        }
+
+        for (x, g) in izip!(x, g) {
+            *g += f32::exp(x - lse) * total;
+        }
+
+        Some(e)
+    })
+}
+
+/// # Sparse Categorical Cross Entropy (from logits)
+/// - T: Target class index of each row (N)
+/// - X: Logits (N x C)
+/// - E: Error, (N x 1) vector for `Reduction::None`, otherwise (1)
+/// - G: Gradient of E w.r.t. X (N x C)
+/// - Dim: [N, C]
+/// - Weights: Optional weight for each class (C)
+/// - Smoothing: Label smoothing, between 0 and 1
+/// - Ignore_Index: Rows with this target have zero error and gradient,
+///   and are not counted by `Reduction::Mean`.
+/// - Sample_Weights: Optional weight for each row (N)
+/// - Reduction: How the rows are combined into E
+///
+/// The same as `cross_entropy` with one-hot targets.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn sparse_cross_entropy(
+    t: &[usize],
+    x: &[f32],
+    e: &mut [f32],
+    g: &mut [f32],
+    dim: [usize; 2],
+    weights: Option<&[f32]>,
+    smoothing: f32,
+    ignore_index: Option<usize>,
+    sample_weights: Option<&[f32]>,
+    reduction: Reduction,
+) -> Result<(), BMLSError> {
+    let len = dim[0] * dim[1];
+    if t.len() != dim[0] {
+        return error::length_mismatch("T", t.len(), "Dim[0]", dim[0])
+    }
+
+    if x.len() != len {
+        return error::length_mismatch("X", x.len(), "Dim", len)
+    }
+
+    if g.len() != len {
+        return error::length_mismatch("G", g.len(), "Dim", len)
+    }
+
+    check_options(dim, weights, smoothing)?;
+
+    let cols = dim[1];
+    for t in t {
+        if *t >= cols && Some(*t) != ignore_index {
+            return error::invalid_class_index(*t, cols)
+        }
+    }
+
+    let uniform = smoothing / cols as f32;
+
+    reduce_rows(e, g, dim, sample_weights, reduction, |i, g| {
+        let t = t[i];
+        if Some(t) == ignore_index {
+            return None
+        }
+
+        let x = &x[i * cols..(i + 1) * cols];
+        let lse = log_sum_exp(x);
+
+        let mut total = 0.0;
+        let mut e = 0.0;
+        for (j, (x, g)) in izip!(x, g.iter_mut()).enumerate() {
+            let target = if j == t { 1. - smoothing } else { 0.0 };
+            let wt = class_weight(weights, j) * (target + uniform);
+            total += wt;
+            e -= wt * (x - lse);
+            *g = -wt;
+        }
+
+        for (x, g) in izip!(x, g) {
+            *g += f32::exp(x - lse) * total;
+        }
+
+        Some(e)
+    })
+}
+
+/// # Binary Cross Entropy (from logits)
+/// - T: Target probabilities (N x C)
+/// - X: Logits (N x C)
+/// - E: Error, (N x 1) vector for `Reduction::None`, otherwise (1)
+/// - G: Gradient of E w.r.t. X (N x C)
+/// - Dim: [N, C]
+/// - Weights: Optional weight for each column (C)
+/// - Smoothing: Label smoothing, between 0 and 1
+/// - Sample_Weights: Optional weight for each row (N)
+/// - Reduction: How the rows are combined into E
+///
+/// Each column is an independent binary classification. The sigmoid is
+/// fused into the loss, so X should not be passed through `sigmoid` first.
+/// With t' = (1 - s) * t + s / 2: \
+/// e = mean over C of (w * (max(x, 0) - x * t' + ln(1 + e^-|x|))) \
+/// g = w * (sigmoid(x) - t') / C
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn binary_cross_entropy(
+    t: &[f32],
+    x: &[f32],
+    e: &mut [f32],
+    g: &mut [f32],
+    dim: [usize; 2],
+    weights: Option<&[f32]>,
+    smoothing: f32,
+    sample_weights: Option<&[f32]>,
+    reduction: Reduction,
+) -> Result<(), BMLSError> {
+    let len = dim[0] * dim[1];
+    if t.len() != len {
+        return error::length_mismatch("T", t.len(), "Dim", len)
+    }
+
+    if x.len() != len {
+        return error::length_mismatch("X", x.len(), "Dim", len)
+    }
+
+    if g.len() != len {
+        return error::length_mismatch("G", g.len(), "Dim", len)
+    }
+
+    check_options(dim, weights, smoothing)?;
+
+    let cols = dim[1];
+    let scale = 1. / cols as f32;
+
+    reduce_rows(e, g, dim, sample_weights, reduction, |i, g| {
+        let t = &t[i * cols..(i + 1) * cols];
+        let x = &x[i * cols..(i + 1) * cols];
+
+        let mut sum = 0.0;
+        for (j, (t, x, g)) in izip!(t, x, g).enumerate() {
+            let w = class_weight(weights, j);
+            let t = (1. - smoothing) * t + smoothing / 2.;
+
+            // stable form of -(t * ln(sigmoid(x)) + (1 - t) * ln(1 - sigmoid(x)))
+            sum += w * (f32::max(*x, 0.0) - x * t + f32::ln_1p(f32::exp(-x.abs())));
+            *g = w * (sigmoid(*x) - t) * scale;
+        }
+
+        Some(sum * scale)
+    })
+}
+
+/// ln(sum(e^x)), computed without overflow.
+#[inline]
+pub(crate) fn log_sum_exp(x: &[f32]) -> f32 {
+    let max = x.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    max + f32::ln(x.iter().map(|x| f32::exp(x - max)).sum::<f32>())
+}
+
+/// Sigmoid that does not overflow for large negative X.
+#[inline]
+pub(crate) fn sigmoid(x: f32) -> f32 {
+    if x >= 0.0 {
+        1. / (1. + f32::exp(-x))
+    } else {
+        let ex = f32::exp(x);
+        ex / (1. + ex)
+    }
+}
+
+#[inline]
+pub(crate) fn class_weight(weights: Option<&[f32]>, class: usize) -> f32 {
+    weights.map_or(1.0, |w| w[class])
+}
+
+/// Validates the class Weights and Smoothing shared by the losses.
+#[inline]
+pub(crate) fn check_options(
+    dim: [usize; 2],
+    weights: Option<&[f32]>,
+    smoothing: f32,
+) -> Result<(), BMLSError> {
+    if let Some(w) = weights {
+        if w.len() != dim[1] {
+            return error::length_mismatch("Weights", w.len(), "Dim[1]", dim[1])
+        }
+    }
+
+    if !(0.0..=1.0).contains(&smoothing) {
+        return error::invalid_label_smoothing(smoothing)
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_cross_entropy_gradient() {
+        let dim = [2, 3];
+        let t = [0.0, 1.0, 0.0, 0.2, 0.3, 0.5];
+        let x = [1.0, -2.0, 0.5, 3.0, 0.1, -0.4];
+        let w = [0.5, 2.0, 1.0];
+
+        let mut e = [0.0; 2];
+        let mut g = [0.0; 6];
+        cross_entropy(&t, &x, &mut e, &mut g, dim, Some(&w), 0.1, None, Reduction::None).unwrap();
+
+        let f = |x: &[f32]| {
+            let mut e = [0.0; 2];
+            let mut g = [0.0; 6];
+            cross_entropy(&t, x, &mut e, &mut g, dim, Some(&w), 0.1, None, Reduction::None).unwrap();
+            e[0] + e[1]
+        };
+
+        for i in 0..6 {
+            let mut xp = x;
+            let mut xm = x;
+            xp[i] += 1e-2;
+            xm[i] -= 1e-2;
+            let fd = (f(&xp) - f(&xm)) / 2e-2;
+            assert!((fd - g[i]).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_sparse_matches_dense() {
+        let dim = [3, 4];
+        let x: Vec<f32> = (0..12).map(|i| f32::sin(i as f32) * 50.0).collect();
+        let labels = [2, 0, 3];
+        let mut t = vec![0.0; 12];
+        for (i, l) in labels.iter().enumerate() {
+            t[i * 4 + l] = 1.0;
+        }
+
+        let mut e1 = [0.0; 3];
+        let mut g1 = [0.0; 12];
+        let mut e2 = [0.0; 3];
+        let mut g2 = [0.0; 12];
+        cross_entropy(&t, &x, &mut e1, &mut g1, dim, None, 0.2, None, Reduction::None).unwrap();
+        sparse_cross_entropy(&labels, &x, &mut e2, &mut g2, dim, None, 0.2, None, None, Reduction::None).unwrap();
+
+        assert!(e1.iter().all(|e| e.is_finite()));
+        for (a, b) in e1.iter().zip(&e2).chain(g1.iter().zip(&g2)) {
+            assert!((a - b).abs() < 1e-4);
+        }
+
+        // ignored rows have no error or gradient
+        let labels = [2, 9, 3];
+        sparse_cross_entropy(&labels, &x, &mut e2, &mut g2, dim, None, 0.0, Some(9), None, Reduction::None).unwrap();
+        assert_eq!(e2[1], 0.0);
+        assert_eq!(g2[4..8], [0.0; 4]);
+
+        // and are not counted by the mean
+        let mut mean = [0.0];
+        sparse_cross_entropy(&labels, &x, &mut mean, &mut g1, dim, None, 0.0, Some(9), None, Reduction::Mean).unwrap();
+        assert!((mean[0] - (e2[0] + e2[2]) / 2.).abs() < 1e-4);
+
+        assert!(sparse_cross_entropy(&labels, &x, &mut e2, &mut g2, dim, None, 0.0, None, None, Reduction::None).is_err());
+    }
+
+    #[test]
+    fn test_binary_cross_entropy() {
+        let dim = [1, 3];
+        let t = [1.0, 0.0, 1.0];
+        let x = [100.0, -100.0, 0.0];
+        let mut e = [0.0];
+        let mut g = [0.0; 3];
+
+        binary_cross_entropy(&t, &x, &mut e, &mut g, dim, None, 0.0, None, Reduction::None).unwrap();
+
+        // only the last column has any error, ln(2)
+        assert!((e[0] - f32::ln(2.0) / 3.0).abs() < 1e-6);
+        assert!((g[2] + 0.5 / 3.0).abs() < 1e-6);
+        assert!(g[0].abs() < 1e-6 && g[1].abs() < 1e-6);
+    }
+}
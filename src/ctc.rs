@@ -0,0 +1,343 @@
+use rayon::prelude::*;
+use crate::error::BMLSError;
+use crate::error;
+use crate::reduction::{Reduction, reduce_rows};
+
+/// # Connectionist Temporal Classification Loss
+/// - X: Log-probabilities, the output of `log_softmax` over C (T x N x C)
+/// - Targets: Target labels of every sample, concatenated (sum of Target_Lengths)
+/// - Input_Lengths: Number of timesteps of each sample, at most T (N)
+/// - Target_Lengths: Number of target labels of each sample (N)
+/// - E: Error, (N x 1) vector for `Reduction::None`, otherwise (1)
+/// - G: Gradient of E w.r.t. X (T x N x C)
+/// - Dim: [T, N, C]
+/// - Blank: Class index of the blank label
+/// - Sample_Weights: Optional weight for each sample (N)
+/// - Reduction: How the samples are combined into E
+///
+/// The error of each sample is -ln(p(target | x)), summed over every
+/// alignment with the forward-backward algorithm in log space.
+/// Timesteps past a sample's input length have zero gradient.
+///
+/// G is w.r.t. the log-probabilities, so pass it through
+/// `log_softmax_wrt_x` to get the gradient w.r.t. the logits.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn ctc(
+    x: &[f32],
+    targets: &[usize],
+    input_lengths: &[usize],
+    target_lengths: &[usize],
+    e: &mut [f32],
+    g: &mut [f32],
+    dim: [usize; 3],
+    blank: usize,
+    sample_weights: Option<&[f32]>,
+    reduction: Reduction,
+) -> Result<(), BMLSError> {
+    let (steps, batch, classes) = (dim[0], dim[1], dim[2]);
+
+    let len = steps * batch * classes;
+    if x.len() != len {
+        return error::length_mismatch("X", x.len(), "Dim", len)
+    }
+
+    if g.len() != len {
+        return error::length_mismatch("G", g.len(), "Dim", len)
+    }
+
+    if input_lengths.len() != batch {
+        return error::length_mismatch("Input_Lengths", input_lengths.len(), "Dim[1]", batch)
+    }
+
+    if target_lengths.len() != batch {
+        return error::length_mismatch("Target_Lengths", target_lengths.len(), "Dim[1]", batch)
+    }
+
+    let total = target_lengths.iter().sum::<usize>();
+    if targets.len() != total {
+        return error::length_mismatch("Targets", targets.len(), "Target_Lengths", total)
+    }
+
+    if blank >= classes {
+        return error::invalid_class_index(blank, classes)
+    }
+
+    // the start of each sample's labels in Targets
+    let mut offsets = Vec::with_capacity(batch);
+    let mut offset = 0;
+    for (b, (input_len, target_len)) in input_lengths.iter().zip(target_lengths).enumerate() {
+        let labels = &targets[offset..offset + target_len];
+
+        for l in labels {
+            if *l >= classes {
+                return error::invalid_class_index(*l, classes)
+            }
+
+            if *l == blank {
+                return error::invalid_ctc_target(b, *l)
+            }
+        }
+
+        // repeated labels need a blank between them
+        let repeats = labels.windows(2).filter(|w| w[0] == w[1]).count();
+        let needed = target_len + repeats;
+        if *input_len < needed || *input_len > steps {
+            return error::invalid_ctc_length(b, *input_len, needed, steps)
+        }
+
+        offsets.push(offset);
+        offset += target_len;
+    }
+
+    // each sample is independent, so compute them in parallel into a
+    // scratch buffer laid out as N x (T x C), then reduce as rows.
+    let row = steps * classes;
+    let mut scratch = vec![0.0; batch * row];
+    let mut losses = vec![0.0; batch];
+
+    scratch.par_chunks_mut(row.max(1))
+        .zip(losses.par_iter_mut())
+        .enumerate()
+        .for_each(|(b, (g, loss))| {
+            let labels = &targets[offsets[b]..offsets[b] + target_lengths[b]];
+            let lp = |t: usize, k: usize| x[(t * batch + b) * classes + k];
+
+            *loss = forward_backward(lp, labels, input_lengths[b], classes, blank, g);
+        });
+
+    reduce_rows(e, &mut scratch, [batch, row], sample_weights, reduction, |b, _| Some(losses[b]))?;
+
+    for (b, s) in scratch.chunks(row.max(1)).enumerate() {
+        for (t, s) in s.chunks(classes.max(1)).enumerate() {
+            let start = (t * batch + b) * classes;
+            g[start..start + classes].copy_from_slice(s);
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns -ln(p(labels | x)) of one sample, and writes its
+/// gradient w.r.t. LP into G (T x C).
+fn forward_backward<F>(
+    lp: F,
+    labels: &[usize],
+    steps: usize,
+    classes: usize,
+    blank: usize,
+    g: &mut [f32],
+) -> f32
+where
+    F: Fn(usize, usize) -> f32,
+{
+    // labels with a blank before, between and after them
+    let s_len = 2 * labels.len() + 1;
+    let label = |s: usize| if s % 2 == 1 { labels[s / 2] } else { blank };
+
+    // s can also be reached from s - 2, skipping a blank,
+    // unless s is a blank or the same label as s - 2
+    let can_skip = |s: usize| s >= 2 && label(s) != blank && label(s) != label(s - 2);
+
+    if steps == 0 {
+        // only an empty target can align with an empty input, with p = 1
+        return 0.0
+    }
+
+    let mut alpha = vec![f32::NEG_INFINITY; steps * s_len];
+    let mut beta = vec![f32::NEG_INFINITY; steps * s_len];
+
+    alpha[0] = lp(0, blank);
+    if s_len > 1 {
+        alpha[1] = lp(0, label(1));
+    }
+
+    for t in 1..steps {
+        let (prev, cur) = alpha[(t - 1) * s_len..(t + 1) * s_len].split_at_mut(s_len);
+        for (s, a) in cur.iter_mut().enumerate() {
+            let mut sum = prev[s];
+            if s >= 1 {
+                sum = log_add(sum, prev[s - 1]);
+            }
+            if can_skip(s) {
+                sum = log_add(sum, prev[s - 2]);
+            }
+            *a = sum + lp(t, label(s));
+        }
+    }
+
+    let last = steps - 1;
+    beta[last * s_len + s_len - 1] = lp(last, blank);
+    if s_len > 1 {
+        beta[last * s_len + s_len - 2] = lp(last, label(s_len - 2));
+    }
+
+    for t in (0..last).rev() {
+        let (cur, next) = beta[t * s_len..(t + 2) * s_len].split_at_mut(s_len);
+        for (s, b) in cur.iter_mut().enumerate() {
+            let mut sum = next[s];
+            if s + 1 < s_len {
+                sum = log_add(sum, next[s + 1]);
+            }
+            if s + 2 < s_len && can_skip(s + 2) {
+                sum = log_add(sum, next[s + 2]);
+            }
+            *b = sum + lp(t, label(s));
+        }
+    }
+
+    let mut log_p = alpha[last * s_len + s_len - 1];
+    if s_len > 1 {
+        log_p = log_add(log_p, alpha[last * s_len + s_len - 2]);
+    }
+
+    // -d(ln p) / d(lp(t, k)) is minus the posterior of emitting k at t,
+    // sum over s with label k of alpha * beta / (p * y(t, k))
+    for (t, g) in g.chunks_mut(classes).take(steps).enumerate() {
+        let mut occupancy = vec![f32::NEG_INFINITY; classes];
+        for s in 0..s_len {
+            let k = label(s);
+            occupancy[k] = log_add(occupancy[k], alpha[t * s_len + s] + beta[t * s_len + s]);
+        }
+
+        for (k, (g, o)) in g.iter_mut().zip(occupancy).enumerate() {
+            // a class on no path has no gradient, even where lp(t, k) is -inf
+            *g = if o == f32::NEG_INFINITY {
+                0.0
+            } else {
+                -f32::exp(o - lp(t, k) - log_p)
+            };
+        }
+    }
+
+    -log_p
+}
+
+/// ln(e^a + e^b), without overflow.
+#[inline]
+fn log_add(a: f32, b: f32) -> f32 {
+    let max = f32::max(a, b);
+    if max == f32::NEG_INFINITY {
+        max
+    } else {
+        max + f32::ln(f32::exp(a - max) + f32::exp(b - max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::log_softmax::log_softmax;
+
+    fn log_probs(dim: [usize; 3]) -> Vec<f32> {
+        let len = dim[0] * dim[1] * dim[2];
+        let logits: Vec<f32> = (0..len).map(|i| f32::sin(i as f32 * 1.7) * 2.0).collect();
+        let mut x = vec![0.0; len];
+        log_softmax(&logits, &mut x, [dim[0] * dim[1], dim[2], 1, 1], 1).unwrap();
+        x
+    }
+
+    #[test]
+    fn test_ctc_single_path() {
+        // one timestep per label, so there is exactly one alignment
+        let dim = [2, 1, 3];
+        let x = log_probs(dim);
+        let mut e = [0.0];
+        let mut g = vec![0.0; 6];
+
+        ctc(&x, &[1, 2], &[2], &[2], &mut e, &mut g, dim, 0, None, Reduction::None).unwrap();
+
+        assert!((e[0] + x[1] + x[5]).abs() < 1e-5);
+        assert_eq!(g, [0.0, -1.0, 0.0, 0.0, 0.0, -1.0]);
+    }
+
+    #[test]
+    fn test_ctc_gradient() {
+        let dim = [6, 2, 4];
+        let x = log_probs(dim);
+        let targets = [1, 1, 3, 2];
+        let input_lengths = [6, 4];
+        let target_lengths = [3, 1];
+
+        let mut e = [0.0];
+        let mut g = vec![0.0; x.len()];
+        ctc(&x, &targets, &input_lengths, &target_lengths, &mut e, &mut g, dim, 0, None, Reduction::Mean).unwrap();
+
+        let f = |x: &[f32]| {
+            let mut e = [0.0];
+            let mut g = vec![0.0; x.len()];
+            ctc(x, &targets, &input_lengths, &target_lengths, &mut e, &mut g, dim, 0, None, Reduction::Mean).unwrap();
+            e[0]
+        };
+
+        for i in 0..x.len() {
+            let mut xp = x.clone();
+            let mut xm = x.clone();
+            xp[i] += 1e-2;
+            xm[i] -= 1e-2;
+            let fd = (f(&xp) - f(&xm)) / 2e-2;
+            assert!((fd - g[i]).abs() < 1e-3);
+        }
+
+        // the second sample has no gradient past its input length
+        for t in 4..6 {
+            let start = (t * 2 + 1) * 4;
+            assert!(g[start..start + 4].iter().all(|g| *g == 0.0));
+        }
+    }
+
+    #[test]
+    fn test_ctc_masked_class() {
+        // class 3 is masked out of every timestep, so its log-probability is -inf
+        let dim = [6, 2, 4];
+        let len = dim[0] * dim[1] * dim[2];
+        let logits: Vec<f32> = (0..len)
+            .map(|i| if i % 4 == 3 { f32::NEG_INFINITY } else { f32::sin(i as f32 * 1.7) * 2.0 })
+            .collect();
+        let mut x = vec![0.0; len];
+        log_softmax(&logits, &mut x, [dim[0] * dim[1], dim[2], 1, 1], 1).unwrap();
+
+        let targets = [1, 2, 2];
+        let input_lengths = [6, 4];
+        let target_lengths = [2, 1];
+
+        let mut e = [0.0];
+        let mut g = vec![0.0; len];
+        ctc(&x, &targets, &input_lengths, &target_lengths, &mut e, &mut g, dim, 0, None, Reduction::Mean).unwrap();
+        assert!(e[0].is_finite());
+
+        let f = |x: &[f32]| {
+            let mut e = [0.0];
+            let mut g = vec![0.0; x.len()];
+            ctc(x, &targets, &input_lengths, &target_lengths, &mut e, &mut g, dim, 0, None, Reduction::Mean).unwrap();
+            e[0]
+        };
+
+        for i in 0..len {
+            if i % 4 == 3 {
+                assert_eq!(g[i], 0.0);
+                continue;
+            }
+            let mut xp = x.clone();
+            let mut xm = x.clone();
+            xp[i] += 1e-2;
+            xm[i] -= 1e-2;
+            let fd = (f(&xp) - f(&xm)) / 2e-2;
+            assert!((fd - g[i]).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_ctc_invalid() {
+        let dim = [2, 1, 3];
+        let x = log_probs(dim);
+        let mut e = [0.0];
+        let mut g = vec![0.0; 6];
+
+        // a repeated label needs 3 timesteps
+        assert!(ctc(&x, &[1, 1], &[2], &[2], &mut e, &mut g, dim, 0, None, Reduction::Sum).is_err());
+        assert!(ctc(&x, &[0], &[2], &[1], &mut e, &mut g, dim, 0, None, Reduction::Sum).is_err());
+        assert!(ctc(&x, &[1], &[3], &[1], &mut e, &mut g, dim, 0, None, Reduction::Sum).is_err());
+    }
+}
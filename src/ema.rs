@@ -0,0 +1,116 @@
+use itertools::izip;
+use crate::error::BMLSError;
+use crate::error;
+
+/// # Exponential Moving Average of Weights
+/// Keeps a shadow copy of each parameter, to evaluate with 
+/// instead of the raw weights.
+/// 
+/// - Decay: Weight of the old shadow value in each update, e.g. 0.999
+/// 
+/// shadow = decay * shadow + (1 - decay) * w
+#[derive(Clone, Debug)]
+pub struct Ema {
+    pub decay: f32,
+    shadow: Vec<Vec<f32>>,
+}
+
+impl Ema {
+    /// - W: Weight Tensors, copied as the initial shadow values.
+    /// - Decay: Hyperparameter
+    pub fn new(w: &[&[f32]], decay: f32) -> Self {
+        Self {
+            decay,
+            shadow: w.iter().map(|w| w.to_vec()).collect(),
+        }
+    }
+
+    /// The shadow weights, one per parameter.
+    pub fn shadow(&self) -> &[Vec<f32>] {
+        &self.shadow
+    }
+
+    /// # EMA Update
+    /// - W: Weight Tensors, usually right after an optimizer step.
+    pub fn update(&mut self, w: &[&[f32]]) -> Result<(), BMLSError> {
+        check_shapes(&self.shadow, w.iter().map(|w| w.len()), w.len())?;
+
+        let decay = self.decay;
+        for (shadow, w) in izip!(&mut self.shadow, w) {
+            for (shadow, w) in izip!(shadow, w.iter()) {
+                *shadow = decay * *shadow + (1. - decay) * w;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// # Copy To
+    /// - W: Tensors to overwrite with the shadow weights
+    pub fn copy_to(&self, w: &mut [&mut [f32]]) -> Result<(), BMLSError> {
+        check_shapes(&self.shadow, w.iter().map(|w| w.len()), w.len())?;
+
+        for (shadow, w) in izip!(&self.shadow, w) {
+            w.copy_from_slice(shadow);
+        }
+
+        Ok(())
+    }
+
+    /// # Swap
+    /// - W: Weight Tensors
+    /// 
+    /// Exchanges W with the shadow weights. Call once before 
+    /// evaluation and again after to restore the training weights.
+    pub fn swap(&mut self, w: &mut [&mut [f32]]) -> Result<(), BMLSError> {
+        check_shapes(&self.shadow, w.iter().map(|w| w.len()), w.len())?;
+
+        for (shadow, w) in izip!(&mut self.shadow, w) {
+            shadow.swap_with_slice(w);
+        }
+
+        Ok(())
+    }
+}
+
+/// Ensures the tensors with Lens match the shadow tensors. 
+pub(crate) fn check_shapes(
+    shadow: &[Vec<f32>],
+    lens: impl Iterator<Item = usize>,
+    count: usize,
+) -> Result<(), BMLSError> {
+    if count != shadow.len() {
+        return error::length_mismatch("W", count, "Shadow", shadow.len())
+    }
+
+    for (shadow, len) in izip!(shadow, lens) {
+        if len != shadow.len() {
+            return error::length_mismatch("W", len, "Shadow", shadow.len())
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_ema() {
+        let mut w = vec![0.0, 10.0];
+        let mut ema = Ema::new(&[&w], 0.5);
+
+        w.copy_from_slice(&[4.0, 2.0]);
+        ema.update(&[&w]).unwrap();
+        assert_eq!(ema.shadow()[0], [2.0, 6.0]);
+
+        ema.swap(&mut [&mut w]).unwrap();
+        assert_eq!(w, [2.0, 6.0]);
+        ema.swap(&mut [&mut w]).unwrap();
+        assert_eq!(w, [4.0, 2.0]);
+
+        assert!(ema.update(&[&w[..1]]).is_err());
+    }
+}
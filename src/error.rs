@@ -9,6 +9,12 @@ pub enum BMLSError {
     InvalidKernelDim(usize, usize, usize, usize),
     #[error("Invalid Strides. Strides cannot be Zero! (strides: {0}, {1})")]
     InvalidStrides(usize, usize),
+    #[error("Invalid Dilation. Dilations cannot be Zero! (dilation: {0}, {1})")]
+    InvalidDilation(usize, usize),
+    #[error("Invalid Output Padding. Output padding must be smaller than the stride! (output padding: {0}, {1})")]
+    InvalidOutputPadding(usize, usize),
+    #[error("Invalid Padding. Padding cannot remove the whole output! (padding: {0}, {1})")]
+    InvalidPadding(usize, usize),
     #[error("Axis {0} of {1} with len {2} must match axis {3} of {4} with len {5}")]
     AxisMismatch(usize, String, usize, usize, String, usize),
     #[error("The Dropout Rate must be between 0 and 1! (rate: {0}")]
@@ -17,12 +23,50 @@ pub enum BMLSError {
     InvalidLRNSize(usize),
     #[error("The '0'(or N) dimension of {0} must match the '1'(or C) dimension of {2}. ({0} len: {1}) ({2} len: {3})")]
     Col2ImChannelMismatch(String, usize, String, usize),
+    #[error("The Timestep must start at 1! (t: {0})")]
+    InvalidTimestep(usize),
+    #[error("The clipping Min must not be greater than Max! (min: {0}, max: {1})")]
+    InvalidClipRange(f32, f32),
+    #[error("The clipping Norm must be greater than zero! (norm: {0})")]
+    InvalidClipNorm(f32),
+    #[error("Axis {0} is out of bounds for a 4 dimensional tensor.")]
+    InvalidAxis(usize),
+    #[error("Class index {0} is out of bounds for {1} classes.")]
+    InvalidClassIndex(usize, usize),
+    #[error("Label Smoothing must be between 0 and 1! (smoothing: {0})")]
+    InvalidLabelSmoothing(f32),
+    #[error("Huber Delta must be greater than zero! (delta: {0})")]
+    InvalidHuberDelta(f32),
+    #[error("The Quantile must be between 0 and 1! (q: {0})")]
+    InvalidQuantile(f32),
+    #[error("Temperature must be greater than zero! (temperature: {0})")]
+    InvalidTemperature(f32),
+    #[error("Beta must be at least 0 and less than 1! (beta: {0})")]
+    InvalidBeta(f32),
+    #[error("CTC sample {0} has input length {1}, but needs at least {2} and at most {3} timesteps.")]
+    InvalidCtcLength(usize, usize, usize, usize),
+    #[error("CTC sample {0} has the blank index {1} in its targets.")]
+    InvalidCtcTarget(usize, usize),
+    #[error("Groups must not be zero and must divide the {1} channels evenly! (groups: {0})")]
+    InvalidGroups(usize, usize),
+    #[error("Lookahead K must not be zero! (k: {0})")]
+    InvalidLookaheadSteps(usize),
+    #[error("Failed to read or write the checkpoint: {0}")]
+    CheckpointIo(#[from] std::io::Error),
+    #[error("The checkpoint is corrupt: {0}")]
+    CheckpointCorrupt(String),
+    #[error("Unsupported checkpoint version {0}, expected {1}.")]
+    CheckpointVersion(u32, u32),
+    #[error("Checkpoint checksum {0:#010x} does not match the computed checksum {1:#010x}.")]
+    CheckpointChecksum(u32, u32),
+    #[error("The checkpoint {0} do not match the tensors to restore. (checkpoint: {1}, tensors: {2})")]
+    CheckpointMismatch(String, usize, usize),
     #[cfg(feature = "ndarray")]
     #[error("Failed to convert Array4 with name {0} to slice!")]
     NdarraySliceError(String),
 }
 
-pub(crate) fn length_mismatch(a_name: &str, a_len: usize, b_name: &str, b_len: usize) -> Result<(), BMLSError> {
+pub(crate) fn length_mismatch<T>(a_name: &str, a_len: usize, b_name: &str, b_len: usize) -> Result<T, BMLSError> {
     Err(BMLSError::LengthMismatch(a_name.to_owned(), a_len, b_name.to_owned(), b_len))
 }
 
@@ -34,6 +78,18 @@ pub(crate) fn invalid_strides(h: usize, w: usize) -> Result<(), BMLSError> {
     Err(BMLSError::InvalidStrides(h, w))
 }
 
+pub(crate) fn invalid_dilation(h: usize, w: usize) -> Result<(), BMLSError> {
+    Err(BMLSError::InvalidDilation(h, w))
+}
+
+pub(crate) fn invalid_output_padding<T>(h: usize, w: usize) -> Result<T, BMLSError> {
+    Err(BMLSError::InvalidOutputPadding(h, w))
+}
+
+pub(crate) fn invalid_padding<T>(h: usize, w: usize) -> Result<T, BMLSError> {
+    Err(BMLSError::InvalidPadding(h, w))
+}
+
 pub(crate) fn axis_mismatch(
     a_axis: usize, a_name: &str, a_len: usize, 
     b_axis: usize, b_name: &str, b_len: usize
@@ -51,4 +107,76 @@ pub(crate) fn invalid_lrn_size(size: usize) -> Result<(), BMLSError> {
 
 pub(crate) fn col2im_channel_mismatch(a_name: &str, a_len: usize, b_name: &str, b_len: usize) -> Result<(), BMLSError> {
     Err(BMLSError::Col2ImChannelMismatch(a_name.to_owned(), a_len, b_name.to_owned(), b_len))
-}
\ No newline at end of file
+}
+
+pub(crate) fn invalid_timestep(t: usize) -> Result<(), BMLSError> {
+    Err(BMLSError::InvalidTimestep(t))
+}
+
+pub(crate) fn invalid_clip_range(min: f32, max: f32) -> Result<(), BMLSError> {
+    Err(BMLSError::InvalidClipRange(min, max))
+}
+
+pub(crate) fn invalid_clip_norm<T>(norm: f32) -> Result<T, BMLSError> {
+    Err(BMLSError::InvalidClipNorm(norm))
+}
+
+pub(crate) fn invalid_axis<T>(axis: usize) -> Result<T, BMLSError> {
+    Err(BMLSError::InvalidAxis(axis))
+}
+
+pub(crate) fn invalid_class_index(index: usize, classes: usize) -> Result<(), BMLSError> {
+    Err(BMLSError::InvalidClassIndex(index, classes))
+}
+
+pub(crate) fn invalid_label_smoothing(smoothing: f32) -> Result<(), BMLSError> {
+    Err(BMLSError::InvalidLabelSmoothing(smoothing))
+}
+
+pub(crate) fn invalid_huber_delta(delta: f32) -> Result<(), BMLSError> {
+    Err(BMLSError::InvalidHuberDelta(delta))
+}
+
+pub(crate) fn invalid_quantile(q: f32) -> Result<(), BMLSError> {
+    Err(BMLSError::InvalidQuantile(q))
+}
+
+pub(crate) fn invalid_temperature(temperature: f32) -> Result<(), BMLSError> {
+    Err(BMLSError::InvalidTemperature(temperature))
+}
+
+pub(crate) fn invalid_beta(beta: f32) -> Result<(), BMLSError> {
+    Err(BMLSError::InvalidBeta(beta))
+}
+
+pub(crate) fn invalid_ctc_length(sample: usize, len: usize, min: usize, max: usize) -> Result<(), BMLSError> {
+    Err(BMLSError::InvalidCtcLength(sample, len, min, max))
+}
+
+pub(crate) fn invalid_ctc_target(sample: usize, blank: usize) -> Result<(), BMLSError> {
+    Err(BMLSError::InvalidCtcTarget(sample, blank))
+}
+
+pub(crate) fn invalid_groups<T>(groups: usize, channels: usize) -> Result<T, BMLSError> {
+    Err(BMLSError::InvalidGroups(groups, channels))
+}
+
+pub(crate) fn invalid_lookahead_steps(k: usize) -> Result<(), BMLSError> {
+    Err(BMLSError::InvalidLookaheadSteps(k))
+}
+
+pub(crate) fn checkpoint_corrupt<T>(reason: &str) -> Result<T, BMLSError> {
+    Err(BMLSError::CheckpointCorrupt(reason.to_owned()))
+}
+
+pub(crate) fn checkpoint_version<T>(version: u32) -> Result<T, BMLSError> {
+    Err(BMLSError::CheckpointVersion(version, crate::checkpoint::CHECKPOINT_VERSION))
+}
+
+pub(crate) fn checkpoint_checksum<T>(expected: u32, found: u32) -> Result<T, BMLSError> {
+    Err(BMLSError::CheckpointChecksum(expected, found))
+}
+
+pub(crate) fn checkpoint_mismatch<T>(name: &str, expected: usize, found: usize) -> Result<T, BMLSError> {
+    Err(BMLSError::CheckpointMismatch(name.to_owned(), expected, found))
+}
@@ -0,0 +1,290 @@
+use itertools::izip;
+use crate::error::BMLSError;
+use crate::error;
+use crate::cross_entropy::{log_sum_exp, sigmoid};
+use crate::reduction::{Reduction, reduce_rows};
+
+/// # Sigmoid Focal Loss (from logits)
+/// - T: Target probabilities, usually 0 or 1 (N x C)
+/// - X: Logits (N x C)
+/// - E: Error, (N x 1) vector for `Reduction::None`, otherwise (1)
+/// - G: Gradient of E w.r.t. X (N x C)
+/// - Dim: [N, C]
+/// - Gamma: Focusing parameter, 0 gives `binary_cross_entropy`. Usually 2.
+/// - Alpha: Optional weight of the positive class, the negative class gets 1 - alpha. Usually 0.25.
+/// - Sample_Weights: Optional weight for each row (N)
+/// - Reduction: How the rows are combined into E
+///
+/// Each column is an independent binary classification, and the error of
+/// each row is the mean over C. With p = sigmoid(x), pt = p * t + (1 - p) * (1 - t)
+/// and at = alpha * t + (1 - alpha) * (1 - t): \
+/// e = at * (1 - pt)^gamma * bce(x, t)
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn sigmoid_focal(
+    t: &[f32],
+    x: &[f32],
+    e: &mut [f32],
+    g: &mut [f32],
+    dim: [usize; 2],
+    gamma: f32,
+    alpha: Option<f32>,
+    sample_weights: Option<&[f32]>,
+    reduction: Reduction,
+) -> Result<(), BMLSError> {
+    let len = dim[0] * dim[1];
+    if t.len() != len {
+        return error::length_mismatch("T", t.len(), "Dim", len)
+    }
+
+    if x.len() != len {
+        return error::length_mismatch("X", x.len(), "Dim", len)
+    }
+
+    if g.len() != len {
+        return error::length_mismatch("G", g.len(), "Dim", len)
+    }
+
+    let cols = dim[1];
+    let scale = 1. / cols as f32;
+
+    reduce_rows(e, g, dim, sample_weights, reduction, |i, g| {
+        let t = &t[i * cols..(i + 1) * cols];
+        let x = &x[i * cols..(i + 1) * cols];
+
+        let mut sum = 0.0;
+        for (t, x, g) in izip!(t, x, g) {
+            let p = sigmoid(*x);
+            let at = alpha.map_or(1.0, |a| a * t + (1. - a) * (1. - t));
+            let ce = f32::max(*x, 0.0) - x * t + f32::ln_1p(f32::exp(-x.abs()));
+
+            // m = 1 - pt, dm/dx = -(2t - 1) * p * (1 - p)
+            let m = 1. - (p * t + (1. - p) * (1. - t));
+            let dm = -(2. * t - 1.) * p * (1. - p);
+
+            sum += at * m.powf(gamma) * ce;
+            *g = at * (m.powf(gamma) * (p - t) + focus_slope(m, gamma) * dm * ce) * scale;
+        }
+
+        Some(sum * scale)
+    })
+}
+
+/// # Softmax Focal Loss (from logits)
+/// - T: Target probabilities, usually one-hot (N x C)
+/// - X: Logits (N x C)
+/// - E: Error, (N x 1) vector for `Reduction::None`, otherwise (1)
+/// - G: Gradient of E w.r.t. X (N x C)
+/// - Dim: [N, C]
+/// - Gamma: Focusing parameter, 0 gives `cross_entropy`. Usually 2.
+/// - Alpha: Optional weight for each class (C), see `class_balanced_weights`
+/// - Sample_Weights: Optional weight for each row (N)
+/// - Reduction: How the rows are combined into E
+///
+/// With p = softmax(x): \
+/// e = -sum(alpha * t * (1 - p)^gamma * ln(p))
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn softmax_focal(
+    t: &[f32],
+    x: &[f32],
+    e: &mut [f32],
+    g: &mut [f32],
+    dim: [usize; 2],
+    gamma: f32,
+    alpha: Option<&[f32]>,
+    sample_weights: Option<&[f32]>,
+    reduction: Reduction,
+) -> Result<(), BMLSError> {
+    let len = dim[0] * dim[1];
+    if t.len() != len {
+        return error::length_mismatch("T", t.len(), "Dim", len)
+    }
+
+    if x.len() != len {
+        return error::length_mismatch("X", x.len(), "Dim", len)
+    }
+
+    if g.len() != len {
+        return error::length_mismatch("G", g.len(), "Dim", len)
+    }
+
+    if let Some(a) = alpha {
+        if a.len() != dim[1] {
+            return error::length_mismatch("Alpha", a.len(), "Dim[1]", dim[1])
+        }
+    }
+
+    let cols = dim[1];
+
+    reduce_rows(e, g, dim, sample_weights, reduction, |i, g| {
+        let t = &t[i * cols..(i + 1) * cols];
+        let x = &x[i * cols..(i + 1) * cols];
+
+        let lse = log_sum_exp(x);
+
+        // with f = -a * (1 - p)^gamma * ln(p), u = p * df/dp
+        // and the gradient is u - p * sum(u)
+        let mut loss = 0.0;
+        let mut total = 0.0;
+        for (j, (t, x, g)) in izip!(t, x, g.iter_mut()).enumerate() {
+            let a = alpha.map_or(1.0, |a| a[j]) * t;
+            let lp = x - lse;
+            let p = f32::exp(lp);
+            let m = 1. - p;
+
+            loss -= a * m.powf(gamma) * lp;
+            *g = a * (focus_slope(m, gamma) * p * lp - m.powf(gamma));
+            total += *g;
+        }
+
+        for (x, g) in izip!(x, g) {
+            *g -= f32::exp(x - lse) * total;
+        }
+
+        Some(loss)
+    })
+}
+
+/// gamma * m^(gamma - 1), the derivative of m^gamma,
+/// taken as 0 where m == 0 so that gamma < 1 does not divide by zero.
+#[inline]
+fn focus_slope(m: f32, gamma: f32) -> f32 {
+    if m > 0.0 {
+        gamma * m.powf(gamma - 1.)
+    } else {
+        0.0
+    }
+}
+
+/// # Class-Balanced Weights
+/// - Counts: Number of samples of each class (C)
+/// - W: Weight for each class (C)
+/// - Beta: Between 0 and 1, usually 0.9 to 0.9999. 0 weights every class equally.
+///
+/// Weights each class by the inverse of its effective number of samples,
+/// (1 - beta^n) / (1 - beta), scaled so the weights sum to C.
+/// Classes with no samples get a weight of 0.
+/// The weights can be passed to the class Weights of the cross entropy
+/// losses, or the Alpha of `softmax_focal`.
+#[inline]
+pub fn class_balanced_weights(
+    counts: &[usize],
+    w: &mut [f32],
+    beta: f32,
+) -> Result<(), BMLSError> {
+    if counts.len() != w.len() {
+        return error::length_mismatch("Counts", counts.len(), "W", w.len())
+    }
+
+    if !(0.0..1.0).contains(&beta) {
+        return error::invalid_beta(beta)
+    }
+
+    let mut sum = 0.0;
+    for (n, w) in izip!(counts, w.iter_mut()) {
+        *w = if *n == 0 {
+            0.0
+        } else {
+            // as f32 rather than i32, so large counts do not wrap
+            (1. - beta) / (1. - beta.powf(*n as f32))
+        };
+        sum += *w;
+    }
+
+    if sum > 0.0 {
+        let scale = w.len() as f32 / sum;
+        for w in w.iter_mut() {
+            *w *= scale;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::cross_entropy::cross_entropy;
+
+    fn check_gradient<F>(x: [f32; 6], g: [f32; 6], f: F)
+    where
+        F: Fn(&[f32]) -> f32,
+    {
+        for i in 0..6 {
+            let mut xp = x;
+            let mut xm = x;
+            xp[i] += 1e-2;
+            xm[i] -= 1e-2;
+            let fd = (f(&xp) - f(&xm)) / 2e-2;
+            assert!((fd - g[i]).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_sigmoid_focal_gradient() {
+        let dim = [2, 3];
+        let t = [1.0, 0.0, 1.0, 0.0, 0.3, 1.0];
+        let x = [0.5, -1.0, 2.0, 1.5, 0.1, -0.7];
+
+        let mut e = [0.0];
+        let mut g = [0.0; 6];
+        sigmoid_focal(&t, &x, &mut e, &mut g, dim, 2.0, Some(0.25), None, Reduction::Sum).unwrap();
+
+        check_gradient(x, g, |x| {
+            let mut e = [0.0];
+            sigmoid_focal(&t, x, &mut e, &mut [0.0; 6], dim, 2.0, Some(0.25), None, Reduction::Sum).unwrap();
+            e[0]
+        });
+    }
+
+    #[test]
+    fn test_softmax_focal_gradient() {
+        let dim = [2, 3];
+        let t = [0.0, 1.0, 0.0, 0.2, 0.3, 0.5];
+        let x = [1.0, -2.0, 0.5, 3.0, 0.1, -0.4];
+        let a = [0.5, 2.0, 1.0];
+
+        let mut e = [0.0];
+        let mut g = [0.0; 6];
+        softmax_focal(&t, &x, &mut e, &mut g, dim, 2.0, Some(&a), None, Reduction::Sum).unwrap();
+
+        check_gradient(x, g, |x| {
+            let mut e = [0.0];
+            softmax_focal(&t, x, &mut e, &mut [0.0; 6], dim, 2.0, Some(&a), None, Reduction::Sum).unwrap();
+            e[0]
+        });
+
+        // gamma = 0 is the cross entropy
+        let mut e2 = [0.0];
+        let mut g2 = [0.0; 6];
+        softmax_focal(&t, &x, &mut e, &mut g, dim, 0.0, Some(&a), None, Reduction::Sum).unwrap();
+        cross_entropy(&t, &x, &mut e2, &mut g2, dim, Some(&a), 0.0, None, Reduction::Sum).unwrap();
+        assert!((e[0] - e2[0]).abs() < 1e-5);
+        for (g, g2) in izip!(&g, &g2) {
+            assert!((g - g2).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_class_balanced_weights() {
+        let mut w = [0.0; 3];
+        class_balanced_weights(&[1000, 10, 0], &mut w, 0.99).unwrap();
+
+        assert!(w[1] > w[0]);
+        assert_eq!(w[2], 0.0);
+        assert!((w.iter().sum::<f32>() - 3.0).abs() < 1e-5);
+
+        // beta = 0 weights the seen classes equally
+        class_balanced_weights(&[1000, 10, 1], &mut w, 0.0).unwrap();
+        assert_eq!(w, [1.0; 3]);
+
+        // counts past i32::MAX weigh like any other large count
+        class_balanced_weights(&[usize::MAX, 3_000_000_000, 1], &mut w, 0.9).unwrap();
+        assert!(w.iter().all(|w| w.is_finite() && *w > 0.0));
+        assert!((w[0] - w[1]).abs() < 1e-6 && w[2] > w[0]);
+
+        assert!(class_balanced_weights(&[1, 2, 3], &mut w, 1.0).is_err());
+    }
+}
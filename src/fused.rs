@@ -0,0 +1,337 @@
+use itertools::izip;
+use rayon::prelude::*;
+use crate::error::BMLSError;
+use crate::error;
+use crate::WeightDecay;
+use crate::sgd::sgd_update;
+use crate::momentum::momentum_update;
+use crate::rms_prop::rms_prop_update;
+use crate::adam::AdamStep;
+
+/// Number of elements updated by one parallel task. Large tensors
+/// are split into several tasks, small tensors are one task each.
+const CHUNK: usize = 4096;
+
+/// # SGD Parameter Group
+/// - G: Gradient w.r.t. W.
+/// - W: Weight Tensor
+/// - Decay: Weight Decay for this tensor
+pub struct SgdGroup<'a> {
+    pub g: &'a [f32],
+    pub w: &'a mut [f32],
+    pub decay: WeightDecay,
+}
+
+/// # Fused SGD
+/// - Groups: Parameter groups to update
+/// - LR: Learning Rate
+///
+/// Updates every group in one parallel pass, with the same
+/// update as `sgd`. Nothing is modified if any lengths do not match.
+pub fn fused_sgd(
+    groups: &mut [SgdGroup],
+    lr: f32,
+) -> Result<(), BMLSError> {
+    for group in groups.iter() {
+        if group.g.len() != group.w.len() {
+            return error::length_mismatch("G", group.g.len(), "W", group.w.len())
+        }
+    }
+
+    let mut tasks = Vec::new();
+    for group in groups.iter_mut() {
+        let (l2, wd) = group.decay.coefficients();
+        for (g, w) in izip!(group.g.chunks(CHUNK), group.w.chunks_mut(CHUNK)) {
+            tasks.push((g, w, l2, wd));
+        }
+    }
+
+    tasks.into_par_iter().for_each(|(g, w, l2, wd)| {
+        for (g, w) in izip!(g, w) {
+            sgd_update(*g, w, lr, l2, wd);
+        }
+    });
+
+    Ok(())
+}
+
+/// # Momentum Parameter Group
+/// - G: Gradient w.r.t. W.
+/// - V: Exponentially weighted average of Past gradients
+/// - W: Weight Tensor
+/// - Decay: Weight Decay for this tensor
+pub struct MomentumGroup<'a> {
+    pub g: &'a [f32],
+    pub v: &'a mut [f32],
+    pub w: &'a mut [f32],
+    pub decay: WeightDecay,
+}
+
+/// # Fused Momentum
+/// - Groups: Parameter groups to update
+/// - LR: Learning Rate
+/// - Beta: Hyperparameter
+///
+/// Updates every group in one parallel pass, with the same
+/// update as `momentum`. Nothing is modified if any lengths do not match.
+pub fn fused_momentum(
+    groups: &mut [MomentumGroup],
+    lr: f32,
+    beta: f32,
+) -> Result<(), BMLSError> {
+    for group in groups.iter() {
+        if group.g.len() != group.v.len() {
+            return error::length_mismatch("G", group.g.len(), "V", group.v.len())
+        }
+
+        if group.v.len() != group.w.len() {
+            return error::length_mismatch("V", group.v.len(), "W", group.w.len())
+        }
+    }
+
+    let mut tasks = Vec::new();
+    for group in groups.iter_mut() {
+        let (l2, wd) = group.decay.coefficients();
+        let chunks = izip!(
+            group.g.chunks(CHUNK),
+            group.v.chunks_mut(CHUNK),
+            group.w.chunks_mut(CHUNK),
+        );
+
+        for (g, v, w) in chunks {
+            tasks.push((g, v, w, l2, wd));
+        }
+    }
+
+    tasks.into_par_iter().for_each(|(g, v, w, l2, wd)| {
+        for (g, v, w) in izip!(g, v, w) {
+            momentum_update(*g, v, w, lr, beta, l2, wd);
+        }
+    });
+
+    Ok(())
+}
+
+/// # RMS_Prop Parameter Group
+/// - G: Gradient w.r.t. W.
+/// - S: Exponentially weighted average of Past squares of gradients
+/// - W: Weight Tensor
+/// - Decay: Weight Decay for this tensor
+pub struct RmsPropGroup<'a> {
+    pub g: &'a [f32],
+    pub s: &'a mut [f32],
+    pub w: &'a mut [f32],
+    pub decay: WeightDecay,
+}
+
+/// # Fused RMS_Prop
+/// - Groups: Parameter groups to update
+/// - LR: Learning Rate
+/// - Beta: Hyperparameter
+///
+/// Updates every group in one parallel pass, with the same
+/// update as `rms_prop`. Nothing is modified if any lengths do not match.
+pub fn fused_rms_prop(
+    groups: &mut [RmsPropGroup],
+    lr: f32,
+    beta: f32,
+) -> Result<(), BMLSError> {
+    for group in groups.iter() {
+        if group.g.len() != group.s.len() {
+            return error::length_mismatch("G", group.g.len(), "S", group.s.len())
+        }
+
+        if group.s.len() != group.w.len() {
+            return error::length_mismatch("S", group.s.len(), "W", group.w.len())
+        }
+    }
+
+    let mut tasks = Vec::new();
+    for group in groups.iter_mut() {
+        let (l2, wd) = group.decay.coefficients();
+        let chunks = izip!(
+            group.g.chunks(CHUNK),
+            group.s.chunks_mut(CHUNK),
+            group.w.chunks_mut(CHUNK),
+        );
+
+        for (g, s, w) in chunks {
+            tasks.push((g, s, w, l2, wd));
+        }
+    }
+
+    tasks.into_par_iter().for_each(|(g, s, w, l2, wd)| {
+        for (g, s, w) in izip!(g, s, w) {
+            rms_prop_update(*g, s, w, lr, beta, l2, wd);
+        }
+    });
+
+    Ok(())
+}
+
+/// # Adam Parameter Group
+/// - G: Gradient w.r.t. W
+/// - V: Exponentialy weighted average of past gradients
+/// - S: Exponentialy weighted average of past squares of gradients
+/// - W: Weight Tensor
+/// - Decay: Weight Decay for this tensor
+pub struct AdamGroup<'a> {
+    pub g: &'a [f32],
+    pub v: &'a mut [f32],
+    pub s: &'a mut [f32],
+    pub w: &'a mut [f32],
+    pub decay: WeightDecay,
+}
+
+/// # Fused Adam
+/// - Groups: Parameter groups to update
+/// - Lr: Learning Rate
+/// - Beta1: Hyperparameter,
+/// - Beta2: Hyperparameter,
+/// - Epsilon: Added to the denominator for numerical stability
+/// - T: Timestep of this update, starting at 1.
+///
+/// Updates every group in one parallel pass, with the same
+/// update as `adam`. Nothing is modified if any lengths do not match.
+pub fn fused_adam(
+    groups: &mut [AdamGroup],
+    lr: f32,
+    beta1: f32,
+    beta2: f32,
+    epsilon: f32,
+    t: usize,
+) -> Result<(), BMLSError> {
+    for group in groups.iter() {
+        if group.g.len() != group.v.len() {
+            return error::length_mismatch("G", group.g.len(), "V", group.v.len())
+        }
+
+        if group.v.len() != group.s.len() {
+            return error::length_mismatch("V", group.v.len(), "S", group.s.len())
+        }
+
+        if group.s.len() != group.w.len() {
+            return error::length_mismatch("S", group.s.len(), "W", group.w.len())
+        }
+    }
+
+    if t == 0 {
+        return error::invalid_timestep(t)
+    }
+
+    let step = AdamStep::new(lr, beta1, beta2, epsilon, t);
+
+    let mut tasks = Vec::new();
+    for group in groups.iter_mut() {
+        let (l2, wd) = group.decay.coefficients();
+        let chunks = izip!(
+            group.g.chunks(CHUNK),
+            group.v.chunks_mut(CHUNK),
+            group.s.chunks_mut(CHUNK),
+            group.w.chunks_mut(CHUNK),
+        );
+
+        for (g, v, s, w) in chunks {
+            tasks.push((g, v, s, w, l2, wd));
+        }
+    }
+
+    tasks.into_par_iter().for_each(|(g, v, s, w, l2, wd)| {
+        for (g, v, s, w) in izip!(g, v, s, w) {
+            step.update(*g, v, s, w, l2, wd);
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::sgd::sgd;
+    use crate::momentum::momentum;
+    use crate::rms_prop::rms_prop;
+    use crate::adam::adam;
+
+    // tensors of several sizes, one larger than a chunk
+    fn tensors(seed: f32) -> Vec<Vec<f32>> {
+        [3, 1, CHUNK * 2 + 17, 64].iter()
+            .map(|len| (0..*len).map(|i| f32::sin(i as f32 * seed)).collect())
+            .collect()
+    }
+
+    fn decay(i: usize) -> WeightDecay {
+        [WeightDecay::None, WeightDecay::L2(0.1), WeightDecay::Decoupled(0.01)][i % 3]
+    }
+
+    #[test]
+    fn test_fused_matches_serial() {
+        let g = tensors(0.37);
+        let mut w1 = tensors(1.3);
+        let mut w2 = w1.clone();
+        let mut v1 = tensors(0.5);
+        let mut v2 = v1.clone();
+        let mut s1 = g.iter().map(|g| vec![0.5; g.len()]).collect::<Vec<_>>();
+        let mut s2 = s1.clone();
+
+        // sgd
+        for (i, (g, w)) in izip!(&g, &mut w1).enumerate() {
+            sgd(g, w, 0.1, decay(i)).unwrap();
+        }
+        let mut groups = izip!(&g, &mut w2).enumerate()
+            .map(|(i, (g, w))| SgdGroup { g, w, decay: decay(i) })
+            .collect::<Vec<_>>();
+        fused_sgd(&mut groups, 0.1).unwrap();
+        assert_eq!(w1, w2);
+
+        // momentum
+        for (i, (g, v, w)) in izip!(&g, &mut v1, &mut w1).enumerate() {
+            momentum(g, v, w, 0.1, 0.9, decay(i)).unwrap();
+        }
+        let mut groups = izip!(&g, &mut v2, &mut w2).enumerate()
+            .map(|(i, (g, v, w))| MomentumGroup { g, v, w, decay: decay(i) })
+            .collect::<Vec<_>>();
+        fused_momentum(&mut groups, 0.1, 0.9).unwrap();
+        assert_eq!(v1, v2);
+        assert_eq!(w1, w2);
+
+        // rms_prop
+        for (i, (g, s, w)) in izip!(&g, &mut s1, &mut w1).enumerate() {
+            rms_prop(g, s, w, 0.1, 0.9, decay(i)).unwrap();
+        }
+        let mut groups = izip!(&g, &mut s2, &mut w2).enumerate()
+            .map(|(i, (g, s, w))| RmsPropGroup { g, s, w, decay: decay(i) })
+            .collect::<Vec<_>>();
+        fused_rms_prop(&mut groups, 0.1, 0.9).unwrap();
+        assert_eq!(s1, s2);
+        assert_eq!(w1, w2);
+
+        // adam
+        for (i, (g, v, s, w)) in izip!(&g, &mut v1, &mut s1, &mut w1).enumerate() {
+            adam(g, v, s, w, 0.1, 0.9, 0.999, 1e-8, decay(i), 3).unwrap();
+        }
+        let mut groups = izip!(&g, &mut v2, &mut s2, &mut w2).enumerate()
+            .map(|(i, (g, v, s, w))| AdamGroup { g, v, s, w, decay: decay(i) })
+            .collect::<Vec<_>>();
+        fused_adam(&mut groups, 0.1, 0.9, 0.999, 1e-8, 3).unwrap();
+        assert_eq!(v1, v2);
+        assert_eq!(s1, s2);
+        assert_eq!(w1, w2);
+    }
+
+    #[test]
+    fn test_fused_mismatch() {
+        let g = vec![1.0; 4];
+        let mut w1 = vec![0.0; 4];
+        let mut w2 = vec![0.0; 3];
+
+        let mut groups = [
+            SgdGroup { g: &g, w: &mut w1, decay: WeightDecay::None },
+            SgdGroup { g: &g, w: &mut w2, decay: WeightDecay::None },
+        ];
+
+        assert!(fused_sgd(&mut groups, 0.1).is_err());
+        assert_eq!(w1, [0.0; 4]);
+    }
+}
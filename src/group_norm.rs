@@ -0,0 +1,333 @@
+use rayon::prelude::*;
+use crate::error::BMLSError;
+use crate::error;
+use crate::layer_norm::{check_params, normalize, normalize_wrt_x};
+
+/// # Group Normalization
+/// - X: Input (N x C x H x W)
+/// - Y: Output (N x C x H x W)
+/// - Gamma: Scale (C)
+/// - Beta: Shift (C)
+/// - Mean: Mean of each group, saved for the backward pass (N x Groups)
+/// - Inv_Std: 1 / sqrt(var + epsilon) of each group, saved for the backward pass (N x Groups)
+/// - X_dim: Dimensions of X.
+/// - Groups: Number of groups to split the channels into. Must divide C.
+/// - Epsilon: Added to the variance for numerical stability
+///
+/// Each sample's channels are split into Groups groups of C / Groups
+/// channels, and each group is normalized over its channels, H and W. \
+/// 1 group is `layer_norm` over C, H and W with per-channel affine parameters,
+/// and C groups is `instance_norm`.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn group_norm(
+    x: &[f32],
+    y: &mut [f32],
+    gamma: &[f32],
+    beta: &[f32],
+    mean: &mut [f32],
+    inv_std: &mut [f32],
+    x_dim: [usize; 4],
+    groups: usize,
+    epsilon: f32,
+) -> Result<(), BMLSError> {
+    let size = check_dims(x, x_dim, groups)?;
+
+    if y.len() != x.len() {
+        return error::length_mismatch("Y", y.len(), "X", x.len());
+    }
+
+    check_params(&[("Gamma", gamma.len()), ("Beta", beta.len())], "X_Dim[1]", x_dim[1])?;
+    check_params(&[("Mean", mean.len()), ("Inv_Std", inv_std.len())], "N x Groups", x_dim[0] * groups)?;
+
+    let channel = channel_of(x_dim, groups);
+    normalize(x, y, mean, inv_std, size, epsilon, |i, j| {
+        let c = channel(i, j);
+        (gamma[c], beta[c])
+    });
+
+    Ok(())
+}
+
+/// # Group Normalization w.r.t. X
+/// - X: Input (N x C x H x W)
+/// - GY: Gradient w.r.t. Y
+/// - GX: Gradient w.r.t. X
+/// - Gamma: Scale (C)
+/// - Mean: Mean saved by `group_norm` (N x Groups)
+/// - Inv_Std: Inv_Std saved by `group_norm` (N x Groups)
+/// - X_dim: Dimensions of X.
+/// - Groups: Number of groups the channels are split into.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn group_norm_wrt_x(
+    x: &[f32],
+    gy: &[f32],
+    gx: &mut [f32],
+    gamma: &[f32],
+    mean: &[f32],
+    inv_std: &[f32],
+    x_dim: [usize; 4],
+    groups: usize,
+) -> Result<(), BMLSError> {
+    let size = check_dims(x, x_dim, groups)?;
+
+    if gy.len() != x.len() {
+        return error::length_mismatch("GY", gy.len(), "X", x.len());
+    }
+
+    if gx.len() != x.len() {
+        return error::length_mismatch("GX", gx.len(), "X", x.len());
+    }
+
+    check_params(&[("Gamma", gamma.len())], "X_Dim[1]", x_dim[1])?;
+    check_params(&[("Mean", mean.len()), ("Inv_Std", inv_std.len())], "N x Groups", x_dim[0] * groups)?;
+
+    let channel = channel_of(x_dim, groups);
+    normalize_wrt_x(x, gy, gx, mean, inv_std, size, |i, j| gamma[channel(i, j)]);
+
+    Ok(())
+}
+
+/// # Group Normalization w.r.t. Gamma
+/// - X: Input (N x C x H x W)
+/// - GY: Gradient w.r.t. Y
+/// - G_Gamma: Gradient w.r.t. Gamma (C)
+/// - Mean: Mean saved by `group_norm` (N x Groups)
+/// - Inv_Std: Inv_Std saved by `group_norm` (N x Groups)
+/// - X_dim: Dimensions of X.
+/// - Groups: Number of groups the channels are split into.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn group_norm_wrt_gamma(
+    x: &[f32],
+    gy: &[f32],
+    g_gamma: &mut [f32],
+    mean: &[f32],
+    inv_std: &[f32],
+    x_dim: [usize; 4],
+    groups: usize,
+) -> Result<(), BMLSError> {
+    check_dims(x, x_dim, groups)?;
+
+    if gy.len() != x.len() {
+        return error::length_mismatch("GY", gy.len(), "X", x.len());
+    }
+
+    check_params(&[("G_Gamma", g_gamma.len())], "X_Dim[1]", x_dim[1])?;
+    check_params(&[("Mean", mean.len()), ("Inv_Std", inv_std.len())], "N x Groups", x_dim[0] * groups)?;
+
+    let (nx, cx, hx, wx) = (x_dim[0], x_dim[1], x_dim[2], x_dim[3]);
+    let plane = hx * wx;
+    let per_group = cx / groups;
+
+    g_gamma.par_iter_mut().enumerate().for_each(|(c, gg)| {
+        for n in 0..nx {
+            let i = n * groups + c / per_group;
+            let (mu, inv) = (mean[i], inv_std[i]);
+            let range = (n * cx + c) * plane..(n * cx + c + 1) * plane;
+            for (x, gy) in x[range.clone()].iter().zip(&gy[range]) {
+                *gg += gy * (x - mu) * inv;
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// # Group Normalization w.r.t. Beta
+/// - GY: Gradient w.r.t. Y (N x C x H x W)
+/// - G_Beta: Gradient w.r.t. Beta (C)
+/// - X_dim: Dimensions of X.
+#[inline]
+pub fn group_norm_wrt_beta(
+    gy: &[f32],
+    g_beta: &mut [f32],
+    x_dim: [usize; 4],
+) -> Result<(), BMLSError> {
+    let (nx, cx, hx, wx) = (x_dim[0], x_dim[1], x_dim[2], x_dim[3]);
+
+    let len = nx*cx*hx*wx;
+    if gy.len() != len {
+        return error::length_mismatch("GY", gy.len(), "X_Dim", len);
+    }
+
+    check_params(&[("G_Beta", g_beta.len())], "X_Dim[1]", cx)?;
+
+    let plane = hx * wx;
+
+    g_beta.par_iter_mut().enumerate().for_each(|(c, gb)| {
+        for n in 0..nx {
+            *gb += gy[(n * cx + c) * plane..(n * cx + c + 1) * plane].iter().sum::<f32>();
+        }
+    });
+
+    Ok(())
+}
+
+/// # Instance Normalization
+/// - X: Input (N x C x H x W)
+/// - Y: Output (N x C x H x W)
+/// - Gamma: Scale (C)
+/// - Beta: Shift (C)
+/// - Mean: Mean of each channel of each sample, saved for the backward pass (N x C)
+/// - Inv_Std: 1 / sqrt(var + epsilon) of each channel of each sample (N x C)
+/// - X_dim: Dimensions of X.
+/// - Epsilon: Added to the variance for numerical stability
+///
+/// Normalizes each channel of each sample over H and W.
+/// The same as `group_norm` with C groups.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn instance_norm(
+    x: &[f32],
+    y: &mut [f32],
+    gamma: &[f32],
+    beta: &[f32],
+    mean: &mut [f32],
+    inv_std: &mut [f32],
+    x_dim: [usize; 4],
+    epsilon: f32,
+) -> Result<(), BMLSError> {
+    group_norm(x, y, gamma, beta, mean, inv_std, x_dim, x_dim[1], epsilon)
+}
+
+/// # Instance Normalization w.r.t. X
+/// See `group_norm_wrt_x`, with C groups.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn instance_norm_wrt_x(
+    x: &[f32],
+    gy: &[f32],
+    gx: &mut [f32],
+    gamma: &[f32],
+    mean: &[f32],
+    inv_std: &[f32],
+    x_dim: [usize; 4],
+) -> Result<(), BMLSError> {
+    group_norm_wrt_x(x, gy, gx, gamma, mean, inv_std, x_dim, x_dim[1])
+}
+
+/// # Instance Normalization w.r.t. Gamma
+/// See `group_norm_wrt_gamma`, with C groups.
+#[inline]
+pub fn instance_norm_wrt_gamma(
+    x: &[f32],
+    gy: &[f32],
+    g_gamma: &mut [f32],
+    mean: &[f32],
+    inv_std: &[f32],
+    x_dim: [usize; 4],
+) -> Result<(), BMLSError> {
+    group_norm_wrt_gamma(x, gy, g_gamma, mean, inv_std, x_dim, x_dim[1])
+}
+
+/// # Instance Normalization w.r.t. Beta
+/// See `group_norm_wrt_beta`.
+#[inline]
+pub fn instance_norm_wrt_beta(
+    gy: &[f32],
+    g_beta: &mut [f32],
+    x_dim: [usize; 4],
+) -> Result<(), BMLSError> {
+    group_norm_wrt_beta(gy, g_beta, x_dim)
+}
+
+/// Checks X against X_dim and the Groups, and returns the size of a group.
+#[inline]
+fn check_dims(
+    x: &[f32],
+    x_dim: [usize; 4],
+    groups: usize,
+) -> Result<usize, BMLSError> {
+    let (nx, cx, hx, wx) = (x_dim[0], x_dim[1], x_dim[2], x_dim[3]);
+
+    let len = nx*cx*hx*wx;
+    if x.len() != len {
+        return error::length_mismatch("X", x.len(), "X_Dim", len);
+    }
+
+    if groups == 0 || cx / groups * groups != cx {
+        return error::invalid_groups(groups, cx);
+    }
+
+    Ok(cx / groups * hx * wx)
+}
+
+/// Maps (group index, index in the group) to the channel.
+#[inline]
+fn channel_of(x_dim: [usize; 4], groups: usize) -> impl Fn(usize, usize) -> usize + Sync {
+    let per_group = x_dim[1] / groups;
+    let plane = (x_dim[2] * x_dim[3]).max(1);
+    move |i, j| (i % groups) * per_group + j / plane
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const DIM: [usize; 4] = [2, 4, 2, 1];
+
+    #[test]
+    fn test_group_norm_gradient() {
+        let x: Vec<f32> = (0..16).map(|i| f32::sin(i as f32 * 1.3) * 3.0 + i as f32 * 0.1).collect();
+        let gamma = [1.0, 0.5, -2.0, 1.5];
+        let beta = [0.1, 0.2, 0.3, 0.4];
+        let r: Vec<f32> = (0..16).map(|i| f32::cos(i as f32)).collect();
+
+        let f = |x: &[f32], gamma: &[f32], beta: &[f32]| {
+            let mut y = vec![0.0; 16];
+            group_norm(x, &mut y, gamma, beta, &mut [0.0; 4], &mut [0.0; 4], DIM, 2, 1e-5).unwrap();
+            y.iter().zip(&r).map(|(y, r)| y * r).sum::<f32>()
+        };
+
+        let mut y = vec![0.0; 16];
+        let (mut m, mut s) = ([0.0; 4], [0.0; 4]);
+        group_norm(&x, &mut y, &gamma, &beta, &mut m, &mut s, DIM, 2, 1e-5).unwrap();
+
+        let mut gx = vec![0.0; 16];
+        let mut gg = [0.0; 4];
+        let mut gb = [0.0; 4];
+        group_norm_wrt_x(&x, &r, &mut gx, &gamma, &m, &s, DIM, 2).unwrap();
+        group_norm_wrt_gamma(&x, &r, &mut gg, &m, &s, DIM, 2).unwrap();
+        group_norm_wrt_beta(&r, &mut gb, DIM).unwrap();
+
+        for i in 0..16 {
+            let mut xp = x.clone();
+            let mut xm = x.clone();
+            xp[i] += 1e-2;
+            xm[i] -= 1e-2;
+            assert!(((f(&xp, &gamma, &beta) - f(&xm, &gamma, &beta)) / 2e-2 - gx[i]).abs() < 1e-2);
+        }
+
+        for c in 0..4 {
+            let (mut gp, mut gm) = (gamma, gamma);
+            gp[c] += 1e-2;
+            gm[c] -= 1e-2;
+            assert!(((f(&x, &gp, &beta) - f(&x, &gm, &beta)) / 2e-2 - gg[c]).abs() < 1e-2);
+
+            let (mut bp, mut bm) = (beta, beta);
+            bp[c] += 1e-2;
+            bm[c] -= 1e-2;
+            assert!(((f(&x, &gamma, &bp) - f(&x, &gamma, &bm)) / 2e-2 - gb[c]).abs() < 1e-2);
+        }
+
+        assert!(group_norm(&x, &mut y, &gamma, &beta, &mut m, &mut s, DIM, 3, 1e-5).is_err());
+    }
+
+    #[test]
+    fn test_instance_norm() {
+        let x: Vec<f32> = (0..16).map(|i| (i * i) as f32).collect();
+        let mut y = vec![0.0; 16];
+        let (mut m, mut s) = ([0.0; 8], [0.0; 8]);
+
+        instance_norm(&x, &mut y, &[1.0; 4], &[0.0; 4], &mut m, &mut s, DIM, 0.0).unwrap();
+
+        // every (sample, channel) plane of 2 becomes [-1, 1]
+        for plane in y.chunks(2) {
+            assert!((plane[0] + 1.0).abs() < 1e-5);
+            assert!((plane[1] - 1.0).abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use itertools::izip;
+use crate::error::BMLSError;
+use crate::error;
+use crate::reduction::{Reduction, reduce_rows};
+
+/// # Multi-Class Hinge Loss
+/// - T: Target class index of each row (N)
+/// - X: Scores (N x C)
+/// - E: Error, (N x 1) vector for `Reduction::None`, otherwise (1)
+/// - G: Gradient of E w.r.t. X (N x C)
+/// - Dim: [N, C]
+/// - Margin: Required gap between the target score and the others
+/// - Sample_Weights: Optional weight for each row (N)
+/// - Reduction: How the rows are combined into E
+///
+/// With y the target class: \
+/// e = sum over j != y of max(0, margin - x[y] + x[j]) / C
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn hinge(
+    t: &[usize],
+    x: &[f32],
+    e: &mut [f32],
+    g: &mut [f32],
+    dim: [usize; 2],
+    margin: f32,
+    sample_weights: Option<&[f32]>,
+    reduction: Reduction,
+) -> Result<(), BMLSError> {
+    multi_margin(t, x, e, g, dim, margin, false, sample_weights, reduction)
+}
+
+/// # Multi-Class Squared Hinge Loss
+/// - T: Target class index of each row (N)
+/// - X: Scores (N x C)
+/// - E: Error, (N x 1) vector for `Reduction::None`, otherwise (1)
+/// - G: Gradient of E w.r.t. X (N x C)
+/// - Dim: [N, C]
+/// - Margin: Required gap between the target score and the others
+/// - Sample_Weights: Optional weight for each row (N)
+/// - Reduction: How the rows are combined into E
+///
+/// With y the target class: \
+/// e = sum over j != y of max(0, margin - x[y] + x[j])^2 / C
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn squared_hinge(
+    t: &[usize],
+    x: &[f32],
+    e: &mut [f32],
+    g: &mut [f32],
+    dim: [usize; 2],
+    margin: f32,
+    sample_weights: Option<&[f32]>,
+    reduction: Reduction,
+) -> Result<(), BMLSError> {
+    multi_margin(t, x, e, g, dim, margin, true, sample_weights, reduction)
+}
+
+#[inline]
+#[allow(clippy::too_many_arguments)]
+fn multi_margin(
+    t: &[usize],
+    x: &[f32],
+    e: &mut [f32],
+    g: &mut [f32],
+    dim: [usize; 2],
+    margin: f32,
+    squared: bool,
+    sample_weights: Option<&[f32]>,
+    reduction: Reduction,
+) -> Result<(), BMLSError> {
+    let len = dim[0] * dim[1];
+    if t.len() != dim[0] {
+        return error::length_mismatch("T", t.len(), "Dim[0]", dim[0])
+    }
+
+    if x.len() != len {
+        return error::length_mismatch("X", x.len(), "Dim", len)
+    }
+
+    if g.len() != len {
+        return error::length_mismatch("G", g.len(), "Dim", len)
+    }
+
+    let cols = dim[1];
+    for t in t {
+        if *t >= cols {
+            return error::invalid_class_index(*t, cols)
+        }
+    }
+
+    let scale = 1. / cols as f32;
+
+    reduce_rows(e, g, dim, sample_weights, reduction, |i, g| {
+        let y = t[i];
+        let x = &x[i * cols..(i + 1) * cols];
+        let xy = x[y];
+
+        let mut sum = 0.0;
+        let mut gy = 0.0;
+        for (j, (x, g)) in izip!(x, g.iter_mut()).enumerate() {
+            let m = margin - xy + x;
+
+            *g = if j == y || m <= 0.0 {
+                0.0
+            } else if squared {
+                sum += m * m;
+                2. * m * scale
+            } else {
+                sum += m;
+                scale
+            };
+
+            gy -= *g;
+        }
+
+        g[y] = gy;
+
+        Some(sum * scale)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_hinge() {
+        let dim = [2, 3];
+        let t = [0, 2];
+        let x = [2.0, 1.5, -1.0, 0.0, 0.5, 3.0];
+        let mut e = [0.0; 2];
+        let mut g = [0.0; 6];
+
+        hinge(&t, &x, &mut e, &mut g, dim, 1.0, None, Reduction::None).unwrap();
+        assert_eq!(e, [0.5 / 3., 0.0]);
+        assert_eq!(g, [-1. / 3., 1. / 3., 0.0, 0.0, 0.0, 0.0]);
+
+        squared_hinge(&t, &x, &mut e, &mut g, dim, 1.0, None, Reduction::None).unwrap();
+        assert_eq!(e, [0.25 / 3., 0.0]);
+        assert_eq!(g, [-1. / 3., 1. / 3., 0.0, 0.0, 0.0, 0.0]);
+
+        assert!(hinge(&[0, 3], &x, &mut e, &mut g, dim, 1.0, None, Reduction::None).is_err());
+    }
+}
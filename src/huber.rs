@@ -0,0 +1,92 @@
+use itertools::izip;
+use crate::error::BMLSError;
+use crate::error;
+use crate::reduction::{Reduction, reduce_rows};
+
+/// # Huber Loss (Smooth L1)
+/// - T: Truth Label (N x C)
+/// - P: Prediction (N x C)
+/// - E: Error, (N x 1) vector for `Reduction::None`, otherwise (1)
+/// - G: Gradient of E w.r.t. P (N x C)
+/// - Dim: [N, C]
+/// - Delta: Where the loss changes from quadratic to linear
+/// - Sample_Weights: Optional weight for each row (N)
+/// - Reduction: How the rows are combined into E
+/// 
+/// The error of each row is the mean over C.
+/// 
+/// With d = p - t: \
+/// 0.5 * d^2 where |d| <= delta, otherwise delta * (|d| - 0.5 * delta)
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn huber(
+    t: &[f32],
+    p: &[f32],
+    e: &mut [f32],
+    g: &mut [f32],
+    dim: [usize; 2],
+    delta: f32,
+    sample_weights: Option<&[f32]>,
+    reduction: Reduction,
+) -> Result<(), BMLSError> {
+    let len = dim[0] * dim[1];
+    if t.len() != len {
+        return error::length_mismatch("T", t.len(), "Dim", len)
+    }
+
+    if t.len() != p.len() {
+        return error::length_mismatch("T", t.len(), "P", p.len())
+    }
+
+    if g.len() != p.len() {
+        return error::length_mismatch("G", g.len(), "P", p.len())
+    }
+
+    if delta <= 0.0 || delta.is_nan() {
+        return error::invalid_huber_delta(delta)
+    }
+
+    let cols = dim[1];
+    let scale = 1. / cols as f32;
+
+    reduce_rows(e, g, dim, sample_weights, reduction, |i, g| {
+        let t = &t[i * cols..(i + 1) * cols];
+        let p = &p[i * cols..(i + 1) * cols];
+
+        let mut sum = 0.0;
+
+        for (t, p, g) in izip!(t, p, g) {
+            let d = p - t;
+
+            if d.abs() <= delta {
+                sum += 0.5 * d * d;
+                *g = d * scale;
+            } else {
+                sum += delta * (d.abs() - 0.5 * delta);
+                *g = delta * d.signum() * scale;
+            }
+        }
+
+        Some(sum * scale)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_huber() {
+        let t = [0.0, 0.0, 0.0, 0.0];
+        let p = [0.5, -3.0, 1.0, 0.0];
+        let mut e = [0.0; 2];
+        let mut g = [0.0; 4];
+
+        huber(&t, &p, &mut e, &mut g, [2, 2], 1.0, None, Reduction::None).unwrap();
+
+        assert_eq!(e, [(0.125 + 2.5) / 2., 0.25]);
+        assert_eq!(g, [0.25, -0.5, 0.5, 0.0]);
+        assert!(huber(&t, &p, &mut e, &mut g, [2, 2], 0.0, None, Reduction::None).is_err());
+    }
+}
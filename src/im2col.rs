@@ -12,11 +12,18 @@ use crate::Ptr;
 /// - Stride: h, w, strides of the filter
 /// - Padh: height padding
 /// - Padw: width padding
+/// - Dilation: h, w, spacing between the elements of the filter, 1 for none
+/// 
+/// The dilated filter covers dh * (fh - 1) + 1 rows and dw * (fw - 1) + 1 columns.
 /// 
 /// Y Height: fc * fh * fw
 /// 
-/// Y Width: (((xh - fh + (padh.0 + padh.1)) / strideh) + 1) * (((xw - fw + (padw.0 + padw.1)) / stridew) + 1) * xn
+/// Y Width: (((xh - (dh * (fh - 1) + 1) + (padh.0 + padh.1)) / strideh) + 1) * (((xw - (dw * (fw - 1) + 1) + (padw.0 + padw.1)) / stridew) + 1) * xn
+/// 
+/// The columns of each sample are contiguous, in row-major order of the filter positions.
+/// `fold` is the adjoint, summing the columns back into an image.
 #[inline]
+#[allow(clippy::too_many_arguments)]
 pub fn im2col(
     x: &[f32],
     y: &mut [f32],
@@ -25,12 +32,36 @@ pub fn im2col(
     stride: [usize; 2],
     padh: [usize; 2],
     padw: [usize; 2],
+    dilation: [usize; 2],
 ) -> Result<(), BMLSError> {
     let (nx, cx, hx, wx) = (x_dim[0], x_dim[1], x_dim[2], x_dim[3]);
     let (nf, cf, hf, wf) = (f_dim[0], f_dim[1], f_dim[2], f_dim[3]);
     let (strideh, stridew) = (stride[0], stride[1]);
-    let hstart = ((hx - hf + (padh[0] + padh[1])) / strideh) + 1;
-    let wstart = ((wx - wf + (padw[0] + padw[1])) / stridew) + 1;
+    let (dilh, dilw) = (dilation[0], dilation[1]);
+    // the extent of the dilated filter
+    let (hk, wk) = (dilh * hf.saturating_sub(1) + 1, dilw * wf.saturating_sub(1) + 1);
+
+    // the kernel dimensions cannot be 0 and the dilated kernel cannot
+    // be larger than the dimensions of the input + the padding.
+    if hf == 0 || hk > (hx+padh[0]+padh[1]) || 
+       wf == 0 || wk > (wx+padw[0]+padw[1]) ||
+       cf != cx || nf == 0
+    {
+        return error::invalid_kernel_dim(f_dim)
+    }
+
+    // strides must not be 0
+    if strideh == 0 || stridew == 0 {
+        return error::invalid_strides(strideh, stridew)
+    }
+
+    // dilations must not be 0
+    if dilh == 0 || dilw == 0 {
+        return error::invalid_dilation(dilh, dilw)
+    }
+
+    let hstart = ((hx + (padh[0] + padh[1]) - hk) / strideh) + 1;
+    let wstart = ((wx + (padw[0] + padw[1]) - wk) / stridew) + 1;
     // size of the output Y
     let (ny, cy) = (hf * wf * cf, hstart * wstart * nx);
 
@@ -46,28 +77,14 @@ pub fn im2col(
         return error::length_mismatch("Y", y.len(), "Y_dim", ylen);
     }
 
-    // the kernel dimensions cannot be 0 or greater than 
-    // the dimensions of the input + the padding.
-    if hf == 0 || hf >= (hx+padh[0]+padh[1]) || 
-       wf == 0 || wf >= (wx+padw[0]+padw[1]) ||
-       cf != cx || nf == 0
-    {
-        return error::invalid_kernel_dim(f_dim)
-    }
-
-    // strides must not be 0
-    if strideh == 0 || stridew == 0 {
-        return error::invalid_strides(strideh, stridew)
-    }
-
     let x = Ptr::new(x);
     let y = Ptr::new(y);
 
     (0..nx).into_par_iter().for_each(|n| {
         for h in 0..hstart {
             for w in 0..wstart {
-                // the column of Y we are in
-                let col = (h * wstart + w) * (n+1);
+                // the column of Y we are in, each sample is a block of columns
+                let col = (n * hstart + h) * wstart + w;
                 for c in 0..cx {
                     let xrow = h * strideh;
                     let xcol = w * stridew;
@@ -75,8 +92,8 @@ pub fn im2col(
                     let xi = n * cx * hx * wx + c * hx * wx;
                     for kw in 0..wf {
                         for kh in 0..hf {
-                            let xrow = (xrow + kh) as isize - padh[0] as isize;
-                            let xcol = (xcol + kw) as isize - padw[0] as isize;
+                            let xrow = (xrow + kh * dilh) as isize - padh[0] as isize;
+                            let xcol = (xcol + kw * dilw) as isize - padw[0] as isize;
                             let row = (kh * wf + kw) + row;
                             let yi = row * cy + col;
                             if xrow >= hx as isize || xrow < 0 || xcol >= wx as isize || xcol < 0 {
@@ -104,11 +121,13 @@ pub fn im2col(
 /// - stride: H and W strides of the filter.
 /// - Padh: height padding
 /// - Padw: width padding
+/// - Dilation: h, w, spacing between the elements of the filter, 1 for none
 /// 
 /// GY Height: fc * fh * fw
 /// 
-/// GY Width: (((xh - fh + (padh.0 + padh.1)) / strideh) + 1) * (((xw - fw + (padw.0 + padw.1)) / stridew) + 1) * xn
+/// GY Width: (((xh - (dh * (fh - 1) + 1) + (padh.0 + padh.1)) / strideh) + 1) * (((xw - (dw * (fw - 1) + 1) + (padw.0 + padw.1)) / stridew) + 1) * xn
 #[inline]
+#[allow(clippy::too_many_arguments)]
 pub fn im2col_wrt_x(
     gy: &[f32],
     gx: &mut [f32],
@@ -117,12 +136,36 @@ pub fn im2col_wrt_x(
     stride: [usize; 2],
     padh: [usize; 2],
     padw: [usize; 2],
+    dilation: [usize; 2],
 ) -> Result<(), BMLSError> {
     let (nx, cx, hx, wx) = (x_dim[0], x_dim[1], x_dim[2], x_dim[3]);
     let (nf, cf, hf, wf) = (f_dim[0], f_dim[1], f_dim[2], f_dim[3]);
     let (strideh, stridew) = (stride[0], stride[1]);
-    let hstart = ((hx - hf + (padh[0] + padh[1])) / strideh) + 1;
-    let wstart = ((wx - wf + (padw[0] + padw[1])) / stridew) + 1;
+    let (dilh, dilw) = (dilation[0], dilation[1]);
+    // the extent of the dilated filter
+    let (hk, wk) = (dilh * hf.saturating_sub(1) + 1, dilw * wf.saturating_sub(1) + 1);
+
+    // the kernel dimensions cannot be 0 and the dilated kernel cannot
+    // be larger than the dimensions of the input + the padding.
+    if hf == 0 || hk > (hx+padh[0]+padh[1]) || 
+       wf == 0 || wk > (wx+padw[0]+padw[1]) ||
+       cf != cx || nf == 0
+    {
+        return error::invalid_kernel_dim(f_dim)
+    }
+
+    // strides must not be 0
+    if strideh == 0 || stridew == 0 {
+        return error::invalid_strides(strideh, stridew)
+    }
+
+    // dilations must not be 0
+    if dilh == 0 || dilw == 0 {
+        return error::invalid_dilation(dilh, dilw)
+    }
+
+    let hstart = ((hx + (padh[0] + padh[1]) - hk) / strideh) + 1;
+    let wstart = ((wx + (padw[0] + padw[1]) - wk) / stridew) + 1;
     // size of the output Y
     let (ny, cy) = (hf * wf * cf, hstart * wstart * nx);
 
@@ -138,28 +181,14 @@ pub fn im2col_wrt_x(
         return error::length_mismatch("GY", gy.len(), "GY_dim", ylen);
     }
 
-    // the kernel dimensions cannot be 0 or greater than 
-    // the dimensions of the input + the padding.
-    if hf == 0 || hf >= (hx+padh[0]+padh[1]) || 
-       wf == 0 || wf >= (wx+padw[0]+padw[1]) ||
-       cf != cx || nf == 0
-    {
-        return error::invalid_kernel_dim(f_dim)
-    }
-
-    // strides must not be 0
-    if strideh == 0 || stridew == 0 {
-        return error::invalid_strides(strideh, stridew)
-    }
-
     let gy = Ptr::new(gy);
     let gx = Ptr::new(gx);
 
     (0..nx).into_par_iter().for_each(|n| {
         for h in 0..hstart {
             for w in 0..wstart {
-                // the column of Y we are in
-                let col = (h * wstart + w) * (n + 1);
+                // the column of Y we are in, each sample is a block of columns
+                let col = (n * hstart + h) * wstart + w;
                 for c in 0..cx {
                     let xrow = h * strideh;
                     let xcol = w * stridew;
@@ -169,8 +198,8 @@ pub fn im2col_wrt_x(
                     for kh in 0..hf {
                         for kw in 0..wf {
 
-                            let xrow = (xrow + kh) as isize - padh[0] as isize;
-                            let xcol = (xcol + kw) as isize - padw[0] as isize;
+                            let xrow = (xrow + kh * dilh) as isize - padh[0] as isize;
+                            let xcol = (xcol + kw * dilw) as isize - padw[0] as isize;
 
                             // Adjust the row of Y we are in
                             let row = (kh * wf + kw) + row;
@@ -208,6 +237,7 @@ mod tests {
                 [1, 1],
                 [0, 0],
                 [0, 0],
+                [1, 1],
             ).unwrap();
 
         for row in 0..12 {
@@ -220,4 +250,48 @@ mod tests {
 
         //panic!("");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn im2col_dilation() {
+        // 3x3, a 2x2 filter dilated by 2 covers all of it
+        let x: Vec<f32> = (1..=9).map(|i| i as f32).collect();
+        let mut y = vec![0.0; 4];
+
+        super::im2col(&x, &mut y, [1, 1, 3, 3], [1, 1, 2, 2], [1, 1], [0, 0], [0, 0], [2, 2]).unwrap();
+        assert_eq!(y, [1.0, 3.0, 7.0, 9.0]);
+
+        // a dilation of 3 covers 4x4, larger than the image
+        assert!(super::im2col(&x, &mut y, [1, 1, 3, 3], [1, 1, 2, 2], [1, 1], [0, 0], [0, 0], [3, 3]).is_err());
+        assert!(super::im2col(&x, &mut y, [1, 1, 3, 3], [1, 1, 2, 2], [1, 1], [0, 0], [0, 0], [0, 1]).is_err());
+    }
+
+    #[test]
+    fn im2col_batch_layout() {
+        // 2 samples of 2 channels of 3x4, a 2x2 filter gives 2x3 positions per sample
+        let (n, c, h, w) = (2, 2, 3, 4);
+        let (hs, ws) = (2, 3);
+        let x: Vec<f32> = (0..48).map(|i| i as f32).collect();
+        let cols = n * hs * ws;
+        let mut y = vec![0.0; c * 2 * 2 * cols];
+
+        super::im2col(&x, &mut y, [n, c, h, w], [1, c, 2, 2], [1, 1], [0, 0], [0, 0], [1, 1]).unwrap();
+
+        // each sample is a contiguous block of hs * ws columns
+        for ni in 0..n {
+            for ci in 0..c {
+                for kh in 0..2 {
+                    for kw in 0..2 {
+                        let row = (ci * 2 + kh) * 2 + kw;
+                        for hi in 0..hs {
+                            for wi in 0..ws {
+                                let col = (ni * hs + hi) * ws + wi;
+                                let xi = ((ni * c + ci) * h + hi + kh) * w + wi + kw;
+                                assert_eq!(y[row * cols + col], x[xi]);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
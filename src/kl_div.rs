@@ -0,0 +1,132 @@
+use itertools::izip;
+use crate::error::BMLSError;
+use crate::error;
+use crate::cross_entropy::log_sum_exp;
+use crate::reduction::{Reduction, reduce_rows_multi};
+
+/// # KL Divergence (from logits)
+/// - T: Teacher (target) Logits (N x C)
+/// - X: Student Logits (N x C)
+/// - E: Error, (N x 1) vector for `Reduction::None`, otherwise (1)
+/// - GT: Gradient of E w.r.t. T (N x C)
+/// - GX: Gradient of E w.r.t. X (N x C)
+/// - Dim: [N, C]
+/// - Temperature: Both logits are divided by this before the softmax
+/// - Sample_Weights: Optional weight for each row (N)
+/// - Reduction: How the rows are combined into E
+///
+/// For distillation. With p = softmax(t / temp) and q = softmax(x / temp): \
+/// e = temp^2 * sum(p * (ln(p) - ln(q)))
+///
+/// The temp^2 keeps the gradient magnitude independent of the temperature.
+/// GT can be ignored when the teacher is not being trained.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn kl_div(
+    t: &[f32],
+    x: &[f32],
+    e: &mut [f32],
+    gt: &mut [f32],
+    gx: &mut [f32],
+    dim: [usize; 2],
+    temperature: f32,
+    sample_weights: Option<&[f32]>,
+    reduction: Reduction,
+) -> Result<(), BMLSError> {
+    let len = dim[0] * dim[1];
+    if t.len() != len {
+        return error::length_mismatch("T", t.len(), "Dim", len)
+    }
+
+    if x.len() != len {
+        return error::length_mismatch("X", x.len(), "Dim", len)
+    }
+
+    if gt.len() != len {
+        return error::length_mismatch("GT", gt.len(), "Dim", len)
+    }
+
+    if gx.len() != len {
+        return error::length_mismatch("GX", gx.len(), "Dim", len)
+    }
+
+    if temperature <= 0.0 || temperature.is_nan() {
+        return error::invalid_temperature(temperature)
+    }
+
+    let cols = dim[1];
+    let inv = 1. / temperature;
+    let mut ts = vec![0.0; cols];
+    let mut xs = vec![0.0; cols];
+
+    reduce_rows_multi(e, &mut [gt, gx], dim[0], sample_weights, reduction, |i, g| {
+        for (ts, xs, t, x) in izip!(
+            &mut ts,
+            &mut xs,
+            &t[i * cols..(i + 1) * cols],
+            &x[i * cols..(i + 1) * cols]
+        ) {
+            *ts = t * inv;
+            *xs = x * inv;
+        }
+
+        let t_lse = log_sum_exp(&ts);
+        let x_lse = log_sum_exp(&xs);
+
+        // sum(p * (ln(p) - ln(q)))
+        let mut kl = 0.0;
+        for (ts, xs) in izip!(&ts, &xs) {
+            kl += f32::exp(ts - t_lse) * ((ts - t_lse) - (xs - x_lse));
+        }
+
+        for (ts, xs, gt) in izip!(&ts, &xs, g[0].iter_mut()) {
+            let lp = ts - t_lse;
+            let lq = xs - x_lse;
+            *gt = temperature * f32::exp(lp) * (lp - lq - kl);
+        }
+
+        for (ts, xs, gx) in izip!(&ts, &xs, g[1].iter_mut()) {
+            *gx = temperature * (f32::exp(xs - x_lse) - f32::exp(ts - t_lse));
+        }
+
+        Some(temperature * temperature * kl)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_kl_div_gradient() {
+        let dim = [2, 3];
+        let t = [1.0, -0.5, 2.0, 0.3, 0.3, -1.0];
+        let x = [0.2, 0.1, -1.0, 2.0, -0.7, 0.4];
+
+        let mut e = [0.0];
+        let mut gt = [0.0; 6];
+        let mut gx = [0.0; 6];
+        kl_div(&t, &x, &mut e, &mut gt, &mut gx, dim, 2.0, None, Reduction::Sum).unwrap();
+
+        let f = |t: &[f32], x: &[f32]| {
+            let mut e = [0.0];
+            kl_div(t, x, &mut e, &mut [0.0; 6], &mut [0.0; 6], dim, 2.0, None, Reduction::Sum).unwrap();
+            e[0]
+        };
+
+        for i in 0..6 {
+            let (mut tp, mut tm, mut xp, mut xm) = (t, t, x, x);
+            tp[i] += 1e-2;
+            tm[i] -= 1e-2;
+            xp[i] += 1e-2;
+            xm[i] -= 1e-2;
+            assert!(((f(&tp, &x) - f(&tm, &x)) / 2e-2 - gt[i]).abs() < 1e-3);
+            assert!(((f(&t, &xp) - f(&t, &xm)) / 2e-2 - gx[i]).abs() < 1e-3);
+        }
+
+        // identical distributions have no divergence
+        kl_div(&t, &t, &mut e, &mut gt, &mut gx, dim, 1.0, None, Reduction::Sum).unwrap();
+        assert!(e[0].abs() < 1e-6);
+    }
+}
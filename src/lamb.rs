@@ -0,0 +1,127 @@
+use rayon::prelude::*;
+use crate::error::BMLSError;
+use crate::error;
+use crate::WeightDecay;
+
+/// # LAMB Optimizer
+/// - G: Gradient w.r.t. W
+/// - V: Exponentialy weighted average of past gradients
+/// - S: Exponentialy weighted average of past squares of gradients
+/// - W: Weight Tensor
+/// - Lr: Learning Rate
+/// - Beta1: Hyperparameter,
+/// - Beta2: Hyperparameter,
+/// - Epsilon: Added to the denominator for numerical stability
+/// - Decay: Weight Decay. LAMB normally uses `WeightDecay::Decoupled`.
+/// - T: Timestep of this update, starting at 1.
+/// 
+/// The Adam update R is scaled by the trust ratio ||W|| / ||R||
+/// of the whole tensor, so W should be a single layer's weights.
+/// If either norm is zero the trust ratio is 1. 
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn lamb(
+    g: &[f32],
+    v: &mut [f32],
+    s: &mut [f32],
+    w: &mut [f32],
+    lr: f32,
+    beta1: f32,
+    beta2: f32,
+    epsilon: f32,
+    decay: WeightDecay,
+    t: usize,
+) -> Result<(), BMLSError> {
+    if g.len() != v.len() {
+        return error::length_mismatch("G", g.len(), "V", v.len())
+    }
+
+    if v.len() != s.len() {
+        return error::length_mismatch("V", v.len(), "S", s.len())
+    }
+
+    if s.len() != w.len() {
+        return error::length_mismatch("S", s.len(), "W", w.len())
+    }
+
+    if t == 0 {
+        return error::invalid_timestep(t)
+    }
+
+    // bias corrections for timestep t
+    let c1 = 1. - beta1.powf(t as f32);
+    let c2 = 1. - beta2.powf(t as f32);
+
+    let (l2, wd) = decay.coefficients();
+
+    // the Adam update for one element, with decoupled decay
+    let update = |v: f32, s: f32, w: f32| {
+        (v / c1) / (f32::sqrt(s / c2) + epsilon) + wd * w
+    };
+
+    // update V and S, and get the squared norms of W and the update.
+    let (w_norm, r_norm) = g.par_iter()
+        .zip(v.par_iter_mut())
+        .zip(s.par_iter_mut())
+        .zip(w.par_iter())
+        .map(|(((g, v), s), w)| {
+            let g = *g + l2 * *w;
+            *v = (*v * beta1) + (1. - beta1) * g;
+            *s = (*s * beta2) + (1. - beta2) * (g * g);
+
+            let r = update(*v, *s, *w);
+            (w * w, r * r)
+        })
+        .reduce(|| (0.0, 0.0), |a, b| (a.0 + b.0, a.1 + b.1));
+
+    let (w_norm, r_norm) = (f32::sqrt(w_norm), f32::sqrt(r_norm));
+    let trust = if w_norm > 0.0 && r_norm > 0.0 { w_norm / r_norm } else { 1.0 };
+
+    w.par_iter_mut()
+        .zip(v.par_iter())
+        .zip(s.par_iter())
+        .for_each(|((w, v), s)| {
+            *w -= lr * trust * update(*v, *s, *w);
+        });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_lamb_trust_ratio() {
+        let g = [1.0, -2.0, 3.0, -4.0];
+        let mut v = [0.0; 4];
+        let mut s = [0.0; 4];
+        let mut w = [4.0, 0.0, 0.0, 3.0];
+
+        lamb(&g, &mut v, &mut s, &mut w, 0.1, 0.9, 0.999, 1e-8, WeightDecay::None, 1).unwrap();
+
+        // at t = 1 the update is sign(g), so ||R|| = 2 and ||W|| = 5.
+        let e = [4.0 - 0.25, 0.25, -0.25, 3.0 + 0.25];
+        for (w, e) in w.iter().zip(e) {
+            assert!((w - e).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_lamb_large_timestep() {
+        let g = [1.0, -2.0, 3.0, -4.0];
+        let step = |t: usize| {
+            let mut v = [0.0; 4];
+            let mut s = [0.0; 4];
+            let mut w = [4.0, 0.0, 0.0, 3.0];
+            lamb(&g, &mut v, &mut s, &mut w, 0.1, 0.9, 0.999, 1e-8, WeightDecay::None, t).unwrap();
+            w
+        };
+
+        // past i32::MAX the timestep must not wrap, the corrections stay 1
+        let w = step(i32::MAX as usize + 2);
+        assert!(w.iter().all(|w| w.is_finite()));
+        assert_eq!(w, step(1_000_000));
+    }
+}
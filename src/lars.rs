@@ -0,0 +1,96 @@
+use rayon::prelude::*;
+use crate::error::BMLSError;
+use crate::error;
+use crate::WeightDecay;
+use crate::momentum::momentum_update;
+
+/// # LARS Optimizer
+/// - G: Gradient w.r.t. W.
+/// - V: Exponentially weighted average of Past scaled gradients
+/// - W: Weight Tensor
+/// - LR: Learning Rate
+/// - Beta: Hyperparameter
+/// - Trust_Coef: Scales the trust ratio, usually 0.001
+/// - Decay: Weight Decay. LARS normally uses `WeightDecay::L2`.
+/// 
+/// `momentum` where the gradient is first scaled by the local learning 
+/// rate trust_coef * ||W|| / ||G|| of the whole tensor, so W should be 
+/// a single layer's weights. If either norm is zero the local rate is 1. 
+#[inline]
+pub fn lars(
+    g: &[f32],
+    v: &mut [f32],
+    w: &mut [f32],
+    lr: f32,
+    beta: f32,
+    trust_coef: f32,
+    decay: WeightDecay,
+) -> Result<(), BMLSError> {
+    if g.len() != v.len() {
+        return error::length_mismatch("G", g.len(), "V", v.len())
+    }
+
+    if v.len() != w.len() {
+        return error::length_mismatch("V", v.len(), "W", w.len())
+    }
+
+    let (l2, wd) = decay.coefficients();
+
+    // squared norms of W and the regularized gradient
+    let (w_norm, g_norm) = g.par_iter()
+        .zip(w.par_iter())
+        .map(|(g, w)| {
+            let g = *g + l2 * *w;
+            (w * w, g * g)
+        })
+        .reduce(|| (0.0, 0.0), |a, b| (a.0 + b.0, a.1 + b.1));
+
+    let (w_norm, g_norm) = (f32::sqrt(w_norm), f32::sqrt(g_norm));
+    let trust = if w_norm > 0.0 && g_norm > 0.0 { trust_coef * w_norm / g_norm } else { 1.0 };
+
+    g.par_iter()
+        .zip(v.par_iter_mut())
+        .zip(w.par_iter_mut())
+        .for_each(|((g, v), w)| {
+            // trust scales the regularized gradient, so it scales L2 as well
+            momentum_update(trust * *g, v, w, lr, beta, trust * l2, wd);
+        });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_lars_trust_ratio() {
+        let g = [0.0, 30.0, 40.0];
+        let mut v = [0.0; 3];
+        let mut w = [2.0, 0.0, 0.0];
+
+        lars(&g, &mut v, &mut w, 1.0, 0.0, 0.5, WeightDecay::None).unwrap();
+
+        // local rate is 0.5 * 2 / 50
+        assert!((v[1] - 0.6).abs() < 1e-6);
+        assert!((v[2] - 0.8).abs() < 1e-6);
+        assert!((w[1] + 0.6).abs() < 1e-6);
+        assert_eq!(w[0], 2.0);
+    }
+
+    #[test]
+    fn test_lars_l2() {
+        let g = [1.0, 0.0];
+        let mut v = [0.0; 2];
+        let mut w = [0.0, 2.0];
+
+        lars(&g, &mut v, &mut w, 1.0, 0.0, 0.5, WeightDecay::L2(0.5)).unwrap();
+
+        // the regularized gradient is [1, 1], so the local rate is 0.5 * 2 / sqrt(2)
+        let r = f32::sqrt(0.5);
+        assert!((v[0] - r).abs() < 1e-6 && (v[1] - r).abs() < 1e-6);
+        assert!((w[0] + r).abs() < 1e-6);
+        assert!((w[1] - (2.0 - r)).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,314 @@
+use rayon::prelude::*;
+use crate::error::BMLSError;
+use crate::error;
+
+/// # Layer Normalization
+/// - X: Input
+/// - Y: Output
+/// - Gamma: Scale for each element of the normalized dimensions
+/// - Beta: Shift for each element of the normalized dimensions
+/// - Mean: Mean of each normalized group, saved for the backward pass
+/// - Inv_Std: 1 / sqrt(var + epsilon) of each group, saved for the backward pass
+/// - X_dim: Dimensions of X.
+/// - Axis: First normalized dimension. Every dimension from Axis
+///   onward is normalized together, e.g. 1 for (C x H x W) of NCHW.
+/// - Epsilon: Added to the variance for numerical stability
+///
+/// Gamma and Beta have the length of the normalized dimensions, and
+/// Mean and Inv_Std the length of the dimensions before Axis.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn layer_norm(
+    x: &[f32],
+    y: &mut [f32],
+    gamma: &[f32],
+    beta: &[f32],
+    mean: &mut [f32],
+    inv_std: &mut [f32],
+    x_dim: [usize; 4],
+    axis: usize,
+    epsilon: f32,
+) -> Result<(), BMLSError> {
+    let (groups, size) = check_dims(x, x_dim, axis)?;
+
+    if y.len() != x.len() {
+        return error::length_mismatch("Y", y.len(), "X", x.len());
+    }
+
+    check_params(&[("Gamma", gamma.len()), ("Beta", beta.len())], "Size", size)?;
+    check_params(&[("Mean", mean.len()), ("Inv_Std", inv_std.len())], "Groups", groups)?;
+
+    normalize(x, y, mean, inv_std, size, epsilon, |_, j| (gamma[j], beta[j]));
+
+    Ok(())
+}
+
+/// # Layer Normalization w.r.t. X
+/// - X: Input
+/// - GY: Gradient w.r.t. Y
+/// - GX: Gradient w.r.t. X
+/// - Gamma: Scale for each element of the normalized dimensions
+/// - Mean: Mean saved by `layer_norm`
+/// - Inv_Std: Inv_Std saved by `layer_norm`
+/// - X_dim: Dimensions of X.
+/// - Axis: First normalized dimension.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn layer_norm_wrt_x(
+    x: &[f32],
+    gy: &[f32],
+    gx: &mut [f32],
+    gamma: &[f32],
+    mean: &[f32],
+    inv_std: &[f32],
+    x_dim: [usize; 4],
+    axis: usize,
+) -> Result<(), BMLSError> {
+    let (groups, size) = check_dims(x, x_dim, axis)?;
+
+    if gy.len() != x.len() {
+        return error::length_mismatch("GY", gy.len(), "X", x.len());
+    }
+
+    if gx.len() != x.len() {
+        return error::length_mismatch("GX", gx.len(), "X", x.len());
+    }
+
+    check_params(&[("Gamma", gamma.len())], "Size", size)?;
+    check_params(&[("Mean", mean.len()), ("Inv_Std", inv_std.len())], "Groups", groups)?;
+
+    normalize_wrt_x(x, gy, gx, mean, inv_std, size, |_, j| gamma[j]);
+
+    Ok(())
+}
+
+/// # Layer Normalization w.r.t. Gamma
+/// - X: Input
+/// - GY: Gradient w.r.t. Y
+/// - G_Gamma: Gradient w.r.t. Gamma
+/// - Mean: Mean saved by `layer_norm`
+/// - Inv_Std: Inv_Std saved by `layer_norm`
+/// - X_dim: Dimensions of X.
+/// - Axis: First normalized dimension.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn layer_norm_wrt_gamma(
+    x: &[f32],
+    gy: &[f32],
+    g_gamma: &mut [f32],
+    mean: &[f32],
+    inv_std: &[f32],
+    x_dim: [usize; 4],
+    axis: usize,
+) -> Result<(), BMLSError> {
+    let (groups, size) = check_dims(x, x_dim, axis)?;
+
+    if gy.len() != x.len() {
+        return error::length_mismatch("GY", gy.len(), "X", x.len());
+    }
+
+    check_params(&[("G_Gamma", g_gamma.len())], "Size", size)?;
+    check_params(&[("Mean", mean.len()), ("Inv_Std", inv_std.len())], "Groups", groups)?;
+
+    g_gamma.par_iter_mut().enumerate().for_each(|(j, gg)| {
+        for i in 0..groups {
+            let index = i * size + j;
+            *gg += gy[index] * (x[index] - mean[i]) * inv_std[i];
+        }
+    });
+
+    Ok(())
+}
+
+/// # Layer Normalization w.r.t. Beta
+/// - GY: Gradient w.r.t. Y
+/// - G_Beta: Gradient w.r.t. Beta
+/// - X_dim: Dimensions of X.
+/// - Axis: First normalized dimension.
+#[inline]
+pub fn layer_norm_wrt_beta(
+    gy: &[f32],
+    g_beta: &mut [f32],
+    x_dim: [usize; 4],
+    axis: usize,
+) -> Result<(), BMLSError> {
+    let (groups, size) = check_dims(gy, x_dim, axis)?;
+
+    check_params(&[("G_Beta", g_beta.len())], "Size", size)?;
+
+    g_beta.par_iter_mut().enumerate().for_each(|(j, gb)| {
+        for i in 0..groups {
+            *gb += gy[i * size + j];
+        }
+    });
+
+    Ok(())
+}
+
+/// Checks X against X_dim and splits it at Axis into (groups, size).
+#[inline]
+pub(crate) fn check_dims(
+    x: &[f32],
+    x_dim: [usize; 4],
+    axis: usize,
+) -> Result<(usize, usize), BMLSError> {
+    if axis > 3 {
+        return error::invalid_axis(axis);
+    }
+
+    let len = x_dim.iter().product::<usize>();
+    if x.len() != len {
+        return error::length_mismatch("X", x.len(), "X_Dim", len);
+    }
+
+    Ok((x_dim[..axis].iter().product(), x_dim[axis..].iter().product()))
+}
+
+/// Checks that each (name, length) in Params is Len.
+#[inline]
+pub(crate) fn check_params(
+    params: &[(&str, usize)],
+    len_name: &str,
+    len: usize,
+) -> Result<(), BMLSError> {
+    for (name, v) in params {
+        if *v != len {
+            return error::length_mismatch(name, *v, len_name, len);
+        }
+    }
+
+    Ok(())
+}
+
+/// Normalizes each contiguous group of Size elements of X to zero mean
+/// and unit variance, then applies the (gamma, beta) Affine returns
+/// for (group, index in group).
+#[inline]
+pub(crate) fn normalize<F>(
+    x: &[f32],
+    y: &mut [f32],
+    mean: &mut [f32],
+    inv_std: &mut [f32],
+    size: usize,
+    epsilon: f32,
+    affine: F,
+) where
+    F: Fn(usize, usize) -> (f32, f32) + Sync,
+{
+    let size = size.max(1);
+
+    y.par_chunks_mut(size)
+        .zip(x.par_chunks(size))
+        .zip(mean.par_iter_mut().zip(inv_std.par_iter_mut()))
+        .enumerate()
+        .for_each(|(i, ((y, x), (mean, inv_std)))| {
+            let mu = x.iter().sum::<f32>() / size as f32;
+            let var = x.iter().map(|x| (x - mu) * (x - mu)).sum::<f32>() / size as f32;
+            let inv = 1. / f32::sqrt(var + epsilon);
+
+            for (j, (x, y)) in x.iter().zip(y).enumerate() {
+                let (gamma, beta) = affine(i, j);
+                *y = (x - mu) * inv * gamma + beta;
+            }
+
+            *mean = mu;
+            *inv_std = inv;
+        });
+}
+
+/// The gradient of `normalize` w.r.t. X, accumulated into GX.
+/// Gamma returns the scale for (group, index in group).
+#[inline]
+pub(crate) fn normalize_wrt_x<F>(
+    x: &[f32],
+    gy: &[f32],
+    gx: &mut [f32],
+    mean: &[f32],
+    inv_std: &[f32],
+    size: usize,
+    gamma: F,
+) where
+    F: Fn(usize, usize) -> f32 + Sync,
+{
+    let size = size.max(1);
+
+    gx.par_chunks_mut(size)
+        .zip(x.par_chunks(size).zip(gy.par_chunks(size)))
+        .enumerate()
+        .for_each(|(i, (gx, (x, gy)))| {
+            let (mu, inv) = (mean[i], inv_std[i]);
+
+            // with d = gy * gamma, the mean of d and of d * x_hat
+            let mut sum = 0.0;
+            let mut dot = 0.0;
+            for (j, (x, gy)) in x.iter().zip(gy).enumerate() {
+                let d = gy * gamma(i, j);
+                sum += d;
+                dot += d * (x - mu) * inv;
+            }
+            let sum = sum / size as f32;
+            let dot = dot / size as f32;
+
+            for (j, ((x, gy), gx)) in x.iter().zip(gy).zip(gx).enumerate() {
+                let x_hat = (x - mu) * inv;
+                *gx += inv * (gy * gamma(i, j) - sum - x_hat * dot);
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const DIM: [usize; 4] = [2, 3, 2, 1];
+
+    fn input() -> Vec<f32> {
+        (0..12).map(|i| f32::sin(i as f32 * 1.3) * 3.0 + i as f32 * 0.1).collect()
+    }
+
+    #[test]
+    fn test_layer_norm_gradient() {
+        let x = input();
+        let gamma: Vec<f32> = (0..6).map(|j| 1.0 + j as f32 * 0.2).collect();
+        let beta: Vec<f32> = (0..6).map(|j| j as f32 * -0.1).collect();
+        let r: Vec<f32> = (0..12).map(|i| f32::cos(i as f32)).collect();
+
+        let f = |x: &[f32], gamma: &[f32], beta: &[f32]| {
+            let mut y = vec![0.0; 12];
+            let (mut m, mut s) = ([0.0; 2], [0.0; 2]);
+            layer_norm(x, &mut y, gamma, beta, &mut m, &mut s, DIM, 1, 1e-5).unwrap();
+            y.iter().zip(&r).map(|(y, r)| y * r).sum::<f32>()
+        };
+
+        let mut y = vec![0.0; 12];
+        let (mut m, mut s) = ([0.0; 2], [0.0; 2]);
+        layer_norm(&x, &mut y, &gamma, &beta, &mut m, &mut s, DIM, 1, 1e-5).unwrap();
+
+        let mut gx = vec![0.0; 12];
+        let mut gg = vec![0.0; 6];
+        let mut gb = vec![0.0; 6];
+        layer_norm_wrt_x(&x, &r, &mut gx, &gamma, &m, &s, DIM, 1).unwrap();
+        layer_norm_wrt_gamma(&x, &r, &mut gg, &m, &s, DIM, 1).unwrap();
+        layer_norm_wrt_beta(&r, &mut gb, DIM, 1).unwrap();
+
+        let fd = |v: &[f32], i: usize, f: &dyn Fn(&[f32]) -> f32| {
+            let mut vp = v.to_vec();
+            let mut vm = v.to_vec();
+            vp[i] += 1e-2;
+            vm[i] -= 1e-2;
+            (f(&vp) - f(&vm)) / 2e-2
+        };
+
+        for (i, gx) in gx.iter().enumerate() {
+            assert!((fd(&x, i, &|x| f(x, &gamma, &beta)) - gx).abs() < 1e-2);
+        }
+
+        for j in 0..6 {
+            assert!((fd(&gamma, j, &|g| f(&x, g, &beta)) - gg[j]).abs() < 1e-2);
+            assert!((fd(&beta, j, &|b| f(&x, &gamma, b)) - gb[j]).abs() < 1e-2);
+        }
+
+        assert!(layer_norm(&x, &mut y, &gamma, &beta, &mut m, &mut s, DIM, 2, 1e-5).is_err());
+    }
+}
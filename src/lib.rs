@@ -1,33 +1,68 @@
 
+mod adadelta;
+mod adagrad;
 mod adam;
+mod adamax;
 mod add;
 mod avg_pool;
+mod batch_norm;
 mod axis_add;
 mod axis_div;
 mod axis_mul;
 mod axis_sub;
+mod checkpoint;
+mod clip;
 mod col2im;
+mod contrastive;
+mod conv2d;
+mod conv_transpose2d;
+mod cosine_embedding;
+mod cross_entropy;
+mod ctc;
 mod div;
 mod dropout;
+mod ema;
 mod error;
+mod focal;
+mod fused;
+mod group_norm;
+mod hinge;
+mod huber;
 mod im2col;
+mod kl_div;
+mod lamb;
+mod lars;
+mod layer_norm;
 mod leaky_relu;
+mod log_cosh;
+mod log_softmax;
+mod lookahead;
+mod loss_scale;
 mod lrn;
+mod mae;
 mod matmul;
 mod max_pool;
 mod momentum;
 mod mse;
 mod mul;
+mod nadam;
+mod nesterov;
+mod quantile;
 mod reduce_mean;
 mod reduce_sum;
+mod reduction;
 mod relu;
+mod rms_norm;
 mod rms_prop;
+mod scheduler;
 mod selu;
 mod sgd;
 mod sigmoid;
 mod softmax;
 mod sub;
 mod tanh;
+mod triplet_margin;
+mod weight_decay;
 
 mod ptr;
 pub use ptr::*;
@@ -66,6 +101,16 @@ pub mod prelude {
         selu_wrt_x,
     };
 
+    pub use scheduler::{
+        Scheduler,
+        StepDecay,
+        ExponentialDecay,
+        CosineAnnealing,
+        LinearWarmup,
+        OneCycle,
+        ReduceOnPlateau,
+    };
+
     pub use rms_prop::rms_prop;
 
     pub use relu::{
@@ -89,8 +134,68 @@ pub mod prelude {
         mul_wrt_x2,
     };
 
+    pub use batch_norm::{
+        batch_norm,
+        batch_norm_wrt_x,
+        batch_norm_wrt_gamma,
+        batch_norm_wrt_beta,
+    };
+
+    pub use layer_norm::{
+        layer_norm,
+        layer_norm_wrt_x,
+        layer_norm_wrt_gamma,
+        layer_norm_wrt_beta,
+    };
+
+    pub use rms_norm::{
+        rms_norm,
+        rms_norm_wrt_x,
+        rms_norm_wrt_gamma,
+    };
+
+    pub use group_norm::{
+        group_norm,
+        group_norm_wrt_x,
+        group_norm_wrt_gamma,
+        group_norm_wrt_beta,
+        instance_norm,
+        instance_norm_wrt_x,
+        instance_norm_wrt_gamma,
+        instance_norm_wrt_beta,
+    };
+
     pub use mse::mse;
 
+    pub use mae::mae;
+
+    pub use log_cosh::log_cosh;
+
+    pub use huber::huber;
+
+    pub use quantile::quantile;
+
+    pub use kl_div::kl_div;
+
+    pub use hinge::{
+        hinge,
+        squared_hinge,
+    };
+
+    pub use cosine_embedding::cosine_embedding;
+
+    pub use triplet_margin::triplet_margin;
+
+    pub use contrastive::contrastive;
+
+    pub use ctc::ctc;
+
+    pub use focal::{
+        sigmoid_focal,
+        softmax_focal,
+        class_balanced_weights,
+    };
+
     pub use max_pool::{
         max_pool,
         max_pool_wrt_a,
@@ -106,6 +211,22 @@ pub mod prelude {
         lrn,
         lrn_wrt_x,
     };
+
+    pub use log_softmax::{
+        log_softmax,
+        log_softmax_wrt_x,
+    };
+
+    pub use lookahead::Lookahead;
+
+    pub use loss_scale::{
+        has_overflow,
+        LossScaler,
+    };
+
+    pub use lars::lars;
+
+    pub use lamb::lamb;
     
     pub use leaky_relu::{
         leaky_relu,
@@ -116,7 +237,34 @@ pub mod prelude {
         im2col,
         im2col_wrt_x,
     };
+
+    pub use conv2d::{
+        conv2d,
+        conv2d_wrt_x,
+        conv2d_wrt_w,
+        conv2d_wrt_b,
+    };
+
+    pub use conv_transpose2d::{
+        conv_transpose2d,
+        conv_transpose2d_wrt_x,
+        conv_transpose2d_wrt_w,
+        conv_transpose2d_wrt_b,
+    };
     
+    pub use fused::{
+        fused_sgd,
+        fused_momentum,
+        fused_rms_prop,
+        fused_adam,
+        SgdGroup,
+        MomentumGroup,
+        RmsPropGroup,
+        AdamGroup,
+    };
+
+    pub use ema::Ema;
+
     pub use dropout::{
         dropout,
         dropout_wrt_x,
@@ -124,6 +272,10 @@ pub mod prelude {
 
     pub use momentum::momentum;
 
+    pub use nesterov::nesterov;
+
+    pub use nadam::nadam;
+
     pub use div::{
         div,
         div_wrt_x1,
@@ -133,6 +285,24 @@ pub mod prelude {
     pub use col2im::{
         col2im,
         col2im_wrt_x,
+        fold,
+    };
+
+    pub use cross_entropy::{
+        cross_entropy,
+        sparse_cross_entropy,
+        binary_cross_entropy,
+    };
+
+    pub use clip::{
+        clip_by_value,
+        clip_by_norm,
+        clip_by_global_norm,
+    };
+
+    pub use checkpoint::{
+        Checkpoint,
+        CHECKPOINT_VERSION,
     };
 
     pub use axis_sub::{
@@ -171,6 +341,16 @@ pub mod prelude {
     };
 
     pub use adam::*;
+
+    pub use adamax::adamax;
+
+    pub use adagrad::adagrad;
+
+    pub use adadelta::adadelta;
+
+    pub use weight_decay::WeightDecay;
+
+    pub use reduction::Reduction;
 }
 
 #[cfg(feature = "ndarray")]
@@ -182,7 +362,75 @@ pub mod prelude {
     use ndarray::Dim;
     use ndarray::Axis;
 
+    pub use adam::Adam;
+    pub use ema::Ema;
+    pub use lookahead::Lookahead;
+    pub use loss_scale::LossScaler;
+    pub use fused::{
+        fused_sgd,
+        fused_momentum,
+        fused_rms_prop,
+        fused_adam,
+        SgdGroup,
+        MomentumGroup,
+        RmsPropGroup,
+        AdamGroup,
+    };
+    pub use checkpoint::{
+        Checkpoint,
+        CHECKPOINT_VERSION,
+    };
+    pub use weight_decay::WeightDecay;
+    pub use reduction::Reduction;
+    pub use focal::class_balanced_weights;
+    pub use scheduler::{
+        Scheduler,
+        StepDecay,
+        ExponentialDecay,
+        CosineAnnealing,
+        LinearWarmup,
+        OneCycle,
+        ReduceOnPlateau,
+    };
+
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn adadelta(
+        g: &Array4<f32>,
+        s: &mut Array4<f32>,
+        d: &mut Array4<f32>,
+        w: &mut Array4<f32>,
+        lr: f32,
+        rho: f32,
+        epsilon: f32,
+        decay: WeightDecay,
+    ) -> Result<(), BMLSError> {
+        let g = slice!(g);
+        let s = slice_mut!(s);
+        let d = slice_mut!(d);
+        let w = slice_mut!(w);
+
+        adadelta::adadelta(g, s, d, w, lr, rho, epsilon, decay)
+    }
+
+    #[inline]
+    pub fn adagrad(
+        g: &Array4<f32>,
+        s: &mut Array4<f32>,
+        w: &mut Array4<f32>,
+        lr: f32,
+        epsilon: f32,
+        decay: WeightDecay,
+    ) -> Result<(), BMLSError> {
+        let g = slice!(g);
+        let s = slice_mut!(s);
+        let w = slice_mut!(w);
+
+        adagrad::adagrad(g, s, w, lr, epsilon, decay)
+    }
+
     #[inline]
+    #[allow(clippy::too_many_arguments)]
     pub fn adam(
         g: &Array4<f32>,
         v: &mut Array4<f32>,
@@ -191,6 +439,9 @@ pub mod prelude {
         lr: f32,
         beta1: f32,
         beta2: f32,
+        epsilon: f32,
+        decay: WeightDecay,
+        t: usize,
     ) -> Result<(), BMLSError> {
         let g = slice!(g);
         let v = slice_mut!(v);
@@ -198,10 +449,32 @@ pub mod prelude {
         let w = slice_mut!(w);
 
         adam::adam(
-            g, v, s, w, lr, beta1, beta2
+            g, v, s, w, lr, beta1, beta2, epsilon, decay, t
         )
     }
 
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn adamax(
+        g: &Array4<f32>,
+        v: &mut Array4<f32>,
+        u: &mut Array4<f32>,
+        w: &mut Array4<f32>,
+        lr: f32,
+        beta1: f32,
+        beta2: f32,
+        epsilon: f32,
+        decay: WeightDecay,
+        t: usize,
+    ) -> Result<(), BMLSError> {
+        let g = slice!(g);
+        let v = slice_mut!(v);
+        let u = slice_mut!(u);
+        let w = slice_mut!(w);
+
+        adamax::adamax(g, v, u, w, lr, beta1, beta2, epsilon, decay, t)
+    }
+
     #[inline]
     pub fn add(
         x1: &Array4<f32>,
@@ -251,6 +524,7 @@ pub mod prelude {
         kernel: [usize; 2],
         padh: [usize; 2],
         padw: [usize; 2],
+        dilation: [usize; 2],
     ) -> Result<(), BMLSError> {
         let x_shape = to_array4(x.raw_dim());
     
@@ -258,7 +532,7 @@ pub mod prelude {
         let y = slice_mut!(y);
 
         avg_pool::avg_pool(
-            x, y, x_shape, stride, kernel, padh, padw
+            x, y, x_shape, stride, kernel, padh, padw, dilation
         )
     }
 
@@ -270,6 +544,7 @@ pub mod prelude {
         kernel: [usize; 2],
         padh: [usize; 2],
         padw: [usize; 2],
+        dilation: [usize; 2],
     ) -> Result<(), BMLSError> {
         let x_shape = to_array4(gx.raw_dim());
 
@@ -277,7 +552,7 @@ pub mod prelude {
         let gx = slice_mut!(gx);
 
         avg_pool::avg_pool_wrt_x(
-            gy, gx, x_shape, stride, kernel, padh, padw
+            gy, gx, x_shape, stride, kernel, padh, padw, dilation
         )
     }
 
@@ -452,6 +727,121 @@ pub mod prelude {
         axis_sub::axis_sub_wrt_x2(gy, g2, dim, axis.0)
     }
     
+    /// Gamma, Beta and the statistics have shape (1 x C x 1 x 1)
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn batch_norm(
+        x: &Array4<f32>,
+        y: &mut Array4<f32>,
+        gamma: &Array4<f32>,
+        beta: &Array4<f32>,
+        mean: &mut Array4<f32>,
+        inv_std: &mut Array4<f32>,
+        running_mean: &mut Array4<f32>,
+        running_var: &mut Array4<f32>,
+        momentum: f32,
+        epsilon: f32,
+        training: bool,
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(x.raw_dim());
+        let x = slice!(x);
+        let y = slice_mut!(y);
+        let gamma = slice!(gamma);
+        let beta = slice!(beta);
+        let mean = slice_mut!(mean);
+        let inv_std = slice_mut!(inv_std);
+        let running_mean = slice_mut!(running_mean);
+        let running_var = slice_mut!(running_var);
+
+        batch_norm::batch_norm(x, y, gamma, beta, mean, inv_std, running_mean, running_var, x_dim, momentum, epsilon, training)
+    }
+
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn batch_norm_wrt_x(
+        x: &Array4<f32>,
+        gy: &Array4<f32>,
+        gx: &mut Array4<f32>,
+        gamma: &Array4<f32>,
+        mean: &Array4<f32>,
+        inv_std: &Array4<f32>,
+        training: bool,
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(x.raw_dim());
+        let x = slice!(x);
+        let gy = slice!(gy);
+        let gx = slice_mut!(gx);
+        let gamma = slice!(gamma);
+        let mean = slice!(mean);
+        let inv_std = slice!(inv_std);
+
+        batch_norm::batch_norm_wrt_x(x, gy, gx, gamma, mean, inv_std, x_dim, training)
+    }
+
+    #[inline]
+    pub fn batch_norm_wrt_gamma(
+        x: &Array4<f32>,
+        gy: &Array4<f32>,
+        g_gamma: &mut Array4<f32>,
+        mean: &Array4<f32>,
+        inv_std: &Array4<f32>,
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(x.raw_dim());
+        let x = slice!(x);
+        let gy = slice!(gy);
+        let g_gamma = slice_mut!(g_gamma);
+        let mean = slice!(mean);
+        let inv_std = slice!(inv_std);
+
+        batch_norm::batch_norm_wrt_gamma(x, gy, g_gamma, mean, inv_std, x_dim)
+    }
+
+    #[inline]
+    pub fn batch_norm_wrt_beta(
+        gy: &Array4<f32>,
+        g_beta: &mut Array4<f32>,
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(gy.raw_dim());
+        let gy = slice!(gy);
+        let g_beta = slice_mut!(g_beta);
+
+        batch_norm::batch_norm_wrt_beta(gy, g_beta, x_dim)
+    }
+
+    #[inline]
+    pub fn clip_by_value(
+        g: &mut Array4<f32>,
+        min: f32,
+        max: f32,
+    ) -> Result<(), BMLSError> {
+        let g = slice_mut!(g);
+
+        clip::clip_by_value(g, min, max)
+    }
+
+    #[inline]
+    pub fn clip_by_norm(
+        g: &mut Array4<f32>,
+        max_norm: f32,
+    ) -> Result<f32, BMLSError> {
+        let g = slice_mut!(g);
+
+        clip::clip_by_norm(g, max_norm)
+    }
+
+    #[inline]
+    pub fn clip_by_global_norm(
+        g: &mut [&mut Array4<f32>],
+        max_norm: f32,
+    ) -> Result<f32, BMLSError> {
+        let mut slices = Vec::with_capacity(g.len());
+        for x in g.iter_mut() {
+            slices.push(slice_mut!(x));
+        }
+
+        clip::clip_by_global_norm(&mut slices, max_norm)
+    }
+
     #[inline]
     pub fn col2im(
         x: &Array4<f32>,
@@ -481,150 +871,876 @@ pub mod prelude {
     }
 
     #[inline]
-    pub fn div(
-        x1: &Array4<f32>,
-        x2: &Array4<f32>,
+    pub fn fold(
+        x: &Array4<f32>,
         y: &mut Array4<f32>,
+        f_dim: Dim<[usize; 4]>,
+        stride: [usize; 2],
+        padh: [usize; 2],
+        padw: [usize; 2],
+        dilation: [usize; 2],
     ) -> Result<(), BMLSError> {
-        let x1 = slice!(x1);
-        let x2 = slice!(x2);
+        let y_dim = to_array4(y.raw_dim());
+        let f_dim = to_array4(f_dim);
+        let x = slice!(x);
         let y = slice_mut!(y);
 
-        div::div(x1, x2, y)
-    }
-
-    #[inline]
-    pub fn div_wrt_x1(
-        x2: &Array4<f32>,
-        gy: &Array4<f32>,
-        g1: &mut Array4<f32>,
-    ) -> Result<(), BMLSError> {
-        let x2 = slice!(x2);
-        let gy = slice!(gy);
-        let g1 = slice_mut!(g1);
-
-        div::div_wrt_x1(x2, gy, g1)
+        col2im::fold(x, y, y_dim, f_dim, stride, padh, padw, dilation)
     }
 
     #[inline]
-    pub fn div_wrt_x2(
+    #[allow(clippy::too_many_arguments)]
+    pub fn contrastive(
         x1: &Array4<f32>,
         x2: &Array4<f32>,
-        gy: &Array4<f32>,
+        t: &[f32],
+        e: &mut Array4<f32>,
+        g1: &mut Array4<f32>,
         g2: &mut Array4<f32>,
+        margin: f32,
+        sample_weights: Option<&[f32]>,
+        reduction: Reduction,
     ) -> Result<(), BMLSError> {
+        let dim = to_array2(x1.raw_dim());
         let x1 = slice!(x1);
         let x2 = slice!(x2);
-        let gy = slice!(gy);
+        let e = slice_mut!(e);
+        let g1 = slice_mut!(g1);
         let g2 = slice_mut!(g2);
 
-        div::div_wrt_x2(x1, x2, gy, g2)
+        contrastive::contrastive(x1, x2, t, e, g1, g2, dim, margin, sample_weights, reduction)
     }
 
     #[inline]
-    pub fn dropout(
+    #[allow(clippy::too_many_arguments)]
+    pub fn conv2d(
         x: &Array4<f32>,
-        r: &mut Array4<f32>,
+        w: &Array4<f32>,
+        b: &Array4<f32>,
         y: &mut Array4<f32>,
-        rate: f32,
+        stride: [usize; 2],
+        padh: [usize; 2],
+        padw: [usize; 2],
+        dilation: [usize; 2],
+        groups: usize,
     ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(x.raw_dim());
+        let w_dim = to_array4(w.raw_dim());
         let x = slice!(x);
-        let r = slice_mut!(r);
+        let w = slice!(w);
+        let b = slice!(b);
         let y = slice_mut!(y);
 
-        dropout::dropout(x, r, y, rate)
+        conv2d::conv2d(x, w, b, y, x_dim, w_dim, stride, padh, padw, dilation, groups)
     }
 
     #[inline]
-    pub fn dropout_wrt_x(
-        r: &Array4<f32>,
+    #[allow(clippy::too_many_arguments)]
+    pub fn conv2d_wrt_x(
+        w: &Array4<f32>,
         gy: &Array4<f32>,
         gx: &mut Array4<f32>,
-        rate: f32,
+        stride: [usize; 2],
+        padh: [usize; 2],
+        padw: [usize; 2],
+        dilation: [usize; 2],
+        groups: usize,
     ) -> Result<(), BMLSError> {
-        let r = slice!(r);
+        let x_dim = to_array4(gx.raw_dim());
+        let w_dim = to_array4(w.raw_dim());
+        let w = slice!(w);
         let gy = slice!(gy);
         let gx = slice_mut!(gx);
 
-        dropout::dropout_wrt_x(r, gy, gx, rate)
+        conv2d::conv2d_wrt_x(w, gy, gx, x_dim, w_dim, stride, padh, padw, dilation, groups)
     }
 
     #[inline]
-    pub fn im2col(
+    #[allow(clippy::too_many_arguments)]
+    pub fn conv2d_wrt_w(
         x: &Array4<f32>,
-        y: &mut Array4<f32>,
-        f_dim: Dim<[usize; 4]>,
+        gy: &Array4<f32>,
+        gw: &mut Array4<f32>,
         stride: [usize; 2],
         padh: [usize; 2],
         padw: [usize; 2],
+        dilation: [usize; 2],
+        groups: usize,
     ) -> Result<(), BMLSError> {
         let x_dim = to_array4(x.raw_dim());
-        let f_dim = to_array4(f_dim);
+        let w_dim = to_array4(gw.raw_dim());
         let x = slice!(x);
-        let y = slice_mut!(y);
+        let gy = slice!(gy);
+        let gw = slice_mut!(gw);
 
-        im2col::im2col(x, y, x_dim, f_dim, stride, padh, padw)
+        conv2d::conv2d_wrt_w(x, gy, gw, x_dim, w_dim, stride, padh, padw, dilation, groups)
     }
 
     #[inline]
-    pub fn im2col_wrt_x(
+    pub fn conv2d_wrt_b(
         gy: &Array4<f32>,
-        gx: &mut Array4<f32>,
-        f_dim: Dim<[usize; 4]>,
-        stride: [usize; 2],
-        padh: [usize; 2],
-        padw: [usize; 2],
+        gb: &mut Array4<f32>,
     ) -> Result<(), BMLSError> {
-        let x_dim = to_array4(gx.raw_dim());
-        let f_dim = to_array4(f_dim);
-
+        let y_dim = to_array4(gy.raw_dim());
         let gy = slice!(gy);
-        let gx = slice_mut!(gx);
+        let gb = slice_mut!(gb);
 
-        im2col::im2col_wrt_x(gy, gx, x_dim, f_dim, stride, padh, padw)
+        conv2d::conv2d_wrt_b(gy, gb, y_dim)
     }
-    
+
     #[inline]
-    pub fn leaky_relu(
+    #[allow(clippy::too_many_arguments)]
+    pub fn conv_transpose2d(
         x: &Array4<f32>,
+        w: &Array4<f32>,
+        b: &Array4<f32>,
         y: &mut Array4<f32>,
-        a: f32,
+        stride: [usize; 2],
+        padh: [usize; 2],
+        padw: [usize; 2],
+        output_padding: [usize; 2],
+        dilation: [usize; 2],
+        groups: usize,
     ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(x.raw_dim());
+        let w_dim = to_array4(w.raw_dim());
         let x = slice!(x);
+        let w = slice!(w);
+        let b = slice!(b);
         let y = slice_mut!(y);
 
-        leaky_relu::leaky_relu(x, y, a)
+        conv_transpose2d::conv_transpose2d(x, w, b, y, x_dim, w_dim, stride, padh, padw, output_padding, dilation, groups)
     }
 
     #[inline]
-    pub fn leaky_relu_wrt_x(
-        x: &Array4<f32>,
+    #[allow(clippy::too_many_arguments)]
+    pub fn conv_transpose2d_wrt_x(
+        w: &Array4<f32>,
         gy: &Array4<f32>,
         gx: &mut Array4<f32>,
-        a: f32,
+        stride: [usize; 2],
+        padh: [usize; 2],
+        padw: [usize; 2],
+        output_padding: [usize; 2],
+        dilation: [usize; 2],
+        groups: usize,
     ) -> Result<(), BMLSError> {
-        let x = slice!(x);
+        let x_dim = to_array4(gx.raw_dim());
+        let w_dim = to_array4(w.raw_dim());
+        let w = slice!(w);
         let gy = slice!(gy);
         let gx = slice_mut!(gx);
 
-        leaky_relu::leaky_relu_wrt_x(x, gy, gx, a)
+        conv_transpose2d::conv_transpose2d_wrt_x(w, gy, gx, x_dim, w_dim, stride, padh, padw, output_padding, dilation, groups)
     }
 
     #[inline]
-    pub fn lrn(
+    #[allow(clippy::too_many_arguments)]
+    pub fn conv_transpose2d_wrt_w(
         x: &Array4<f32>,
-        y: &mut Array4<f32>,
-        n: usize,
-        alpha: f32,
-        beta: f32,
-        k: f32,
-        inter: bool,
+        gy: &Array4<f32>,
+        gw: &mut Array4<f32>,
+        stride: [usize; 2],
+        padh: [usize; 2],
+        padw: [usize; 2],
+        output_padding: [usize; 2],
+        dilation: [usize; 2],
+        groups: usize,
     ) -> Result<(), BMLSError> {
         let x_dim = to_array4(x.raw_dim());
+        let w_dim = to_array4(gw.raw_dim());
         let x = slice!(x);
-        let y = slice_mut!(y);
+        let gy = slice!(gy);
+        let gw = slice_mut!(gw);
 
-        lrn::lrn(x, y, x_dim, n, alpha, beta, k, inter)
+        conv_transpose2d::conv_transpose2d_wrt_w(x, gy, gw, x_dim, w_dim, stride, padh, padw, output_padding, dilation, groups)
+    }
+
+    #[inline]
+    pub fn conv_transpose2d_wrt_b(
+        gy: &Array4<f32>,
+        gb: &mut Array4<f32>,
+    ) -> Result<(), BMLSError> {
+        let y_dim = to_array4(gy.raw_dim());
+        let gy = slice!(gy);
+        let gb = slice_mut!(gb);
+
+        conv_transpose2d::conv_transpose2d_wrt_b(gy, gb, y_dim)
+    }
+
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn cosine_embedding(
+        x1: &Array4<f32>,
+        x2: &Array4<f32>,
+        t: &[f32],
+        e: &mut Array4<f32>,
+        g1: &mut Array4<f32>,
+        g2: &mut Array4<f32>,
+        margin: f32,
+        sample_weights: Option<&[f32]>,
+        reduction: Reduction,
+    ) -> Result<(), BMLSError> {
+        let dim = to_array2(x1.raw_dim());
+        let x1 = slice!(x1);
+        let x2 = slice!(x2);
+        let e = slice_mut!(e);
+        let g1 = slice_mut!(g1);
+        let g2 = slice_mut!(g2);
+
+        cosine_embedding::cosine_embedding(x1, x2, t, e, g1, g2, dim, margin, sample_weights, reduction)
+    }
+
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn cross_entropy(
+        t: &Array4<f32>,
+        x: &Array4<f32>,
+        e: &mut Array4<f32>,
+        g: &mut Array4<f32>,
+        weights: Option<&[f32]>,
+        smoothing: f32,
+        sample_weights: Option<&[f32]>,
+        reduction: Reduction,
+    ) -> Result<(), BMLSError> {
+        let dim = to_array2(x.raw_dim());
+        let t = slice!(t);
+        let x = slice!(x);
+        let e = slice_mut!(e);
+        let g = slice_mut!(g);
+
+        cross_entropy::cross_entropy(t, x, e, g, dim, weights, smoothing, sample_weights, reduction)
+    }
+
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn sparse_cross_entropy(
+        t: &[usize],
+        x: &Array4<f32>,
+        e: &mut Array4<f32>,
+        g: &mut Array4<f32>,
+        weights: Option<&[f32]>,
+        smoothing: f32,
+        ignore_index: Option<usize>,
+        sample_weights: Option<&[f32]>,
+        reduction: Reduction,
+    ) -> Result<(), BMLSError> {
+        let dim = to_array2(x.raw_dim());
+        let x = slice!(x);
+        let e = slice_mut!(e);
+        let g = slice_mut!(g);
+
+        cross_entropy::sparse_cross_entropy(t, x, e, g, dim, weights, smoothing, ignore_index, sample_weights, reduction)
+    }
+
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn binary_cross_entropy(
+        t: &Array4<f32>,
+        x: &Array4<f32>,
+        e: &mut Array4<f32>,
+        g: &mut Array4<f32>,
+        weights: Option<&[f32]>,
+        smoothing: f32,
+        sample_weights: Option<&[f32]>,
+        reduction: Reduction,
+    ) -> Result<(), BMLSError> {
+        let dim = to_array2(x.raw_dim());
+        let t = slice!(t);
+        let x = slice!(x);
+        let e = slice_mut!(e);
+        let g = slice_mut!(g);
+
+        cross_entropy::binary_cross_entropy(t, x, e, g, dim, weights, smoothing, sample_weights, reduction)
+    }
+
+    /// X, G: (T x N x C x 1)
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn ctc(
+        x: &Array4<f32>,
+        targets: &[usize],
+        input_lengths: &[usize],
+        target_lengths: &[usize],
+        e: &mut Array4<f32>,
+        g: &mut Array4<f32>,
+        blank: usize,
+        sample_weights: Option<&[f32]>,
+        reduction: Reduction,
+    ) -> Result<(), BMLSError> {
+        let dim = to_array4(x.raw_dim());
+        let x = slice!(x);
+        let e = slice_mut!(e);
+        let g = slice_mut!(g);
+
+        ctc::ctc(x, targets, input_lengths, target_lengths, e, g, [dim[0], dim[1], dim[2]], blank, sample_weights, reduction)
+    }
+
+    #[inline]
+    pub fn div(
+        x1: &Array4<f32>,
+        x2: &Array4<f32>,
+        y: &mut Array4<f32>,
+    ) -> Result<(), BMLSError> {
+        let x1 = slice!(x1);
+        let x2 = slice!(x2);
+        let y = slice_mut!(y);
+
+        div::div(x1, x2, y)
+    }
+
+    #[inline]
+    pub fn div_wrt_x1(
+        x2: &Array4<f32>,
+        gy: &Array4<f32>,
+        g1: &mut Array4<f32>,
+    ) -> Result<(), BMLSError> {
+        let x2 = slice!(x2);
+        let gy = slice!(gy);
+        let g1 = slice_mut!(g1);
+
+        div::div_wrt_x1(x2, gy, g1)
+    }
+
+    #[inline]
+    pub fn div_wrt_x2(
+        x1: &Array4<f32>,
+        x2: &Array4<f32>,
+        gy: &Array4<f32>,
+        g2: &mut Array4<f32>,
+    ) -> Result<(), BMLSError> {
+        let x1 = slice!(x1);
+        let x2 = slice!(x2);
+        let gy = slice!(gy);
+        let g2 = slice_mut!(g2);
+
+        div::div_wrt_x2(x1, x2, gy, g2)
+    }
+
+    #[inline]
+    pub fn dropout(
+        x: &Array4<f32>,
+        r: &mut Array4<f32>,
+        y: &mut Array4<f32>,
+        rate: f32,
+    ) -> Result<(), BMLSError> {
+        let x = slice!(x);
+        let r = slice_mut!(r);
+        let y = slice_mut!(y);
+
+        dropout::dropout(x, r, y, rate)
+    }
+
+    #[inline]
+    pub fn dropout_wrt_x(
+        r: &Array4<f32>,
+        gy: &Array4<f32>,
+        gx: &mut Array4<f32>,
+        rate: f32,
+    ) -> Result<(), BMLSError> {
+        let r = slice!(r);
+        let gy = slice!(gy);
+        let gx = slice_mut!(gx);
+
+        dropout::dropout_wrt_x(r, gy, gx, rate)
+    }
+
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn hinge(
+        t: &[usize],
+        x: &Array4<f32>,
+        e: &mut Array4<f32>,
+        g: &mut Array4<f32>,
+        margin: f32,
+        sample_weights: Option<&[f32]>,
+        reduction: Reduction,
+    ) -> Result<(), BMLSError> {
+        let dim = to_array2(x.raw_dim());
+        let x = slice!(x);
+        let e = slice_mut!(e);
+        let g = slice_mut!(g);
+
+        hinge::hinge(t, x, e, g, dim, margin, sample_weights, reduction)
+    }
+
+    #[inline]
+    pub fn huber(
+        t: &Array4<f32>,
+        p: &Array4<f32>,
+        e: &mut Array4<f32>,
+        g: &mut Array4<f32>,
+        delta: f32,
+        sample_weights: Option<&[f32]>,
+        reduction: Reduction,
+    ) -> Result<(), BMLSError> {
+        let dim = to_array2(t.raw_dim());
+        let t = slice!(t);
+        let p = slice!(p);
+        let e = slice_mut!(e);
+        let g = slice_mut!(g);
+
+        huber::huber(t, p, e, g, dim, delta, sample_weights, reduction)
+    }
+
+    #[inline]
+    pub fn instance_norm(
+        x: &Array4<f32>,
+        y: &mut Array4<f32>,
+        gamma: &Array4<f32>,
+        beta: &Array4<f32>,
+        mean: &mut Array4<f32>,
+        inv_std: &mut Array4<f32>,
+        epsilon: f32,
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(x.raw_dim());
+        let x = slice!(x);
+        let y = slice_mut!(y);
+        let gamma = slice!(gamma);
+        let beta = slice!(beta);
+        let mean = slice_mut!(mean);
+        let inv_std = slice_mut!(inv_std);
+
+        group_norm::instance_norm(x, y, gamma, beta, mean, inv_std, x_dim, epsilon)
+    }
+
+    #[inline]
+    pub fn instance_norm_wrt_x(
+        x: &Array4<f32>,
+        gy: &Array4<f32>,
+        gx: &mut Array4<f32>,
+        gamma: &Array4<f32>,
+        mean: &Array4<f32>,
+        inv_std: &Array4<f32>,
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(x.raw_dim());
+        let x = slice!(x);
+        let gy = slice!(gy);
+        let gx = slice_mut!(gx);
+        let gamma = slice!(gamma);
+        let mean = slice!(mean);
+        let inv_std = slice!(inv_std);
+
+        group_norm::instance_norm_wrt_x(x, gy, gx, gamma, mean, inv_std, x_dim)
+    }
+
+    #[inline]
+    pub fn instance_norm_wrt_gamma(
+        x: &Array4<f32>,
+        gy: &Array4<f32>,
+        g_gamma: &mut Array4<f32>,
+        mean: &Array4<f32>,
+        inv_std: &Array4<f32>,
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(x.raw_dim());
+        let x = slice!(x);
+        let gy = slice!(gy);
+        let g_gamma = slice_mut!(g_gamma);
+        let mean = slice!(mean);
+        let inv_std = slice!(inv_std);
+
+        group_norm::instance_norm_wrt_gamma(x, gy, g_gamma, mean, inv_std, x_dim)
+    }
+
+    #[inline]
+    pub fn instance_norm_wrt_beta(
+        gy: &Array4<f32>,
+        g_beta: &mut Array4<f32>,
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(gy.raw_dim());
+        let gy = slice!(gy);
+        let g_beta = slice_mut!(g_beta);
+
+        group_norm::instance_norm_wrt_beta(gy, g_beta, x_dim)
+    }
+
+    #[inline]
+    pub fn im2col(
+        x: &Array4<f32>,
+        y: &mut Array4<f32>,
+        f_dim: Dim<[usize; 4]>,
+        stride: [usize; 2],
+        padh: [usize; 2],
+        padw: [usize; 2],
+        dilation: [usize; 2],
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(x.raw_dim());
+        let f_dim = to_array4(f_dim);
+        let x = slice!(x);
+        let y = slice_mut!(y);
+
+        im2col::im2col(x, y, x_dim, f_dim, stride, padh, padw, dilation)
+    }
+
+    #[inline]
+    pub fn im2col_wrt_x(
+        gy: &Array4<f32>,
+        gx: &mut Array4<f32>,
+        f_dim: Dim<[usize; 4]>,
+        stride: [usize; 2],
+        padh: [usize; 2],
+        padw: [usize; 2],
+        dilation: [usize; 2],
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(gx.raw_dim());
+        let f_dim = to_array4(f_dim);
+
+        let gy = slice!(gy);
+        let gx = slice_mut!(gx);
+
+        im2col::im2col_wrt_x(gy, gx, x_dim, f_dim, stride, padh, padw, dilation)
+    }
+    
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn kl_div(
+        t: &Array4<f32>,
+        x: &Array4<f32>,
+        e: &mut Array4<f32>,
+        gt: &mut Array4<f32>,
+        gx: &mut Array4<f32>,
+        temperature: f32,
+        sample_weights: Option<&[f32]>,
+        reduction: Reduction,
+    ) -> Result<(), BMLSError> {
+        let dim = to_array2(x.raw_dim());
+        let t = slice!(t);
+        let x = slice!(x);
+        let e = slice_mut!(e);
+        let gt = slice_mut!(gt);
+        let gx = slice_mut!(gx);
+
+        kl_div::kl_div(t, x, e, gt, gx, dim, temperature, sample_weights, reduction)
+    }
+
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn lamb(
+        g: &Array4<f32>,
+        v: &mut Array4<f32>,
+        s: &mut Array4<f32>,
+        w: &mut Array4<f32>,
+        lr: f32,
+        beta1: f32,
+        beta2: f32,
+        epsilon: f32,
+        decay: WeightDecay,
+        t: usize,
+    ) -> Result<(), BMLSError> {
+        let g = slice!(g);
+        let v = slice_mut!(v);
+        let s = slice_mut!(s);
+        let w = slice_mut!(w);
+
+        lamb::lamb(g, v, s, w, lr, beta1, beta2, epsilon, decay, t)
+    }
+
+    #[inline]
+    pub fn lars(
+        g: &Array4<f32>,
+        v: &mut Array4<f32>,
+        w: &mut Array4<f32>,
+        lr: f32,
+        beta: f32,
+        trust_coef: f32,
+        decay: WeightDecay,
+    ) -> Result<(), BMLSError> {
+        let g = slice!(g);
+        let v = slice_mut!(v);
+        let w = slice_mut!(w);
+
+        lars::lars(g, v, w, lr, beta, trust_coef, decay)
+    }
+
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn layer_norm(
+        x: &Array4<f32>,
+        y: &mut Array4<f32>,
+        gamma: &Array4<f32>,
+        beta: &Array4<f32>,
+        mean: &mut Array4<f32>,
+        inv_std: &mut Array4<f32>,
+        axis: usize,
+        epsilon: f32,
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(x.raw_dim());
+        let x = slice!(x);
+        let y = slice_mut!(y);
+        let gamma = slice!(gamma);
+        let beta = slice!(beta);
+        let mean = slice_mut!(mean);
+        let inv_std = slice_mut!(inv_std);
+
+        layer_norm::layer_norm(x, y, gamma, beta, mean, inv_std, x_dim, axis, epsilon)
+    }
+
+    #[inline]
+    pub fn layer_norm_wrt_x(
+        x: &Array4<f32>,
+        gy: &Array4<f32>,
+        gx: &mut Array4<f32>,
+        gamma: &Array4<f32>,
+        mean: &Array4<f32>,
+        inv_std: &Array4<f32>,
+        axis: usize,
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(x.raw_dim());
+        let x = slice!(x);
+        let gy = slice!(gy);
+        let gx = slice_mut!(gx);
+        let gamma = slice!(gamma);
+        let mean = slice!(mean);
+        let inv_std = slice!(inv_std);
+
+        layer_norm::layer_norm_wrt_x(x, gy, gx, gamma, mean, inv_std, x_dim, axis)
+    }
+
+    #[inline]
+    pub fn layer_norm_wrt_gamma(
+        x: &Array4<f32>,
+        gy: &Array4<f32>,
+        g_gamma: &mut Array4<f32>,
+        mean: &Array4<f32>,
+        inv_std: &Array4<f32>,
+        axis: usize,
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(x.raw_dim());
+        let x = slice!(x);
+        let gy = slice!(gy);
+        let g_gamma = slice_mut!(g_gamma);
+        let mean = slice!(mean);
+        let inv_std = slice!(inv_std);
+
+        layer_norm::layer_norm_wrt_gamma(x, gy, g_gamma, mean, inv_std, x_dim, axis)
+    }
+
+    #[inline]
+    pub fn layer_norm_wrt_beta(
+        gy: &Array4<f32>,
+        g_beta: &mut Array4<f32>,
+        axis: usize,
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(gy.raw_dim());
+        let gy = slice!(gy);
+        let g_beta = slice_mut!(g_beta);
+
+        layer_norm::layer_norm_wrt_beta(gy, g_beta, x_dim, axis)
+    }
+
+    #[inline]
+    pub fn leaky_relu(
+        x: &Array4<f32>,
+        y: &mut Array4<f32>,
+        a: f32,
+    ) -> Result<(), BMLSError> {
+        let x = slice!(x);
+        let y = slice_mut!(y);
+
+        leaky_relu::leaky_relu(x, y, a)
+    }
+
+    #[inline]
+    pub fn leaky_relu_wrt_x(
+        x: &Array4<f32>,
+        gy: &Array4<f32>,
+        gx: &mut Array4<f32>,
+        a: f32,
+    ) -> Result<(), BMLSError> {
+        let x = slice!(x);
+        let gy = slice!(gy);
+        let gx = slice_mut!(gx);
+
+        leaky_relu::leaky_relu_wrt_x(x, gy, gx, a)
+    }
+
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn sigmoid_focal(
+        t: &Array4<f32>,
+        x: &Array4<f32>,
+        e: &mut Array4<f32>,
+        g: &mut Array4<f32>,
+        gamma: f32,
+        alpha: Option<f32>,
+        sample_weights: Option<&[f32]>,
+        reduction: Reduction,
+    ) -> Result<(), BMLSError> {
+        let dim = to_array2(x.raw_dim());
+        let t = slice!(t);
+        let x = slice!(x);
+        let e = slice_mut!(e);
+        let g = slice_mut!(g);
+
+        focal::sigmoid_focal(t, x, e, g, dim, gamma, alpha, sample_weights, reduction)
+    }
+
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn softmax_focal(
+        t: &Array4<f32>,
+        x: &Array4<f32>,
+        e: &mut Array4<f32>,
+        g: &mut Array4<f32>,
+        gamma: f32,
+        alpha: Option<&[f32]>,
+        sample_weights: Option<&[f32]>,
+        reduction: Reduction,
+    ) -> Result<(), BMLSError> {
+        let dim = to_array2(x.raw_dim());
+        let t = slice!(t);
+        let x = slice!(x);
+        let e = slice_mut!(e);
+        let g = slice_mut!(g);
+
+        focal::softmax_focal(t, x, e, g, dim, gamma, alpha, sample_weights, reduction)
+    }
+
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn group_norm(
+        x: &Array4<f32>,
+        y: &mut Array4<f32>,
+        gamma: &Array4<f32>,
+        beta: &Array4<f32>,
+        mean: &mut Array4<f32>,
+        inv_std: &mut Array4<f32>,
+        groups: usize,
+        epsilon: f32,
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(x.raw_dim());
+        let x = slice!(x);
+        let y = slice_mut!(y);
+        let gamma = slice!(gamma);
+        let beta = slice!(beta);
+        let mean = slice_mut!(mean);
+        let inv_std = slice_mut!(inv_std);
+
+        group_norm::group_norm(x, y, gamma, beta, mean, inv_std, x_dim, groups, epsilon)
+    }
+
+    #[inline]
+    pub fn group_norm_wrt_x(
+        x: &Array4<f32>,
+        gy: &Array4<f32>,
+        gx: &mut Array4<f32>,
+        gamma: &Array4<f32>,
+        mean: &Array4<f32>,
+        inv_std: &Array4<f32>,
+        groups: usize,
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(x.raw_dim());
+        let x = slice!(x);
+        let gy = slice!(gy);
+        let gx = slice_mut!(gx);
+        let gamma = slice!(gamma);
+        let mean = slice!(mean);
+        let inv_std = slice!(inv_std);
+
+        group_norm::group_norm_wrt_x(x, gy, gx, gamma, mean, inv_std, x_dim, groups)
+    }
+
+    #[inline]
+    pub fn group_norm_wrt_gamma(
+        x: &Array4<f32>,
+        gy: &Array4<f32>,
+        g_gamma: &mut Array4<f32>,
+        mean: &Array4<f32>,
+        inv_std: &Array4<f32>,
+        groups: usize,
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(x.raw_dim());
+        let x = slice!(x);
+        let gy = slice!(gy);
+        let g_gamma = slice_mut!(g_gamma);
+        let mean = slice!(mean);
+        let inv_std = slice!(inv_std);
+
+        group_norm::group_norm_wrt_gamma(x, gy, g_gamma, mean, inv_std, x_dim, groups)
+    }
+
+    #[inline]
+    pub fn group_norm_wrt_beta(
+        gy: &Array4<f32>,
+        g_beta: &mut Array4<f32>,
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(gy.raw_dim());
+        let gy = slice!(gy);
+        let g_beta = slice_mut!(g_beta);
+
+        group_norm::group_norm_wrt_beta(gy, g_beta, x_dim)
+    }
+
+    #[inline]
+    pub fn has_overflow(
+        g: &Array4<f32>,
+    ) -> Result<bool, BMLSError> {
+        let g = slice!(g);
+
+        Ok(loss_scale::has_overflow(g))
+    }
+
+    #[inline]
+    pub fn log_softmax(
+        x: &Array4<f32>,
+        y: &mut Array4<f32>,
+        axis: Axis,
+    ) -> Result<(), BMLSError> {
+        let dim = to_array4(x.raw_dim());
+        let x = slice!(x);
+        let y = slice_mut!(y);
+
+        log_softmax::log_softmax(x, y, dim, axis.0)
+    }
+
+    #[inline]
+    pub fn log_softmax_wrt_x(
+        y: &Array4<f32>,
+        gy: &Array4<f32>,
+        gx: &mut Array4<f32>,
+        axis: Axis,
+    ) -> Result<(), BMLSError> {
+        let dim = to_array4(y.raw_dim());
+        let y = slice!(y);
+        let gy = slice!(gy);
+        let gx = slice_mut!(gx);
+
+        log_softmax::log_softmax_wrt_x(y, gy, gx, dim, axis.0)
+    }
+
+    #[inline]
+    pub fn log_cosh(
+        t: &Array4<f32>,
+        p: &Array4<f32>,
+        e: &mut Array4<f32>,
+        g: &mut Array4<f32>,
+        sample_weights: Option<&[f32]>,
+        reduction: Reduction,
+    ) -> Result<(), BMLSError> {
+        let dim = to_array2(t.raw_dim());
+        let t = slice!(t);
+        let p = slice!(p);
+        let e = slice_mut!(e);
+        let g = slice_mut!(g);
+
+        log_cosh::log_cosh(t, p, e, g, dim, sample_weights, reduction)
+    }
+
+    #[inline]
+    pub fn lrn(
+        x: &Array4<f32>,
+        y: &mut Array4<f32>,
+        n: usize,
+        alpha: f32,
+        beta: f32,
+        k: f32,
+        inter: bool,
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(x.raw_dim());
+        let x = slice!(x);
+        let y = slice_mut!(y);
+
+        lrn::lrn(x, y, x_dim, n, alpha, beta, k, inter)
     }
 
     #[inline]
@@ -634,6 +1750,24 @@ pub mod prelude {
         lrn::lrn_wrt_x()
     }
 
+    #[inline]
+    pub fn mae(
+        t: &Array4<f32>,
+        p: &Array4<f32>,
+        e: &mut Array4<f32>,
+        g: &mut Array4<f32>,
+        sample_weights: Option<&[f32]>,
+        reduction: Reduction,
+    ) -> Result<(), BMLSError> {
+        let dim = to_array2(t.raw_dim());
+        let t = slice!(t);
+        let p = slice!(p);
+        let e = slice_mut!(e);
+        let g = slice_mut!(g);
+
+        mae::mae(t, p, e, g, dim, sample_weights, reduction)
+    }
+
     #[inline]
     pub fn matmul(
         a: &Array4<f32>,
@@ -693,6 +1827,7 @@ pub mod prelude {
     }
 
     #[inline]
+    #[allow(clippy::too_many_arguments)]
     pub fn max_pool(
         x: &Array4<f32>,
         y: &mut Array4<f32>,
@@ -701,13 +1836,14 @@ pub mod prelude {
         stride: [usize; 2],
         padh: [usize; 2],
         padw: [usize; 2],
+        dilation: [usize; 2],
     ) -> Result<(), BMLSError> {
         let x_dim = to_array4(x.raw_dim());
         let x = slice!(x);
         let y = slice_mut!(y);
         let i = slice_mut!(i);
 
-        max_pool::max_pool(x, y, i, x_dim, kernel, stride, padh, padw)
+        max_pool::max_pool(x, y, i, x_dim, kernel, stride, padh, padw, dilation)
     }
 
     #[inline]
@@ -730,12 +1866,13 @@ pub mod prelude {
         w: &mut Array4<f32>,
         lr: f32,
         beta: f32,
+        decay: WeightDecay,
     ) -> Result<(), BMLSError> {
         let g = slice!(g);
         let v = slice_mut!(v);
         let w = slice_mut!(w);
 
-        momentum::momentum(g, v, w, lr, beta)
+        momentum::momentum(g, v, w, lr, beta, decay)
     }
 
     #[inline]
@@ -743,7 +1880,9 @@ pub mod prelude {
         t: &Array4<f32>,
         p: &Array4<f32>,
         e: &mut Array4<f32>,
-        g: &mut Array4<f32>, 
+        g: &mut Array4<f32>,
+        sample_weights: Option<&[f32]>,
+        reduction: Reduction,
     ) -> Result<(), BMLSError> {
         let dim = to_array2(t.raw_dim());
         let t = slice!(t);
@@ -751,7 +1890,7 @@ pub mod prelude {
         let e = slice_mut!(e);
         let g = slice_mut!(g);
 
-        mse::mse(t, p, e, g, dim)
+        mse::mse(t, p, e, g, dim, sample_weights, reduction)
     }
 
     #[inline]
@@ -793,6 +1932,63 @@ pub mod prelude {
         mul::mul_wrt_x2(x1, gy, g2)
     }
 
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn nadam(
+        g: &Array4<f32>,
+        v: &mut Array4<f32>,
+        s: &mut Array4<f32>,
+        w: &mut Array4<f32>,
+        lr: f32,
+        beta1: f32,
+        beta2: f32,
+        epsilon: f32,
+        decay: WeightDecay,
+        t: usize,
+    ) -> Result<(), BMLSError> {
+        let g = slice!(g);
+        let v = slice_mut!(v);
+        let s = slice_mut!(s);
+        let w = slice_mut!(w);
+
+        nadam::nadam(g, v, s, w, lr, beta1, beta2, epsilon, decay, t)
+    }
+
+    #[inline]
+    pub fn nesterov(
+        g: &Array4<f32>,
+        v: &mut Array4<f32>,
+        w: &mut Array4<f32>,
+        lr: f32,
+        beta: f32,
+        decay: WeightDecay,
+    ) -> Result<(), BMLSError> {
+        let g = slice!(g);
+        let v = slice_mut!(v);
+        let w = slice_mut!(w);
+
+        nesterov::nesterov(g, v, w, lr, beta, decay)
+    }
+
+    #[inline]
+    pub fn quantile(
+        t: &Array4<f32>,
+        p: &Array4<f32>,
+        e: &mut Array4<f32>,
+        g: &mut Array4<f32>,
+        q: f32,
+        sample_weights: Option<&[f32]>,
+        reduction: Reduction,
+    ) -> Result<(), BMLSError> {
+        let dim = to_array2(t.raw_dim());
+        let t = slice!(t);
+        let p = slice!(p);
+        let e = slice_mut!(e);
+        let g = slice_mut!(g);
+
+        quantile::quantile(t, p, e, g, dim, q, sample_weights, reduction)
+    }
+
     #[inline]
     pub fn reduce_mean(
         x: &Array4<f32>,
@@ -869,6 +2065,60 @@ pub mod prelude {
         relu::relu_wrt_x(x, gy, gx)
     }
 
+    #[inline]
+    pub fn rms_norm(
+        x: &Array4<f32>,
+        y: &mut Array4<f32>,
+        gamma: &Array4<f32>,
+        inv_rms: &mut Array4<f32>,
+        axis: usize,
+        epsilon: f32,
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(x.raw_dim());
+        let x = slice!(x);
+        let y = slice_mut!(y);
+        let gamma = slice!(gamma);
+        let inv_rms = slice_mut!(inv_rms);
+
+        rms_norm::rms_norm(x, y, gamma, inv_rms, x_dim, axis, epsilon)
+    }
+
+    #[inline]
+    pub fn rms_norm_wrt_x(
+        x: &Array4<f32>,
+        gy: &Array4<f32>,
+        gx: &mut Array4<f32>,
+        gamma: &Array4<f32>,
+        inv_rms: &Array4<f32>,
+        axis: usize,
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(x.raw_dim());
+        let x = slice!(x);
+        let gy = slice!(gy);
+        let gx = slice_mut!(gx);
+        let gamma = slice!(gamma);
+        let inv_rms = slice!(inv_rms);
+
+        rms_norm::rms_norm_wrt_x(x, gy, gx, gamma, inv_rms, x_dim, axis)
+    }
+
+    #[inline]
+    pub fn rms_norm_wrt_gamma(
+        x: &Array4<f32>,
+        gy: &Array4<f32>,
+        g_gamma: &mut Array4<f32>,
+        inv_rms: &Array4<f32>,
+        axis: usize,
+    ) -> Result<(), BMLSError> {
+        let x_dim = to_array4(x.raw_dim());
+        let x = slice!(x);
+        let gy = slice!(gy);
+        let g_gamma = slice_mut!(g_gamma);
+        let inv_rms = slice!(inv_rms);
+
+        rms_norm::rms_norm_wrt_gamma(x, gy, g_gamma, inv_rms, x_dim, axis)
+    }
+
     #[inline]
     pub fn rms_prop(
         g: &Array4<f32>,
@@ -876,12 +2126,13 @@ pub mod prelude {
         w: &mut Array4<f32>,
         lr: f32,
         beta: f32,
+        decay: WeightDecay,
     ) -> Result<(), BMLSError> {
         let g = slice!(g);
         let s = slice_mut!(s);
         let w = slice_mut!(w);
 
-        rms_prop::rms_prop(g, s, w, lr, beta)
+        rms_prop::rms_prop(g, s, w, lr, beta, decay)
     }
 
     #[inline]
@@ -917,11 +2168,12 @@ pub mod prelude {
         g: &Array4<f32>,
         w: &mut Array4<f32>,
         lr: f32,
+        decay: WeightDecay,
     ) -> Result<(), BMLSError> {
         let g = slice!(g);
         let w = slice_mut!(w);
 
-        sgd::sgd(g, w, lr)
+        sgd::sgd(g, w, lr, decay)
     }
 
     #[inline]
@@ -952,12 +2204,13 @@ pub mod prelude {
     pub fn softmax(
         x: &Array4<f32>,
         y: &mut Array4<f32>,
+        axis: Axis,
     ) -> Result<(), BMLSError> {
-        let dim = to_array2(x.raw_dim());
+        let dim = to_array4(x.raw_dim());
         let x = slice!(x);
         let y = slice_mut!(y);
 
-        softmax::softmax(x, y, dim)
+        softmax::softmax(x, y, dim, axis.0)
     }
 
     #[inline]
@@ -965,13 +2218,14 @@ pub mod prelude {
         y: &Array4<f32>,
         gy: &Array4<f32>,
         gx: &mut Array4<f32>,
+        axis: Axis,
     ) -> Result<(), BMLSError> {
-        let dim = to_array2(y.raw_dim());
+        let dim = to_array4(y.raw_dim());
         let y = slice!(y);
         let gy = slice!(gy);
         let gx = slice_mut!(gx);
 
-        softmax::softmax_wrt_x(y, gy, gx, dim)
+        softmax::softmax_wrt_x(y, gy, gx, dim, axis.0)
     }
 
     #[inline]
@@ -1009,6 +2263,51 @@ pub mod prelude {
         sub::sub_wrt_x2(gy, g2)
     }
 
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn squared_hinge(
+        t: &[usize],
+        x: &Array4<f32>,
+        e: &mut Array4<f32>,
+        g: &mut Array4<f32>,
+        margin: f32,
+        sample_weights: Option<&[f32]>,
+        reduction: Reduction,
+    ) -> Result<(), BMLSError> {
+        let dim = to_array2(x.raw_dim());
+        let x = slice!(x);
+        let e = slice_mut!(e);
+        let g = slice_mut!(g);
+
+        hinge::squared_hinge(t, x, e, g, dim, margin, sample_weights, reduction)
+    }
+
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn triplet_margin(
+        a: &Array4<f32>,
+        p: &Array4<f32>,
+        neg: &Array4<f32>,
+        e: &mut Array4<f32>,
+        ga: &mut Array4<f32>,
+        gp: &mut Array4<f32>,
+        gn: &mut Array4<f32>,
+        margin: f32,
+        sample_weights: Option<&[f32]>,
+        reduction: Reduction,
+    ) -> Result<(), BMLSError> {
+        let dim = to_array2(a.raw_dim());
+        let a = slice!(a);
+        let p = slice!(p);
+        let neg = slice!(neg);
+        let e = slice_mut!(e);
+        let ga = slice_mut!(ga);
+        let gp = slice_mut!(gp);
+        let gn = slice_mut!(gn);
+
+        triplet_margin::triplet_margin(a, p, neg, e, ga, gp, gn, dim, margin, sample_weights, reduction)
+    }
+
     #[inline]
     pub fn tanh(
         x: &Array4<f32>,
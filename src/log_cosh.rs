@@ -0,0 +1,83 @@
+use itertools::izip;
+use crate::error::BMLSError;
+use crate::error;
+use crate::reduction::{Reduction, reduce_rows};
+
+/// # Log-Cosh Loss
+/// - T: Truth Label (N x C)
+/// - P: Prediction (N x C)
+/// - E: Error, (N x 1) vector for `Reduction::None`, otherwise (1)
+/// - G: Gradient of E w.r.t. P (N x C)
+/// - Dim: [N, C]
+/// - Sample_Weights: Optional weight for each row (N)
+/// - Reduction: How the rows are combined into E
+/// 
+/// The error of each row is the mean over C.
+/// 
+/// With d = p - t: ln(cosh(d)), which is about d^2 / 2 for small d
+/// and |d| - ln(2) for large d. The gradient is tanh(d).
+#[inline]
+pub fn log_cosh(
+    t: &[f32],
+    p: &[f32],
+    e: &mut [f32],
+    g: &mut [f32],
+    dim: [usize; 2],
+    sample_weights: Option<&[f32]>,
+    reduction: Reduction,
+) -> Result<(), BMLSError> {
+    let len = dim[0] * dim[1];
+    if t.len() != len {
+        return error::length_mismatch("T", t.len(), "Dim", len)
+    }
+
+    if t.len() != p.len() {
+        return error::length_mismatch("T", t.len(), "P", p.len())
+    }
+
+    if g.len() != p.len() {
+        return error::length_mismatch("G", g.len(), "P", p.len())
+    }
+
+    let cols = dim[1];
+    let scale = 1. / cols as f32;
+
+    reduce_rows(e, g, dim, sample_weights, reduction, |i, g| {
+        let t = &t[i * cols..(i + 1) * cols];
+        let p = &p[i * cols..(i + 1) * cols];
+
+        let mut sum = 0.0;
+
+        for (t, p, g) in izip!(t, p, g) {
+            let d = p - t;
+
+            // cosh(d) overflows for |d| > ~89, so use
+            // ln(cosh(d)) = |d| + ln(1 + e^(-2|d|)) - ln(2)
+            sum += d.abs() + f32::ln_1p(f32::exp(-2. * d.abs())) - std::f32::consts::LN_2;
+            *g = f32::tanh(d) * scale;
+        }
+
+        Some(sum * scale)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_log_cosh() {
+        let t = [0.0, 0.0];
+        let p = [0.5, -200.0];
+        let mut e = [0.0; 2];
+        let mut g = [0.0; 2];
+
+        log_cosh(&t, &p, &mut e, &mut g, [2, 1], None, Reduction::None).unwrap();
+
+        assert!((e[0] - f32::ln(f32::cosh(0.5))).abs() < 1e-6);
+        assert!((e[1] - (200.0 - std::f32::consts::LN_2)).abs() < 1e-3);
+        assert!((g[0] - f32::tanh(0.5)).abs() < 1e-6);
+        assert_eq!(g[1], -1.0);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::error::BMLSError;
+use crate::error;
+use crate::softmax::{axis_strides, max_along};
+
+/// # Log Softmax Operator
+/// - X: Input
+/// - Y: Output
+/// - Dim: Dimensions of X and Y
+/// - Axis: Axis to take the log softmax along
+/// 
+/// y = x - max - ln(sum(e^(x - max))), which stays finite
+/// where ln(softmax(x)) would underflow to -inf.
+#[inline]
+pub fn log_softmax(
+    x: &[f32],
+    y: &mut [f32],
+    dim: [usize; 4],
+    axis: usize,
+) -> Result<(), BMLSError> {
+    if axis > 3 {
+        return error::invalid_axis(axis);
+    }
+
+    let len = dim[0]*dim[1]*dim[2]*dim[3];
+    if x.len() != len {
+        return error::length_mismatch("X", x.len(), "Dim", len);
+    }
+
+    if y.len() != len { 
+        return error::length_mismatch("Y", y.len(), "Dim", len);
+    }
+
+    let (outer, size, inner) = axis_strides(dim, axis);
+
+    for o in 0..outer {
+        for i in 0..inner {
+            let base = o * size * inner + i;
+            let max = max_along(x, base, size, inner);
+
+            let mut sum = 0.0;
+            for k in 0..size {
+                sum += f32::exp(x[base + k * inner] - max);
+            }
+
+            let lse = max + f32::ln(sum);
+            for k in 0..size {
+                let yi = base + k * inner;
+                y[yi] = x[yi] - lse;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// # Log Softmax w.r.t. X
+/// - Y: Output of the forward op
+/// - GY: Gradient w.r.t. Y
+/// - GX: Gradient w.r.t. X
+/// - Dim: Dimensions of X and Y
+/// - Axis: Axis of the forward op
+/// 
+/// gx = gy - e^y * sum(gy)
+#[inline]
+pub fn log_softmax_wrt_x(
+    y: &[f32],
+    gy: &[f32],
+    gx: &mut [f32],
+    dim: [usize; 4],
+    axis: usize,
+) -> Result<(), BMLSError> {
+    if axis > 3 {
+        return error::invalid_axis(axis);
+    }
+
+    let len = dim[0]*dim[1]*dim[2]*dim[3];
+    if y.len() != len {
+        return error::length_mismatch("Y", y.len(), "Dim", len);
+    }
+
+    if gy.len() != len {
+        return error::length_mismatch("GY", gy.len(), "Dim", len);
+    }
+
+    if gx.len() != len {
+        return error::length_mismatch("GX", gx.len(), "Dim", len);
+    }
+
+    let (outer, size, inner) = axis_strides(dim, axis);
+
+    for o in 0..outer {
+        for i in 0..inner {
+            let base = o * size * inner + i;
+
+            let mut sum = 0.0;
+            for k in 0..size {
+                sum += gy[base + k * inner];
+            }
+
+            for k in 0..size {
+                let xi = base + k * inner;
+                gx[xi] += gy[xi] - f32::exp(y[xi]) * sum;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_log_softmax() {
+        let x = [1.0, 2.0, 3.0, 1000.0, 0.0, -1000.0];
+        let mut y = [0.0; 6];
+
+        // along the first axis of [2, 3, 1, 1]
+        log_softmax(&x, &mut y, [2, 3, 1, 1], 0).unwrap();
+
+        assert!((y[0] - (1.0 - 1000.0)).abs() < 1e-3);
+        assert!((y[3] - 0.0).abs() < 1e-6);
+        assert!((y[5] - (-1003.0)).abs() < 1e-3);
+        assert!(y.iter().all(|y| y.is_finite()));
+    }
+
+    #[test]
+    fn test_log_softmax_wrt_x() {
+        // [1, 3, 1, 2], along the channels
+        let dim = [1, 3, 1, 2];
+        let x = [0.5, -1.0, 2.0, 0.3, -0.7, 1.1];
+        let gy = [0.1, -0.4, 0.3, 0.9, 0.2, -0.6];
+
+        let mut y = [0.0; 6];
+        let mut gx = [0.0; 6];
+        log_softmax(&x, &mut y, dim, 1).unwrap();
+        log_softmax_wrt_x(&y, &gy, &mut gx, dim, 1).unwrap();
+
+        // finite differences of sum(gy * y)
+        let f = |x: &[f32]| {
+            let mut y = [0.0; 6];
+            log_softmax(x, &mut y, dim, 1).unwrap();
+            y.iter().zip(gy).map(|(y, g)| y * g).sum::<f32>()
+        };
+
+        for i in 0..6 {
+            let mut xp = x;
+            let mut xm = x;
+            xp[i] += 1e-2;
+            xm[i] -= 1e-2;
+            let fd = (f(&xp) - f(&xm)) / 2e-2;
+            assert!((fd - gx[i]).abs() < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use itertools::izip;
+use crate::error::BMLSError;
+use crate::error;
+use crate::ema::check_shapes;
+
+/// # Lookahead Optimizer
+/// Wraps any optimizer (the inner, or fast, optimizer) and keeps
+/// a set of slow weights. Every K steps the slow weights move 
+/// toward the fast weights, and the fast weights are reset to them.
+/// 
+/// - K: Number of inner steps between syncs
+/// - Alpha: Step size of the slow weights, e.g. 0.5
+/// 
+/// slow = slow + alpha * (fast - slow)
+#[derive(Clone, Debug)]
+pub struct Lookahead {
+    pub k: usize,
+    pub alpha: f32,
+    slow: Vec<Vec<f32>>,
+    steps: usize,
+}
+
+impl Lookahead {
+    /// - W: Weight Tensors, copied as the initial slow weights.
+    /// - K: Hyperparameter
+    /// - Alpha: Hyperparameter
+    pub fn new(w: &[&[f32]], k: usize, alpha: f32) -> Self {
+        Self {
+            k,
+            alpha,
+            slow: w.iter().map(|w| w.to_vec()).collect(),
+            steps: 0,
+        }
+    }
+
+    /// The slow weights, one per parameter.
+    pub fn slow(&self) -> &[Vec<f32>] {
+        &self.slow
+    }
+
+    /// # Lookahead Step
+    /// - W: Weight Tensors (the fast weights)
+    /// - Inner: Performs one step of the inner optimizer on W
+    /// 
+    /// ```ignore
+    /// lookahead.step(&mut w, |w| {
+    ///     for (g, v, w) in izip!(&g, &mut v, w) {
+    ///         momentum(g, v, w, lr, beta, WeightDecay::None)?;
+    ///     }
+    ///     Ok(())
+    /// })?;
+    /// ```
+    pub fn step<F>(
+        &mut self,
+        w: &mut [&mut [f32]],
+        inner: F,
+    ) -> Result<(), BMLSError> 
+    where
+        F: FnOnce(&mut [&mut [f32]]) -> Result<(), BMLSError>
+    {
+        if self.k == 0 {
+            return error::invalid_lookahead_steps(self.k)
+        }
+
+        check_shapes(&self.slow, w.iter().map(|w| w.len()), w.len())?;
+
+        inner(w)?;
+
+        self.steps += 1;
+        if self.steps < self.k {
+            return Ok(())
+        }
+
+        self.steps = 0;
+
+        let alpha = self.alpha;
+        for (slow, fast) in izip!(&mut self.slow, w) {
+            for (slow, fast) in izip!(slow, fast.iter_mut()) {
+                *slow += alpha * (*fast - *slow);
+                *fast = *slow;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::sgd::sgd;
+    use crate::WeightDecay;
+
+    #[test]
+    fn test_lookahead() {
+        let g = vec![1.0; 2];
+        let mut w = vec![0.0; 2];
+        let mut la = Lookahead::new(&[&w], 2, 0.5);
+
+        let inner = |w: &mut [&mut [f32]]| sgd(&g, w[0], 1.0, WeightDecay::None);
+
+        la.step(&mut [&mut w], inner).unwrap();
+        assert_eq!(w, [-1.0; 2]);
+        assert_eq!(la.slow()[0], [0.0; 2]);
+
+        // sync: slow moves halfway to -2, fast is reset to slow.
+        la.step(&mut [&mut w], inner).unwrap();
+        assert_eq!(w, [-1.0; 2]);
+        assert_eq!(la.slow()[0], [-1.0; 2]);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::error::BMLSError;
+
+/// # Has Overflow
+/// - G: Gradient
+/// 
+/// True if any value in G is infinite or NaN.
+#[inline]
+pub fn has_overflow(g: &[f32]) -> bool {
+    g.iter().any(|g| !g.is_finite())
+}
+
+/// # Dynamic Loss Scaler
+/// Scales the loss gradient up before the backward pass, so small
+/// gradients survive reduced precision, and scales the parameter
+/// gradients back down before the optimizer step.
+/// 
+/// - Scale: Initial scale, e.g. 65536
+/// - Growth_Factor: Multiplies the scale after Growth_Interval clean steps
+/// - Backoff_Factor: Multiplies the scale after an overflow
+/// - Growth_Interval: Number of steps without overflow before growing
+/// 
+/// When the gradients overflow, the optimizer step is skipped
+/// and the scale is reduced.
+#[derive(Clone, Debug)]
+pub struct LossScaler {
+    pub growth_factor: f32,
+    pub backoff_factor: f32,
+    pub growth_interval: usize,
+    scale: f32,
+    good_steps: usize,
+}
+
+impl LossScaler {
+    pub fn new(
+        scale: f32,
+        growth_factor: f32,
+        backoff_factor: f32,
+        growth_interval: usize,
+    ) -> Self {
+        Self {
+            growth_factor,
+            backoff_factor,
+            growth_interval,
+            scale,
+            good_steps: 0,
+        }
+    }
+
+    /// The current scale.
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// # Scale Gradient
+    /// - G: Gradient of the loss (like the G of `mse`), scaled in place.
+    pub fn scale_grad(&self, g: &mut [f32]) {
+        for g in g.iter_mut() {
+            *g *= self.scale;
+        }
+    }
+
+    /// # Loss Scaler Step
+    /// - G: Scaled Gradients w.r.t. each W, unscaled in place.
+    /// - Optimizer: Performs the optimizer step with the unscaled G.
+    /// 
+    /// Returns false if G overflowed and the step was skipped.
+    pub fn step<F>(
+        &mut self,
+        g: &mut [&mut [f32]],
+        optimizer: F,
+    ) -> Result<bool, BMLSError> 
+    where
+        F: FnOnce(&mut [&mut [f32]]) -> Result<(), BMLSError>
+    {
+        let inv = 1. / self.scale;
+        let mut overflow = false;
+        for g in g.iter_mut() {
+            for g in g.iter_mut() {
+                *g *= inv;
+            }
+            overflow |= has_overflow(g);
+        }
+
+        if !overflow {
+            optimizer(g)?;
+        }
+
+        self.update(overflow);
+
+        Ok(!overflow)
+    }
+
+    /// # Update
+    /// - Overflow: Whether the gradients of this step overflowed
+    /// 
+    /// Adjusts the scale. Only needed when not using `step`.
+    pub fn update(&mut self, overflow: bool) {
+        if overflow {
+            self.scale *= self.backoff_factor;
+            self.good_steps = 0;
+            return
+        }
+
+        self.good_steps += 1;
+        if self.good_steps >= self.growth_interval {
+            self.scale *= self.growth_factor;
+            self.good_steps = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::sgd::sgd;
+    use crate::WeightDecay;
+
+    #[test]
+    fn test_loss_scaler() {
+        let mut scaler = LossScaler::new(1024.0, 2.0, 0.5, 2);
+        let mut w = vec![1.0; 2];
+
+        let mut g = vec![0.25; 2];
+        scaler.scale_grad(&mut g);
+        assert_eq!(g, [256.0; 2]);
+
+        let stepped = scaler.step(&mut [&mut g], |g| sgd(g[0], &mut w, 1.0, WeightDecay::None)).unwrap();
+        assert!(stepped);
+        assert_eq!(w, [0.75; 2]);
+        assert_eq!(scaler.scale(), 1024.0);
+
+        // overflow: the step is skipped and the scale backs off
+        let mut g = vec![f32::INFINITY, 0.0];
+        let stepped = scaler.step(&mut [&mut g], |g| sgd(g[0], &mut w, 1.0, WeightDecay::None)).unwrap();
+        assert!(!stepped);
+        assert_eq!(w, [0.75; 2]);
+        assert_eq!(scaler.scale(), 512.0);
+
+        // two clean steps grow the scale
+        scaler.update(false);
+        scaler.update(false);
+        assert_eq!(scaler.scale(), 1024.0);
+    }
+}
@@ -0,0 +1,119 @@
+use itertools::izip;
+use crate::error::BMLSError;
+use crate::error;
+use crate::reduction::{Reduction, reduce_rows};
+
+/// # Mean Absolute Error (L1)
+/// - T: Truth Label (N x C)
+/// - P: Prediction (N x C)
+/// - E: Error, (N x 1) vector for `Reduction::None`, otherwise (1)
+/// - G: Gradient of E w.r.t. P (N x C)
+/// - Dim: [N, C]
+/// - Sample_Weights: Optional weight for each row (N)
+/// - Reduction: How the rows are combined into E
+/// 
+/// The error of each row is the mean over C.
+/// 
+/// The gradient is taken as 0 where p == t.
+#[inline]
+pub fn mae(
+    t: &[f32],
+    p: &[f32],
+    e: &mut [f32],
+    g: &mut [f32],
+    dim: [usize; 2],
+    sample_weights: Option<&[f32]>,
+    reduction: Reduction,
+) -> Result<(), BMLSError> {
+    let len = dim[0] * dim[1];
+    if t.len() != len {
+        return error::length_mismatch("T", t.len(), "Dim", len)
+    }
+
+    if t.len() != p.len() {
+        return error::length_mismatch("T", t.len(), "P", p.len())
+    }
+
+    if g.len() != p.len() {
+        return error::length_mismatch("G", g.len(), "P", p.len())
+    }
+
+    let cols = dim[1];
+    let scale = 1. / cols as f32;
+
+    reduce_rows(e, g, dim, sample_weights, reduction, |i, g| {
+        let t = &t[i * cols..(i + 1) * cols];
+        let p = &p[i * cols..(i + 1) * cols];
+
+        let mut sum = 0.0;
+
+        for (t, p, g) in izip!(t, p, g) {
+            let d = p - t;
+            sum += d.abs();
+
+            *g = if d > 0.0 {
+                scale
+            } else if d < 0.0 {
+                -scale
+            } else {
+                0.0
+            };
+        }
+
+        Some(sum * scale)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_mae_gradient() {
+        let dim = [2, 3];
+        let t = [0.0, 1.0, -1.0, 2.0, 0.5, 0.0];
+        let p = [0.3, 0.2, -1.5, 2.4, 1.5, -0.7];
+        let weights = [1.0, 3.0];
+
+        let cases = [
+            (None, Reduction::None),
+            (None, Reduction::Sum),
+            (None, Reduction::Mean),
+            (Some(&weights[..]), Reduction::Sum),
+            (Some(&weights[..]), Reduction::Mean),
+        ];
+
+        for (sw, reduction) in cases {
+            let len = reduction.output_len(2);
+            // the sum of E, the rows of `Reduction::None` are independent
+            let f = |p: &[f32]| {
+                let mut e = vec![0.0; len];
+                mae(&t, p, &mut e, &mut [0.0; 6], dim, sw, reduction).unwrap();
+                e.iter().sum::<f32>()
+            };
+
+            let mut e = vec![0.0; len];
+            let mut g = [0.0; 6];
+            mae(&t, &p, &mut e, &mut g, dim, sw, reduction).unwrap();
+
+            for i in 0..6 {
+                let mut pp = p;
+                let mut pm = p;
+                pp[i] += 1e-2;
+                pm[i] -= 1e-2;
+                let fd = (f(&pp) - f(&pm)) / 2e-2;
+                assert!((fd - g[i]).abs() < 1e-3);
+            }
+        }
+
+        // row errors are 1.6 / 3 and 2.1 / 3
+        let mut e = [0.0; 2];
+        mae(&t, &p, &mut e, &mut [0.0; 6], dim, None, Reduction::None).unwrap();
+        assert!((e[0] - 1.6 / 3.).abs() < 1e-6 && (e[1] - 0.7).abs() < 1e-6);
+
+        let mut e = [0.0];
+        mae(&t, &p, &mut e, &mut [0.0; 6], dim, Some(&weights), Reduction::Mean).unwrap();
+        assert!((e[0] - (1.6 / 3. + 3. * 0.7) / 4.).abs() < 1e-6);
+    }
+}
@@ -13,11 +13,14 @@ use crate::Ptr;
 /// - Stride: H and W strides of the Kernel
 /// - Padh: Height Padding
 /// - Padw: Width Padding
+/// - Dilation: H and W spacing between the elements of the Kernel, 1 for none
 /// 
 /// The batches and channels of Y are the same as X. \
-/// The height of Y: ((xh - kh + (padh.0 + padh.1)) / strideh) + 1 \
-/// The width  of Y: ((xw - kw + (padw.0 + padw.1)) / stridew) + 1
+/// The dilated kernel covers dh * (kh - 1) + 1 rows and dw * (kw - 1) + 1 columns. \
+/// The height of Y: ((xh - (dh * (kh - 1) + 1) + (padh.0 + padh.1)) / strideh) + 1 \
+/// The width  of Y: ((xw - (dw * (kw - 1) + 1) + (padw.0 + padw.1)) / stridew) + 1
 #[inline]
+#[allow(clippy::too_many_arguments)]
 pub fn max_pool(
     x: &[f32],
     y: &mut [f32],
@@ -27,13 +30,35 @@ pub fn max_pool(
     stride: [usize; 2],
     padh: [usize; 2],
     padw: [usize; 2],
+    dilation: [usize; 2],
 ) -> Result<(), BMLSError> {
     let (strideh, stridew) = (stride[0], stride[1]);
     let (kernelh, kernelw) = (kernel[0], kernel[1]);
+    let (dilh, dilw) = (dilation[0], dilation[1]);
     let (xn, xc, xh, xw) = (x_dim[0], x_dim[1], x_dim[2], x_dim[3]);
+    // the extent of the dilated kernel
+    let (hk, wk) = (dilh * kernelh.saturating_sub(1) + 1, dilw * kernelw.saturating_sub(1) + 1);
 
-    let hstart = ((x_dim[2] - kernelh + (padh[0] + padh[1])) / strideh) + 1;
-    let wstart = ((x_dim[3] - kernelw + (padw[0] + padw[1])) / stridew) + 1;
+    // the kernel dimensions cannot be 0 and the dilated kernel cannot
+    // be larger than the dimensions of the input + the padding.
+    if kernelh == 0 || hk > (xh+padh[0]+padh[1]) || 
+       kernelw == 0 || wk > (xw+padw[0]+padw[1]) 
+    {
+        return error::invalid_kernel_dim([1, 1, kernelh, kernelw])
+    }
+
+    // strides must not be 0
+    if strideh == 0 || stridew == 0 {
+        return error::invalid_strides(strideh, stridew)
+    }
+
+    // dilations must not be 0
+    if dilh == 0 || dilw == 0 {
+        return error::invalid_dilation(dilh, dilw)
+    }
+
+    let hstart = ((xh + (padh[0] + padh[1]) - hk) / strideh) + 1;
+    let wstart = ((xw + (padw[0] + padw[1]) - wk) / stridew) + 1;
 
     let (_, yc, yh, yw) = (x_dim[0], x_dim[1], hstart, wstart);
 
@@ -54,19 +79,6 @@ pub fn max_pool(
         return error::length_mismatch("I", i.len(), "Y", y.len())
     }
 
-    // the kernel dimensions cannot be 0 or greater than 
-    // the dimensions of the input + the padding.
-    if kernel[0] == 0 || kernel[0] >= (xh+padh[0]+padh[1]) || 
-       kernel[1] == 0 || kernel[1] >= (xw+padw[0]+padw[1]) 
-    {
-        return error::invalid_kernel_dim([1, 1, kernelh, kernelw])
-    }
-
-    // strides must not be 0
-    if strideh == 0 || stridew == 0 {
-        return error::invalid_strides(strideh, stridew)
-    }
-
     let x = Ptr::new(x);
     let y = Ptr::new(y);
     let i = Ptr::new(i);
@@ -82,8 +94,8 @@ pub fn max_pool(
                     for kh in 0..kernelh {
                         for kw in 0..kernelw {
 
-                            let xrow = ((h * strideh) + kh) as isize - padh[0] as isize;
-                            let xcol = ((w * stridew) + kw) as isize - padw[0] as isize;
+                            let xrow = ((h * strideh) + kh * dilh) as isize - padh[0] as isize;
+                            let xcol = ((w * stridew) + kw * dilw) as isize - padw[0] as isize;
 
                             if xrow >= xh as isize || xrow < 0 || xcol >= xw as isize || xcol < 0 {
                                 if max < 0.0 {
@@ -163,6 +175,7 @@ mod tests {
                 [1, 1],
                 [1, 1],
                 [1, 1],
+                [1, 1],
             ).unwrap();
 
         // Compare the calculated pooled values and max indices with the expected values
@@ -223,4 +236,53 @@ mod tests {
 
         //panic!("");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_max_pool_dilation() {
+        // 4x5, x[r, c] = 5r + c
+        let x: Vec<f32> = (0..20).map(|i| i as f32).collect();
+        // a 2x2 kernel dilated by (2, 1) covers 3x2, so Y is 2x4
+        let mut y = [0.0; 8];
+        let mut i = [0; 8];
+
+        max_pool(&x, &mut y, &mut i, [1, 1, 4, 5], [2, 2], [1, 1], [0, 0], [0, 0], [2, 1]).unwrap();
+
+        // the max of each window is at (h + 2, w + 1), the row between is skipped
+        for h in 0..2 {
+            for w in 0..4 {
+                assert_eq!(i[h * 4 + w], (h + 2) * 5 + w + 1);
+                assert_eq!(y[h * 4 + w], ((h + 2) * 5 + w + 1) as f32);
+            }
+        }
+
+        let mut gx = [0.0; 20];
+        max_pool_wrt_a(&i, &[1.0; 8], &mut gx).unwrap();
+        for (j, gx) in gx.iter().enumerate() {
+            let (r, c) = (j / 5, j % 5);
+            assert_eq!(*gx, if r >= 2 && c >= 1 { 1.0 } else { 0.0 });
+        }
+
+        assert!(matches!(
+            max_pool(&x, &mut y, &mut i, [1, 1, 4, 5], [2, 2], [1, 1], [0, 0], [0, 0], [0, 1]),
+            Err(BMLSError::InvalidDilation(0, 1))
+        ));
+    }
+
+    #[test]
+    fn test_max_pool_full_kernel() {
+        // a kernel as large as the padded input gives a 1x1 output
+        let x: Vec<f32> = (0..9).map(|i| i as f32).collect();
+        let mut y = [0.0];
+        let mut i = [0];
+
+        max_pool(&x, &mut y, &mut i, [1, 1, 3, 3], [3, 3], [1, 1], [0, 0], [0, 0], [1, 1]).unwrap();
+        assert_eq!((y[0], i[0]), (8.0, 8));
+
+        // the dilated extent is what must fit, 2x2 dilated by 2 covers 3x3
+        max_pool(&x, &mut y, &mut i, [1, 1, 3, 3], [2, 2], [1, 1], [0, 0], [0, 0], [2, 2]).unwrap();
+        assert_eq!((y[0], i[0]), (8.0, 8));
+
+        assert!(max_pool(&x, &mut y, &mut i, [1, 1, 3, 3], [2, 2], [1, 1], [0, 0], [0, 0], [3, 3]).is_err());
+        assert!(max_pool(&x, &mut y, &mut i, [1, 1, 3, 3], [4, 3], [1, 1], [0, 0], [0, 0], [1, 1]).is_err());
+    }
+}
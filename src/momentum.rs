@@ -1,7 +1,7 @@
-
 use itertools::izip;
 use crate::error::BMLSError;
 use crate::error;
+use crate::WeightDecay;
 
 /// # Momentum Optimizer
 /// - G: Gradient w.r.t. W.
@@ -9,6 +9,7 @@ use crate::error;
 /// - W: Weight Tensor
 /// - LR: Learning Rate
 /// - Beta: Hyperparameter
+/// - Decay: Weight Decay
 #[inline]
 pub fn momentum(
     g: &[f32],
@@ -16,6 +17,7 @@ pub fn momentum(
     w: &mut [f32],
     lr: f32,
     beta: f32,
+    decay: WeightDecay,
 ) -> Result<(), BMLSError> {
     if g.len() != v.len() {
         return error::length_mismatch("G", g.len(), "V", v.len())
@@ -25,12 +27,52 @@ pub fn momentum(
         return error::length_mismatch("V", v.len(), "W", w.len())
     }
 
+    let (l2, wd) = decay.coefficients();
+
     for (g, v, w) in izip!(g, v, w) {
-        // v = Bv + (1 - B)g
-        *v = *v * beta + (1. - beta) * *g;
-        // w -= lr * v
-        *w -= lr * *v;
+        momentum_update(*g, v, w, lr, beta, l2, wd);
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Momentum update of a single element of V and W.
+/// - L2, WD: coefficients of the Weight Decay
+#[inline(always)]
+pub(crate) fn momentum_update(g: f32, v: &mut f32, w: &mut f32, lr: f32, beta: f32, l2: f32, wd: f32) {
+    let g = g + l2 * *w;
+    // v = Bv + (1 - B)g
+    *v = *v * beta + (1. - beta) * g;
+    // w -= lr * v
+    *w -= lr * (*v + wd * *w);
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_momentum_decay() {
+        let g = [1.0, -1.0, 0.0];
+        let mut v1 = [0.0; 3];
+        let mut v2 = [0.0; 3];
+        let mut v3 = [0.0; 3];
+        let mut w1 = [2.0, 2.0, 2.0];
+        let mut w2 = w1;
+        let mut w3 = w1;
+
+        momentum(&g, &mut v1, &mut w1, 0.1, 0.9, WeightDecay::None).unwrap();
+        momentum(&g, &mut v2, &mut w2, 0.1, 0.9, WeightDecay::L2(0.5)).unwrap();
+        momentum(&g, &mut v3, &mut w3, 0.1, 0.9, WeightDecay::Decoupled(0.5)).unwrap();
+
+        // L2 passes through V, decoupled decay does not
+        for (v, w, e) in izip!(v2, w2, [(0.2, 1.98), (0.0, 2.0), (0.1, 1.99)]) {
+            assert!((v - e.0).abs() < 1e-6 && (w - e.1).abs() < 1e-6);
+        }
+        for (v1, v3, w1, w3, e) in izip!(v1, v3, w1, w3, [(1.99, 1.89), (2.01, 1.91), (2.0, 1.9)]) {
+            assert_eq!(v1, v3);
+            assert!((w1 - e.0).abs() < 1e-6 && (w3 - e.1).abs() < 1e-6);
+        }
+    }
+}
@@ -1,12 +1,16 @@
-
 use crate::error::BMLSError;
 use crate::error;
+use crate::reduction::{Reduction, reduce_rows};
 
 /// ## Inputs
 /// - T: Truth Label (N x C)
 /// - P: Prediction (N x C)
-/// - E: Error (N x 1) vector
-/// - G: Gradient (N x C)
+/// - E: Error, (N x 1) vector for `Reduction::None`, otherwise (1)
+/// - G: Gradient of E w.r.t. P (N x C)
+/// - Sample_Weights: Optional weight for each row (N)
+/// - Reduction: How the rows are combined into E
+/// 
+/// The error of each row is the mean over C.
 #[inline]
 pub fn mse(
     t: &[f32],
@@ -14,6 +18,8 @@ pub fn mse(
     e: &mut [f32],
     g: &mut [f32],
     dim: [usize; 2],
+    sample_weights: Option<&[f32]>,
+    reduction: Reduction,
 ) -> Result<(), BMLSError> {
     let len = dim[0] * dim[1];
     if t.len() != len {
@@ -24,30 +30,23 @@ pub fn mse(
         return error::length_mismatch("T", t.len(), "P", p.len())
     }
 
-    if e.len() != dim[0] {
-        return error::length_mismatch("E", e.len(), "Dim[0]", dim[0])
-    }
-
     if g.len() != p.len() {
         return error::length_mismatch("G", g.len(), "P", p.len())
     }
 
-    let rows = dim[0];
     let cols = dim[1];
     
-    for i in 0..rows {
+    reduce_rows(e, g, dim, sample_weights, reduction, |i, g| {
         let mut sum = 0.0;
 
-        for j in 0..cols {
+        for (j, g) in g.iter_mut().enumerate() {
             let index = i * cols + j;
             let loss = p[index] - t[index];
 
             sum += loss*loss;
-            g[index] = 2.0 * loss;
+            *g = 2.0 * loss / cols as f32;
         }
 
-        e[i] = sum / cols as f32
-    }
-
-    Ok(())
+        Some(sum / cols as f32)
+    })
 }
@@ -0,0 +1,109 @@
+use itertools::izip;
+use crate::error::BMLSError;
+use crate::error;
+use crate::WeightDecay;
+
+/// # NAdam Optimizer
+/// - G: Gradient w.r.t. W
+/// - V: Exponentialy weighted average of past gradients
+/// - S: Exponentialy weighted average of past squares of gradients
+/// - W: Weight Tensor
+/// - Lr: Learning Rate
+/// - Beta1: Hyperparameter,
+/// - Beta2: Hyperparameter,
+/// - Epsilon: Added to the denominator for numerical stability
+/// - Decay: Weight Decay
+/// - T: Timestep of this update, starting at 1.
+/// 
+/// Adam with a Nesterov look-ahead on V. 
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn nadam(
+    g: &[f32],
+    v: &mut [f32],
+    s: &mut [f32],
+    w: &mut [f32],
+    lr: f32,
+    beta1: f32,
+    beta2: f32,
+    epsilon: f32,
+    decay: WeightDecay,
+    t: usize,
+) -> Result<(), BMLSError> {
+    if g.len() != v.len() {
+        return error::length_mismatch("G", g.len(), "V", v.len())
+    }
+
+    if v.len() != s.len() {
+        return error::length_mismatch("V", v.len(), "S", s.len())
+    }
+
+    if s.len() != w.len() {
+        return error::length_mismatch("S", s.len(), "W", w.len())
+    }
+
+    if t == 0 {
+        return error::invalid_timestep(t)
+    }
+
+    // bias corrections for this step and the next
+    let c1 = 1. - beta1.powf(t as f32);
+    let c1_next = 1. - beta1.powf(t as f32 + 1.);
+    let c2 = 1. - beta2.powf(t as f32);
+
+    let (l2, wd) = decay.coefficients();
+
+    for (g, v, s, w) in izip!(g, v, s, w) {
+        let g = *g + l2 * *w;
+
+        // update V and S
+        *v = (*v * beta1) + (1. - beta1) * g;
+        *s = (*s * beta2) + (1. - beta2) * (g * g);
+
+        // look ahead: mix the next corrected V with the current gradient
+        let vc = beta1 * *v / c1_next + (1. - beta1) * g / c1;
+        let sc = *s / c2;
+
+        *w -= lr * (vc / (f32::sqrt(sc) + epsilon) + wd * *w);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_nadam() {
+        // minimize w^2
+        let mut v = [0.0; 2];
+        let mut s = [0.0; 2];
+        let mut w = [1.0, -1.0];
+
+        for t in 1..=2000 {
+            let g = [2. * w[0], 2. * w[1]];
+            nadam(&g, &mut v, &mut s, &mut w, 0.01, 0.9, 0.999, 1e-8, WeightDecay::None, t).unwrap();
+        }
+
+        assert!(w[0].abs() < 1e-2 && w[1].abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_nadam_large_timestep() {
+        let g = [0.5, -3.0];
+        let step = |t: usize| {
+            let mut v = [0.0; 2];
+            let mut s = [0.0; 2];
+            let mut w = [1.0; 2];
+            nadam(&g, &mut v, &mut s, &mut w, 0.1, 0.9, 0.999, 1e-8, WeightDecay::None, t).unwrap();
+            w
+        };
+
+        // past i32::MAX the timestep must not wrap, the corrections stay 1
+        let w = step(i32::MAX as usize + 2);
+        assert!(w.iter().all(|w| w.is_finite()));
+        assert_eq!(w, step(1_000_000));
+    }
+}
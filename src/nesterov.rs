@@ -0,0 +1,63 @@
+use itertools::izip;
+use crate::error::BMLSError;
+use crate::error;
+use crate::WeightDecay;
+
+/// # Nesterov Accelerated Gradient
+/// - G: Gradient w.r.t. W.
+/// - V: Exponentially weighted average of Past gradients
+/// - W: Weight Tensor
+/// - LR: Learning Rate
+/// - Beta: Hyperparameter
+/// - Decay: Weight Decay
+/// 
+/// Same state as `momentum`, but the step looks ahead along V.
+#[inline]
+pub fn nesterov(
+    g: &[f32],
+    v: &mut [f32],
+    w: &mut [f32],
+    lr: f32,
+    beta: f32,
+    decay: WeightDecay,
+) -> Result<(), BMLSError> {
+    if g.len() != v.len() {
+        return error::length_mismatch("G", g.len(), "V", v.len())
+    }
+
+    if v.len() != w.len() {
+        return error::length_mismatch("V", v.len(), "W", w.len())
+    }
+
+    let (l2, wd) = decay.coefficients();
+
+    for (g, v, w) in izip!(g, v, w) {
+        let g = *g + l2 * *w;
+        // v = Bv + (1 - B)g
+        *v = *v * beta + (1. - beta) * g;
+        // w -= lr * (Bv + (1 - B)g)
+        *w -= lr * (*v * beta + (1. - beta) * g + wd * *w);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_nesterov() {
+        // minimize w^2
+        let mut v = [0.0; 2];
+        let mut w = [3.0, -2.0];
+
+        for _ in 0..500 {
+            let g = [2. * w[0], 2. * w[1]];
+            nesterov(&g, &mut v, &mut w, 0.1, 0.9, WeightDecay::None).unwrap();
+        }
+
+        assert!(w[0].abs() < 1e-3 && w[1].abs() < 1e-3);
+    }
+}
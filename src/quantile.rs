@@ -0,0 +1,94 @@
+use itertools::izip;
+use crate::error::BMLSError;
+use crate::error;
+use crate::reduction::{Reduction, reduce_rows};
+
+/// # Quantile (Pinball) Loss
+/// - T: Truth Label (N x C)
+/// - P: Prediction (N x C)
+/// - E: Error, (N x 1) vector for `Reduction::None`, otherwise (1)
+/// - G: Gradient of E w.r.t. P (N x C)
+/// - Dim: [N, C]
+/// - Q: Quantile to predict, between 0 and 1. 0.5 gives half the `mae`.
+/// - Sample_Weights: Optional weight for each row (N)
+/// - Reduction: How the rows are combined into E
+/// 
+/// The error of each row is the mean over C.
+/// 
+/// With d = t - p: max(q * d, (q - 1) * d)
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn quantile(
+    t: &[f32],
+    p: &[f32],
+    e: &mut [f32],
+    g: &mut [f32],
+    dim: [usize; 2],
+    q: f32,
+    sample_weights: Option<&[f32]>,
+    reduction: Reduction,
+) -> Result<(), BMLSError> {
+    let len = dim[0] * dim[1];
+    if t.len() != len {
+        return error::length_mismatch("T", t.len(), "Dim", len)
+    }
+
+    if t.len() != p.len() {
+        return error::length_mismatch("T", t.len(), "P", p.len())
+    }
+
+    if g.len() != p.len() {
+        return error::length_mismatch("G", g.len(), "P", p.len())
+    }
+
+    if !(0.0..=1.0).contains(&q) {
+        return error::invalid_quantile(q)
+    }
+
+    let cols = dim[1];
+    let scale = 1. / cols as f32;
+
+    reduce_rows(e, g, dim, sample_weights, reduction, |i, g| {
+        let t = &t[i * cols..(i + 1) * cols];
+        let p = &p[i * cols..(i + 1) * cols];
+
+        let mut sum = 0.0;
+
+        for (t, p, g) in izip!(t, p, g) {
+            let d = t - p;
+
+            if d > 0.0 {
+                // under-prediction
+                sum += q * d;
+                *g = -q * scale;
+            } else {
+                // over-prediction
+                sum += (q - 1.) * d;
+                *g = (1. - q) * scale;
+            }
+        }
+
+        Some(sum * scale)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_quantile() {
+        let t = [1.0, 1.0];
+        let p = [0.0, 3.0];
+        let mut e = [0.0];
+        let mut g = [0.0; 2];
+
+        quantile(&t, &p, &mut e, &mut g, [1, 2], 0.9, None, Reduction::None).unwrap();
+
+        assert!((e[0] - (0.9 + 0.2) / 2.).abs() < 1e-6);
+        assert!((g[0] + 0.45).abs() < 1e-6);
+        assert!((g[1] - 0.05).abs() < 1e-6);
+        assert!(quantile(&t, &p, &mut e, &mut g, [1, 2], 1.5, None, Reduction::None).is_err());
+    }
+}
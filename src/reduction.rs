@@ -0,0 +1,198 @@
+use crate::error::BMLSError;
+use crate::error;
+
+/// # Reduction
+/// How a loss combines the errors of each sample (row).
+///
+/// The gradient is always the gradient of what is written to E,
+/// so it can be passed straight back through the network.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Reduction {
+    /// One error per sample, E has length N.
+    None,
+    /// Weighted mean over the samples, E has length 1. \
+    /// sum(w * e) / sum(w)
+    #[default]
+    Mean,
+    /// Weighted sum over the samples, E has length 1. \
+    /// sum(w * e)
+    Sum,
+}
+
+impl Reduction {
+    /// Length of the error vector E for N samples.
+    #[inline]
+    pub fn output_len(self, rows: usize) -> usize {
+        match self {
+            Reduction::None => rows,
+            Reduction::Mean | Reduction::Sum => 1,
+        }
+    }
+}
+
+/// Computes a loss row by row and applies the Sample_Weights and Reduction.
+///
+/// Row is called with each row index and that row's slice of G. It writes
+/// the gradient of the row's error into G and returns the error, or `None`
+/// if the row should be ignored. Ignored rows get a zero gradient and
+/// are not counted by `Reduction::Mean`.
+#[inline]
+pub(crate) fn reduce_rows<F>(
+    e: &mut [f32],
+    g: &mut [f32],
+    dim: [usize; 2],
+    sample_weights: Option<&[f32]>,
+    reduction: Reduction,
+    mut row: F,
+) -> Result<(), BMLSError>
+where
+    F: FnMut(usize, &mut [f32]) -> Option<f32>,
+{
+    reduce_rows_multi(e, &mut [g], dim[0], sample_weights, reduction, |i, g| row(i, g[0]))
+}
+
+/// `reduce_rows` for losses with a gradient for each of several inputs.
+///
+/// Each gradient in Grads is split into N equal rows, and Row is
+/// called with row i of every gradient, in the same order.
+#[inline]
+pub(crate) fn reduce_rows_multi<F>(
+    e: &mut [f32],
+    grads: &mut [&mut [f32]],
+    rows: usize,
+    sample_weights: Option<&[f32]>,
+    reduction: Reduction,
+    mut row: F,
+) -> Result<(), BMLSError>
+where
+    F: FnMut(usize, &mut [&mut [f32]]) -> Option<f32>,
+{
+    let len = reduction.output_len(rows);
+    if e.len() != len {
+        return error::length_mismatch("E", e.len(), "Reduction", len)
+    }
+
+    if let Some(w) = sample_weights {
+        if w.len() != rows {
+            return error::length_mismatch("Sample_Weights", w.len(), "Dim[0]", rows)
+        }
+    }
+
+    let mut chunks = grads.iter_mut()
+        .map(|g| {
+            let size = (g.len() / rows.max(1)).max(1);
+            g.chunks_mut(size)
+        })
+        .collect::<Vec<_>>();
+
+    let mut row_grads = Vec::with_capacity(chunks.len());
+    let mut sum = 0.0;
+    let mut total = 0.0;
+
+    for i in 0..rows {
+        row_grads.clear();
+        row_grads.extend(chunks.iter_mut().map(|c| c.next().unwrap_or_default()));
+
+        let w = sample_weights.map_or(1.0, |w| w[i]);
+
+        let loss = match row(i, &mut row_grads) {
+            Some(loss) => loss,
+            None => {
+                for g in row_grads.iter_mut() {
+                    g.fill(0.0);
+                }
+                if reduction == Reduction::None {
+                    e[i] = 0.0;
+                }
+                continue;
+            }
+        };
+
+        for g in row_grads.iter_mut().flat_map(|g| g.iter_mut()) {
+            *g *= w;
+        }
+
+        if reduction == Reduction::None {
+            e[i] = w * loss;
+        }
+
+        sum += w * loss;
+        total += w;
+    }
+
+    match reduction {
+        Reduction::None => {}
+        Reduction::Sum => e[0] = sum,
+        Reduction::Mean => {
+            // nothing counted, so there is nothing to average
+            let scale = if total == 0.0 { 0.0 } else { 1. / total };
+            e[0] = sum * scale;
+            for g in grads.iter_mut().flat_map(|g| g.iter_mut()) {
+                *g *= scale;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::mse::mse;
+
+    #[test]
+    fn test_reduction() {
+        let dim = [3, 2];
+        let t = [0.0; 6];
+        let p = [1.0, 1.0, 2.0, 0.0, -1.0, 3.0];
+        let w = [1.0, 0.5, 0.0];
+
+        let mut rows = [0.0; 3];
+        let mut g = [0.0; 6];
+        mse(&t, &p, &mut rows, &mut g, dim, Some(&w), Reduction::None).unwrap();
+        assert_eq!(rows, [1.0, 1.0, 0.0]);
+        assert_eq!(g[4..], [0.0, 0.0]);
+
+        let mut e = [0.0];
+        mse(&t, &p, &mut e, &mut g, dim, Some(&w), Reduction::Sum).unwrap();
+        assert_eq!(e[0], 2.0);
+
+        mse(&t, &p, &mut e, &mut g, dim, Some(&w), Reduction::Mean).unwrap();
+        assert_eq!(e[0], 2.0 / 1.5);
+
+        assert!(mse(&t, &p, &mut rows, &mut g, dim, None, Reduction::Mean).is_err());
+        assert!(mse(&t, &p, &mut e, &mut g, dim, Some(&w[..2]), Reduction::Mean).is_err());
+    }
+
+    #[test]
+    fn test_reduction_gradient() {
+        let dim = [3, 2];
+        let t = [0.5, -1.0, 2.0, 0.0, 1.0, 1.0];
+        let p = [1.0, 1.0, 2.0, 0.5, -1.0, 3.0];
+        let w = [1.0, 0.5, 2.0];
+
+        for reduction in [Reduction::Mean, Reduction::Sum] {
+            let mut e = [0.0];
+            let mut g = [0.0; 6];
+            mse(&t, &p, &mut e, &mut g, dim, Some(&w), reduction).unwrap();
+
+            let f = |p: &[f32]| {
+                let mut e = [0.0];
+                let mut g = [0.0; 6];
+                mse(&t, p, &mut e, &mut g, dim, Some(&w), reduction).unwrap();
+                e[0]
+            };
+
+            for i in 0..6 {
+                let mut pp = p;
+                let mut pm = p;
+                pp[i] += 1e-2;
+                pm[i] -= 1e-2;
+                let fd = (f(&pp) - f(&pm)) / 2e-2;
+                assert!((fd - g[i]).abs() < 1e-3);
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use rayon::prelude::*;
+use crate::error::BMLSError;
+use crate::error;
+use crate::layer_norm::{check_dims, check_params};
+
+/// # RMS Normalization
+/// - X: Input
+/// - Y: Output
+/// - Gamma: Scale for each element of the normalized dimensions
+/// - Inv_Rms: 1 / sqrt(mean(x^2) + epsilon) of each group, saved for the backward pass
+/// - X_dim: Dimensions of X.
+/// - Axis: First normalized dimension. Every dimension from Axis
+///   onward is normalized together, like `layer_norm`.
+/// - Epsilon: Added to the mean square for numerical stability
+///
+/// y = x * inv_rms * gamma
+/// 
+/// Like `layer_norm`, without centering or a shift.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn rms_norm(
+    x: &[f32],
+    y: &mut [f32],
+    gamma: &[f32],
+    inv_rms: &mut [f32],
+    x_dim: [usize; 4],
+    axis: usize,
+    epsilon: f32,
+) -> Result<(), BMLSError> {
+    let (groups, size) = check_dims(x, x_dim, axis)?;
+
+    if y.len() != x.len() {
+        return error::length_mismatch("Y", y.len(), "X", x.len());
+    }
+
+    check_params(&[("Gamma", gamma.len())], "Size", size)?;
+    check_params(&[("Inv_Rms", inv_rms.len())], "Groups", groups)?;
+
+    let size = size.max(1);
+
+    y.par_chunks_mut(size)
+        .zip(x.par_chunks(size))
+        .zip(inv_rms.par_iter_mut())
+        .for_each(|((y, x), inv_rms)| {
+            let ms = x.iter().map(|x| x * x).sum::<f32>() / size as f32;
+            let inv = 1. / f32::sqrt(ms + epsilon);
+
+            for ((x, y), gamma) in x.iter().zip(y).zip(gamma) {
+                *y = x * inv * gamma;
+            }
+
+            *inv_rms = inv;
+        });
+
+    Ok(())
+}
+
+/// # RMS Normalization w.r.t. X
+/// - X: Input
+/// - GY: Gradient w.r.t. Y
+/// - GX: Gradient w.r.t. X
+/// - Gamma: Scale for each element of the normalized dimensions
+/// - Inv_Rms: Inv_Rms saved by `rms_norm`
+/// - X_dim: Dimensions of X.
+/// - Axis: First normalized dimension.
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn rms_norm_wrt_x(
+    x: &[f32],
+    gy: &[f32],
+    gx: &mut [f32],
+    gamma: &[f32],
+    inv_rms: &[f32],
+    x_dim: [usize; 4],
+    axis: usize,
+) -> Result<(), BMLSError> {
+    let (groups, size) = check_dims(x, x_dim, axis)?;
+
+    if gy.len() != x.len() {
+        return error::length_mismatch("GY", gy.len(), "X", x.len());
+    }
+
+    if gx.len() != x.len() {
+        return error::length_mismatch("GX", gx.len(), "X", x.len());
+    }
+
+    check_params(&[("Gamma", gamma.len())], "Size", size)?;
+    check_params(&[("Inv_Rms", inv_rms.len())], "Groups", groups)?;
+
+    let size = size.max(1);
+
+    gx.par_chunks_mut(size)
+        .zip(x.par_chunks(size).zip(gy.par_chunks(size)))
+        .zip(inv_rms.par_iter())
+        .for_each(|((gx, (x, gy)), inv)| {
+            // mean(gy * gamma * x_hat)
+            let dot = x.iter().zip(gy).zip(gamma)
+                .map(|((x, gy), gamma)| gy * gamma * x * inv)
+                .sum::<f32>() / size as f32;
+
+            for (((x, gy), gamma), gx) in x.iter().zip(gy).zip(gamma).zip(gx) {
+                *gx += inv * (gy * gamma - x * inv * dot);
+            }
+        });
+
+    Ok(())
+}
+
+/// # RMS Normalization w.r.t. Gamma
+/// - X: Input
+/// - GY: Gradient w.r.t. Y
+/// - G_Gamma: Gradient w.r.t. Gamma
+/// - Inv_Rms: Inv_Rms saved by `rms_norm`
+/// - X_dim: Dimensions of X.
+/// - Axis: First normalized dimension.
+#[inline]
+pub fn rms_norm_wrt_gamma(
+    x: &[f32],
+    gy: &[f32],
+    g_gamma: &mut [f32],
+    inv_rms: &[f32],
+    x_dim: [usize; 4],
+    axis: usize,
+) -> Result<(), BMLSError> {
+    let (groups, size) = check_dims(x, x_dim, axis)?;
+
+    if gy.len() != x.len() {
+        return error::length_mismatch("GY", gy.len(), "X", x.len());
+    }
+
+    check_params(&[("G_Gamma", g_gamma.len())], "Size", size)?;
+    check_params(&[("Inv_Rms", inv_rms.len())], "Groups", groups)?;
+
+    g_gamma.par_iter_mut().enumerate().for_each(|(j, gg)| {
+        for (i, inv) in inv_rms.iter().enumerate() {
+            let index = i * size + j;
+            *gg += gy[index] * x[index] * inv;
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_rms_norm_gradient() {
+        let dim = [3, 4, 1, 1];
+        let x: Vec<f32> = (0..12).map(|i| f32::sin(i as f32 * 1.3) * 3.0).collect();
+        let gamma = [1.0, 0.5, -2.0, 1.5];
+        let r: Vec<f32> = (0..12).map(|i| f32::cos(i as f32)).collect();
+
+        let f = |x: &[f32], gamma: &[f32]| {
+            let mut y = vec![0.0; 12];
+            rms_norm(x, &mut y, gamma, &mut [0.0; 3], dim, 1, 1e-6).unwrap();
+            y.iter().zip(&r).map(|(y, r)| y * r).sum::<f32>()
+        };
+
+        let mut y = vec![0.0; 12];
+        let mut inv = [0.0; 3];
+        rms_norm(&x, &mut y, &gamma, &mut inv, dim, 1, 1e-6).unwrap();
+
+        // unit root mean square with gamma = 1
+        let rms = (y[0] * y[0] + 4. * y[1] * y[1] + y[2] * y[2] / 4. + y[3] * y[3] / 2.25) / 4.;
+        assert!((rms - 1.0).abs() < 1e-4);
+
+        let mut gx = vec![0.0; 12];
+        let mut gg = [0.0; 4];
+        rms_norm_wrt_x(&x, &r, &mut gx, &gamma, &inv, dim, 1).unwrap();
+        rms_norm_wrt_gamma(&x, &r, &mut gg, &inv, dim, 1).unwrap();
+
+        for i in 0..12 {
+            let mut xp = x.clone();
+            let mut xm = x.clone();
+            xp[i] += 1e-2;
+            xm[i] -= 1e-2;
+            assert!(((f(&xp, &gamma) - f(&xm, &gamma)) / 2e-2 - gx[i]).abs() < 1e-2);
+        }
+
+        for j in 0..4 {
+            let (mut gp, mut gm) = (gamma, gamma);
+            gp[j] += 1e-2;
+            gm[j] -= 1e-2;
+            assert!(((f(&x, &gp) - f(&x, &gm)) / 2e-2 - gg[j]).abs() < 1e-2);
+        }
+    }
+}
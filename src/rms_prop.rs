@@ -1,7 +1,7 @@
-
 use itertools::izip;
 use crate::error::BMLSError;
 use crate::error;
+use crate::WeightDecay;
 
 /// # RMS_Prop Optimizer
 /// - G: Gradient w.r.t. W.
@@ -9,6 +9,7 @@ use crate::error;
 /// - W: Weight Tensor
 /// - LR: Learning Rate
 /// - Beta: Hyperparameter
+/// - Decay: Weight Decay
 #[inline]
 pub fn rms_prop(
     g: &[f32],
@@ -16,6 +17,7 @@ pub fn rms_prop(
     w: &mut [f32],
     lr: f32,
     beta: f32,
+    decay: WeightDecay,
 ) -> Result<(), BMLSError> {
     if g.len() != s.len() {
         return error::length_mismatch("G", g.len(), "V", s.len())
@@ -25,12 +27,60 @@ pub fn rms_prop(
         return error::length_mismatch("S", s.len(), "W", w.len())
     }
 
+    let (l2, wd) = decay.coefficients();
+
     for (g, s, w) in izip!(g, s, w) {
-        // v = Bv + (1 - B)g
-        *s = *s * beta + (1. - beta) * f32::powi(*g, 2);
-        // w -= lr * v
-        *w -= lr * (*w / (f32::sqrt(*s) + 0.00000000001));
+        rms_prop_update(*g, s, w, lr, beta, l2, wd);
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// RMS_Prop update of a single element of S and W.
+/// - L2, WD: coefficients of the Weight Decay
+#[inline(always)]
+pub(crate) fn rms_prop_update(g: f32, s: &mut f32, w: &mut f32, lr: f32, beta: f32, l2: f32, wd: f32) {
+    let g = g + l2 * *w;
+    // s = Bs + (1 - B)g^2
+    *s = *s * beta + (1. - beta) * f32::powi(g, 2);
+    // w -= lr * g / sqrt(s), the step follows the gradient, not W
+    *w -= lr * (g / (f32::sqrt(*s) + 0.00000000001) + wd * *w);
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_rms_prop_decay() {
+        let g = [1.0, -1.0, 0.0];
+        let mut s1 = [0.0; 3];
+        let mut s2 = [0.0; 3];
+        let mut s3 = [0.0; 3];
+        let mut w1 = [2.0, 2.0, 2.0];
+        let mut w2 = w1;
+        let mut w3 = w1;
+
+        rms_prop(&g, &mut s1, &mut w1, 0.1, 0.9, WeightDecay::None).unwrap();
+        rms_prop(&g, &mut s2, &mut w2, 0.1, 0.9, WeightDecay::L2(0.5)).unwrap();
+        rms_prop(&g, &mut s3, &mut w3, 0.1, 0.9, WeightDecay::Decoupled(0.5)).unwrap();
+
+        // the step is g / sqrt(s), so a zero gradient leaves W alone
+        let step = 0.1 / f32::sqrt(0.1);
+        // L2 adds 0.5 * w = 1 to the gradient before S is updated
+        let l2 = 0.1 * 2. / f32::sqrt(0.4);
+        for (w, e) in izip!(w1, [2. - step, 2. + step, 2.0]) {
+            assert!((w - e).abs() < 1e-5);
+        }
+        for (w, e) in izip!(w2, [2. - l2, 2.0, 2. - step]) {
+            assert!((w - e).abs() < 1e-5);
+        }
+        for (w, e) in izip!(w3, [1.9 - step, 1.9 + step, 1.9]) {
+            assert!((w - e).abs() < 1e-5);
+        }
+        for (s, e) in izip!(s2, [0.4, 0.0, 0.1]) {
+            assert!((s - e).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use std::f32::consts::PI;
+
+/// # Learning Rate Scheduler
+/// Produces the learning rate to pass to an optimizer
+/// (`sgd`, `momentum`, `rms_prop`, `adam`) at each step.
+///
+/// Steps start at 0.
+pub trait Scheduler {
+    fn lr(&self, step: usize) -> f32;
+}
+
+/// # Step Decay
+/// - Lr: Initial Learning Rate
+/// - Gamma: Factor to multiply the Lr by every Step_Size steps
+/// - Step_Size: Number of steps between decays
+///
+/// lr * gamma^(step / step_size)
+#[derive(Copy, Clone, Debug)]
+pub struct StepDecay {
+    pub lr: f32,
+    pub gamma: f32,
+    pub step_size: usize,
+}
+
+impl StepDecay {
+    pub fn new(lr: f32, gamma: f32, step_size: usize) -> Self {
+        Self { lr, gamma, step_size }
+    }
+}
+
+impl Scheduler for StepDecay {
+    #[inline]
+    fn lr(&self, step: usize) -> f32 {
+        self.lr * self.gamma.powf((step / self.step_size.max(1)) as f32)
+    }
+}
+
+/// # Exponential Decay
+/// - Lr: Initial Learning Rate
+/// - Gamma: Factor to multiply the Lr by every step
+///
+/// lr * gamma^step
+#[derive(Copy, Clone, Debug)]
+pub struct ExponentialDecay {
+    pub lr: f32,
+    pub gamma: f32,
+}
+
+impl ExponentialDecay {
+    pub fn new(lr: f32, gamma: f32) -> Self {
+        Self { lr, gamma }
+    }
+}
+
+impl Scheduler for ExponentialDecay {
+    #[inline]
+    fn lr(&self, step: usize) -> f32 {
+        self.lr * self.gamma.powf(step as f32)
+    }
+}
+
+/// # Cosine Annealing with Warm Restarts
+/// - Lr_Max: Learning Rate at the start of each cycle
+/// - Lr_Min: Learning Rate at the end of each cycle
+/// - T0: Length of the first cycle in steps
+/// - T_Mult: Factor to grow the cycle length by after each restart
+///
+/// Within a cycle of length Ti, at step Tcur of that cycle: \
+/// lr_min + (lr_max - lr_min) * (1 + cos(pi * tcur / ti)) / 2
+#[derive(Copy, Clone, Debug)]
+pub struct CosineAnnealing {
+    pub lr_max: f32,
+    pub lr_min: f32,
+    pub t0: usize,
+    pub t_mult: usize,
+}
+
+impl CosineAnnealing {
+    pub fn new(lr_max: f32, lr_min: f32, t0: usize, t_mult: usize) -> Self {
+        Self { lr_max, lr_min, t0, t_mult }
+    }
+}
+
+impl Scheduler for CosineAnnealing {
+    #[inline]
+    fn lr(&self, step: usize) -> f32 {
+        let mut ti = self.t0.max(1);
+        let mut tcur = step;
+
+        // find the cycle the step is in
+        while tcur >= ti {
+            tcur -= ti;
+            ti *= self.t_mult.max(1);
+        }
+
+        let cos = f32::cos(PI * tcur as f32 / ti as f32);
+        self.lr_min + (self.lr_max - self.lr_min) * (1. + cos) / 2.
+    }
+}
+
+/// # Linear Warmup
+/// - Warmup: Number of warmup steps
+/// - Inner: Schedule to follow after the warmup
+///
+/// Ramps linearly up to the Inner schedule's initial learning rate
+/// over Warmup steps, then follows Inner, starting from its step 0.
+#[derive(Copy, Clone, Debug)]
+pub struct LinearWarmup<S: Scheduler> {
+    pub warmup: usize,
+    pub inner: S,
+}
+
+impl<S: Scheduler> LinearWarmup<S> {
+    pub fn new(warmup: usize, inner: S) -> Self {
+        Self { warmup, inner }
+    }
+}
+
+impl<S: Scheduler> Scheduler for LinearWarmup<S> {
+    #[inline]
+    fn lr(&self, step: usize) -> f32 {
+        if step < self.warmup {
+            self.inner.lr(0) * (step + 1) as f32 / (self.warmup + 1) as f32
+        } else {
+            self.inner.lr(step - self.warmup)
+        }
+    }
+}
+
+/// # One Cycle
+/// - Max_Lr: Peak Learning Rate
+/// - Total_Steps: Length of the cycle
+/// - Pct_Start: Fraction of the cycle spent increasing the Lr
+/// - Div_Factor: Initial Lr is Max_Lr / Div_Factor
+/// - Final_Div_Factor: Final Lr is Initial Lr / Final_Div_Factor
+///
+/// Cosine-anneals from the initial Lr up to Max_Lr, then
+/// back down to the final Lr. Steps past Total_Steps use the final Lr.
+#[derive(Copy, Clone, Debug)]
+pub struct OneCycle {
+    pub max_lr: f32,
+    pub total_steps: usize,
+    pub pct_start: f32,
+    pub div_factor: f32,
+    pub final_div_factor: f32,
+}
+
+impl OneCycle {
+    pub fn new(
+        max_lr: f32,
+        total_steps: usize,
+        pct_start: f32,
+        div_factor: f32,
+        final_div_factor: f32
+    ) -> Self {
+        Self { max_lr, total_steps, pct_start, div_factor, final_div_factor }
+    }
+}
+
+impl Scheduler for OneCycle {
+    #[inline]
+    fn lr(&self, step: usize) -> f32 {
+        let initial = self.max_lr / self.div_factor;
+        let last = initial / self.final_div_factor;
+
+        // the last step of the warmup and of the cycle
+        let up = ((self.pct_start * self.total_steps as f32) as usize).max(1);
+        let end = self.total_steps.saturating_sub(1).max(up);
+
+        // cosine interpolation between start and stop at pct in 0..1
+        let anneal = |start: f32, stop: f32, pct: f32| {
+            stop + (start - stop) * (1. + f32::cos(PI * pct)) / 2.
+        };
+
+        if step <= up {
+            anneal(initial, self.max_lr, step as f32 / up as f32)
+        } else if step < end {
+            anneal(self.max_lr, last, (step - up) as f32 / (end - up) as f32)
+        } else {
+            last
+        }
+    }
+}
+
+/// # Reduce on Plateau
+/// - Lr: Initial Learning Rate
+/// - Factor: Factor to multiply the Lr by when the metric plateaus
+/// - Patience: Number of steps without improvement before reducing
+/// - Threshold: Minimum decrease in the metric to count as an improvement
+/// - Min_Lr: Lower bound on the Lr
+///
+/// Tracks a metric that should decrease, like validation loss.
+/// Call `step` with the metric once per evaluation.
+#[derive(Copy, Clone, Debug)]
+pub struct ReduceOnPlateau {
+    pub factor: f32,
+    pub patience: usize,
+    pub threshold: f32,
+    pub min_lr: f32,
+    lr: f32,
+    best: f32,
+    bad_steps: usize,
+}
+
+impl ReduceOnPlateau {
+    pub fn new(
+        lr: f32,
+        factor: f32,
+        patience: usize,
+        threshold: f32,
+        min_lr: f32
+    ) -> Self {
+        Self {
+            factor,
+            patience,
+            threshold,
+            min_lr,
+            lr,
+            best: f32::INFINITY,
+            bad_steps: 0
+        }
+    }
+
+    /// Records the metric and returns the learning rate to use next.
+    pub fn step(&mut self, metric: f32) -> f32 {
+        if metric < self.best - self.threshold {
+            self.best = metric;
+            self.bad_steps = 0;
+        } else {
+            self.bad_steps += 1;
+        }
+
+        if self.bad_steps > self.patience {
+            self.lr = f32::max(self.lr * self.factor, self.min_lr);
+            self.bad_steps = 0;
+        }
+
+        self.lr
+    }
+}
+
+impl Scheduler for ReduceOnPlateau {
+    /// The current learning rate. The step is ignored, since
+    /// the Lr only changes when a metric is recorded with `step`.
+    #[inline]
+    fn lr(&self, _: usize) -> f32 {
+        self.lr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn test_step_decay() {
+        let s = StepDecay::new(1.0, 0.5, 10);
+        assert!(close(s.lr(0), 1.0));
+        assert!(close(s.lr(9), 1.0));
+        assert!(close(s.lr(10), 0.5));
+        assert!(close(s.lr(25), 0.25));
+
+        // a step past i32::MAX must not wrap into a huge lr
+        let s = StepDecay::new(1.0, 0.5, 1);
+        assert_eq!(s.lr(i32::MAX as usize + 2), 0.0);
+    }
+
+    #[test]
+    fn test_exponential_decay() {
+        let s = ExponentialDecay::new(2.0, 0.5);
+        assert!(close(s.lr(0), 2.0));
+        assert!(close(s.lr(3), 0.25));
+        assert_eq!(s.lr(i32::MAX as usize + 2), 0.0);
+    }
+
+    #[test]
+    fn test_cosine_annealing() {
+        let s = CosineAnnealing::new(1.0, 0.0, 10, 2);
+        assert!(close(s.lr(0), 1.0));
+        assert!(close(s.lr(5), 0.5));
+        // restart, with a cycle of length 20
+        assert!(close(s.lr(10), 1.0));
+        assert!(close(s.lr(20), 0.5));
+        assert!(close(s.lr(30), 1.0));
+    }
+
+    #[test]
+    fn test_linear_warmup() {
+        let s = LinearWarmup::new(3, ExponentialDecay::new(1.0, 0.5));
+        assert!(close(s.lr(0), 0.25));
+        assert!(close(s.lr(2), 0.75));
+        assert!(close(s.lr(3), 1.0));
+        assert!(close(s.lr(4), 0.5));
+    }
+
+    #[test]
+    fn test_one_cycle() {
+        let s = OneCycle::new(1.0, 101, 0.3, 25.0, 1e4);
+        assert!(close(s.lr(0), 0.04));
+        assert!(close(s.lr(30), 1.0));
+        assert!(s.lr(60) < 1.0);
+        assert!(close(s.lr(100), 0.04 / 1e4));
+        assert!(close(s.lr(500), 0.04 / 1e4));
+    }
+
+    #[test]
+    fn test_reduce_on_plateau() {
+        let mut s = ReduceOnPlateau::new(1.0, 0.1, 1, 0.0, 0.05);
+        assert!(close(s.step(1.0), 1.0));
+        assert!(close(s.step(1.0), 1.0));
+        assert!(close(s.step(1.0), 0.1));
+        assert!(close(s.step(0.5), 0.1));
+        assert!(close(s.step(0.5), 0.1));
+        assert!(close(s.step(0.5), 0.05));
+        assert!(close(s.lr(0), 0.05));
+    }
+}
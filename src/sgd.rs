@@ -1,21 +1,62 @@
-
 use itertools::izip;
 use crate::error::BMLSError;
 use crate::error;
+use crate::WeightDecay;
 
+/// # SGD Optimizer
+/// - G: Gradient w.r.t. W.
+/// - W: Weight Tensor
+/// - LR: Learning Rate
+/// - Decay: Weight Decay
 #[inline]
 pub fn sgd(
     g: &[f32],
     w: &mut [f32],
     lr: f32,
+    decay: WeightDecay,
 ) -> Result<(), BMLSError> {
     if g.len() != w.len() {
         return error::length_mismatch("G", g.len(), "W", w.len())
     }
 
+    let (l2, wd) = decay.coefficients();
+
     for (g, w) in izip!(g, w) {
-        *w -= *g * lr
+        sgd_update(*g, w, lr, l2, wd);
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// SGD update of a single element of W.
+/// - L2, WD: coefficients of the Weight Decay
+#[inline(always)]
+pub(crate) fn sgd_update(g: f32, w: &mut f32, lr: f32, l2: f32, wd: f32) {
+    let g = g + l2 * *w;
+    *w -= lr * (g + wd * *w)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_sgd_decay() {
+        let g = [1.0, -1.0, 0.0];
+        let mut w1 = [2.0, 2.0, 2.0];
+        let mut w2 = w1;
+        let mut w3 = w1;
+
+        sgd(&g, &mut w1, 0.1, WeightDecay::None).unwrap();
+        sgd(&g, &mut w2, 0.1, WeightDecay::L2(0.5)).unwrap();
+        sgd(&g, &mut w3, 0.1, WeightDecay::Decoupled(0.5)).unwrap();
+
+        assert_eq!(w1, [1.9, 2.1, 2.0]);
+        // for plain SGD both forms of decay are the same
+        for (w2, w3, e) in izip!(w2, w3, [1.8, 2.0, 1.9]) {
+            assert!((w2 - e).abs() < 1e-6);
+            assert!((w3 - e).abs() < 1e-6);
+        }
+    }
+}
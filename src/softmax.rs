@@ -1,17 +1,26 @@
-
 use crate::error::BMLSError;
 use crate::error;
 
+/// # Softmax Operator
+/// - X: Input
+/// - Y: Output
+/// - Dim: Dimensions of X and Y
+/// - Axis: Axis to take the softmax along
+/// 
+/// For an (N x C) matrix use Dim [N, C, 1, 1] and Axis 1. 
+/// For per-pixel class probabilities of an NCHW tensor use Axis 1.
 #[inline]
 pub fn softmax(
     x: &[f32],
     y: &mut [f32],
-    dim: [usize; 2],
+    dim: [usize; 4],
+    axis: usize,
 ) -> Result<(), BMLSError> {
-    let rows = dim[0];
-    let cols = dim[1];
+    if axis > 3 {
+        return error::invalid_axis(axis);
+    }
 
-    let len = rows * cols;
+    let len = dim[0]*dim[1]*dim[2]*dim[3];
     if x.len() != len {
         return error::length_mismatch("X", x.len(), "Dim", len);
     }
@@ -20,49 +29,77 @@ pub fn softmax(
         return error::length_mismatch("Y", y.len(), "Dim", len);
     }
 
-    for i in 0..rows {
-        // the max value in the row.
-        // we will take e^x - max for stability.
-        // as input values become larger, they converge to NaN,
-        // however, negative numbers converge to 0. 
-        // by subtracting the max, whatever the max value is will
-        // output as 1, and everything else as 0, resulting in a 
-        // cleaner and more stable output.
-        // let mut max = x[i * cols];
-        // for j in 1..cols {
-        //     if x[i * cols + j] > max {
-        //         max = x[i * cols + j];
-        //     }
-        // }
-
-        // calculate the sum and assign e^x-max to y.
-        let mut sum = 0.0;
-        for j in 0..cols {
-            // y = e^(x+max) (later on we will divide by sum)
-            y[i * cols + j] = f32::exp(x[i * cols + j]);
-            sum += y[i * cols + j];
-        }
+    let (outer, size, inner) = axis_strides(dim, axis);
+
+    for o in 0..outer {
+        for i in 0..inner {
+            // index of the first element along the axis
+            let base = o * size * inner + i;
+
+            // the max value along the axis.
+            // we will take e^x - max for stability.
+            // as input values become larger, e^x overflows to inf,
+            // and inf / inf is NaN. Subtracting the max keeps every
+            // exponent at or below 0, without changing the result.
+            let max = max_along(x, base, size, inner);
 
-        // divide the y value by the sum.
-        for j in 0..cols {
-            y[i * cols + j] /= sum;
+            // calculate the sum and assign e^x-max to y.
+            let mut sum = 0.0;
+            for k in 0..size {
+                let yi = base + k * inner;
+                y[yi] = f32::exp(x[yi] - max);
+                sum += y[yi];
+            }
+
+            // divide the y value by the sum.
+            for k in 0..size {
+                y[base + k * inner] /= sum;
+            }
         }
     }
 
     Ok(())
 }
 
+/// Splits Dim around Axis into (outer, axis, inner) sizes.
+/// Element K along the axis at (o, i) is at o * axis * inner + k * inner + i.
+#[inline]
+pub(crate) fn axis_strides(dim: [usize; 4], axis: usize) -> (usize, usize, usize) {
+    let outer = dim[..axis].iter().product();
+    let inner = dim[axis + 1..].iter().product();
+    (outer, dim[axis], inner)
+}
+
+/// Max of the Size values starting at Base, Inner apart.
+#[inline]
+pub(crate) fn max_along(x: &[f32], base: usize, size: usize, inner: usize) -> f32 {
+    (0..size)
+        .map(|k| x[base + k * inner])
+        .fold(f32::NEG_INFINITY, f32::max)
+}
+
+/// # Softmax w.r.t. X
+/// - Y: Output of the forward op
+/// - GY: Gradient w.r.t. Y
+/// - G1: Gradient w.r.t. X
+/// - Dim: Dimensions of X and Y
+/// - Axis: Axis of the forward op
+/// 
+/// The jacobian of softmax is diag(y) - y * y^T, so along the axis
+/// gx = y * (gy - dot(gy, y)), which is linear in the size of the axis.
 #[inline]
 pub fn softmax_wrt_x(
     y: &[f32],
     gy: &[f32],
     g1: &mut [f32],
-    dim: [usize; 2],
+    dim: [usize; 4],
+    axis: usize,
 ) -> Result<(), BMLSError> {
-    let rows = dim[0];
-    let cols = dim[1];
+    if axis > 3 {
+        return error::invalid_axis(axis);
+    }
 
-    let len = rows * cols;
+    let len = dim[0]*dim[1]*dim[2]*dim[3];
     if y.len() != len {
         return error::length_mismatch("Y", y.len(), "Dim", len);
     }
@@ -75,24 +112,23 @@ pub fn softmax_wrt_x(
         return error::length_mismatch("G1", g1.len(), "Dim", len);
     }
 
-    for i in 0..rows {
-        for j in 0..cols {
-            let v = y[i * cols + j];
+    let (outer, size, inner) = axis_strides(dim, axis);
 
-            let mut sum = 0.0;
-            for k in 0..cols {
-                if i == j {
-                    // when i and j are the same, gx is
-                    // gy * (y * (1. - y));
-                    sum += gy[i * cols + k] * (v * (1. - v));
-                } else {
-                    // when i and j are not the same, gx is
-                    // gy * -other_y*y
-                    sum += gy[i * cols + k] * (-y[i * cols + k] * v);
-                }
+    for o in 0..outer {
+        for i in 0..inner {
+            let base = o * size * inner + i;
+
+            // dot(gy, y) along the axis
+            let mut dot = 0.0;
+            for k in 0..size {
+                let yi = base + k * inner;
+                dot += gy[yi] * y[yi];
             }
 
-            g1[i * cols + j] += sum;
+            for k in 0..size {
+                let yi = base + k * inner;
+                g1[yi] += y[yi] * (gy[yi] - dot);
+            }
         }
     }
 
@@ -110,7 +146,7 @@ mod tests {
         let mut output_data: [f32; 6] = [0.0; 6];
         let dim = [2, 3]; // Example dimensions for a 2x3 matrix
     
-        softmax(&input_data, &mut output_data, dim).unwrap();
+        softmax(&input_data, &mut output_data, [2, 3, 1, 1], 1).unwrap();
     
         // Print the result
         for i in 0..dim[0] {
@@ -135,7 +171,8 @@ mod tests {
                 &softmax_output,
                 &gradient_b,
                 &mut gradient_a,
-                dim,
+                [2, 3, 1, 1],
+                1,
             ).unwrap();
     
         // Print the result
@@ -153,4 +190,62 @@ mod tests {
 
         //panic!("")
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_softmax_stable() {
+        let x = [1000.0, 1001.0, -1000.0, 89.0, 90.0, 0.0];
+        let mut y = [0.0; 6];
+
+        softmax(&x, &mut y, [2, 3, 1, 1], 1).unwrap();
+
+        let e = 1. / (1. + f32::exp(1.));
+        for (y, e) in y.iter().zip([e, 1. - e, 0.0, e, 1. - e, 0.0]) {
+            assert!((y - e).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_softmax_axis() {
+        // [1, 3, 2, 2], softmax over the channels of each pixel
+        let x: Vec<f32> = (0..12).map(|i| (i * 7 % 5) as f32).collect();
+        let mut y = vec![0.0; 12];
+
+        softmax(&x, &mut y, [1, 3, 2, 2], 1).unwrap();
+
+        for p in 0..4 {
+            let sum: f32 = (0..3).map(|c| y[c * 4 + p]).sum();
+            assert!((sum - 1.0).abs() < 1e-6);
+        }
+
+        assert!(softmax(&x, &mut y, [1, 3, 2, 2], 4).is_err());
+    }
+
+    #[test]
+    fn test_softmax_wrt_x_finite_difference() {
+        // [2, 3, 1, 2], along the channels
+        let dim = [2, 3, 1, 2];
+        let x: Vec<f32> = (0..12).map(|i| f32::sin(i as f32 * 1.7) * 2.0).collect();
+        let gy: Vec<f32> = (0..12).map(|i| f32::cos(i as f32 * 0.9)).collect();
+
+        let mut y = vec![0.0; 12];
+        let mut gx = vec![0.0; 12];
+        softmax(&x, &mut y, dim, 1).unwrap();
+        softmax_wrt_x(&y, &gy, &mut gx, dim, 1).unwrap();
+
+        // finite differences of sum(gy * y)
+        let f = |x: &[f32]| {
+            let mut y = vec![0.0; 12];
+            softmax(x, &mut y, dim, 1).unwrap();
+            y.iter().zip(&gy).map(|(y, g)| y * g).sum::<f32>()
+        };
+
+        for i in 0..12 {
+            let mut xp = x.clone();
+            let mut xm = x.clone();
+            xp[i] += 1e-2;
+            xm[i] -= 1e-2;
+            let fd = (f(&xp) - f(&xm)) / 2e-2;
+            assert!((fd - gx[i]).abs() < 1e-3, "{} {} {}", i, fd, gx[i]);
+        }
+    }
+}
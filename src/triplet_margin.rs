@@ -0,0 +1,150 @@
+use itertools::izip;
+use crate::error::BMLSError;
+use crate::error;
+use crate::reduction::{Reduction, reduce_rows_multi};
+
+/// # Triplet Margin Loss
+/// - A: Anchor Embeddings (N x D)
+/// - P: Positive Embeddings, similar to the anchor (N x D)
+/// - Neg: Negative Embeddings, dissimilar to the anchor (N x D)
+/// - E: Error, (N x 1) vector for `Reduction::None`, otherwise (1)
+/// - GA: Gradient of E w.r.t. A (N x D)
+/// - GP: Gradient of E w.r.t. P (N x D)
+/// - GN: Gradient of E w.r.t. Neg (N x D)
+/// - Dim: [N, D]
+/// - Margin: Required gap between the two distances
+/// - Sample_Weights: Optional weight for each row (N)
+/// - Reduction: How the rows are combined into E
+///
+/// With d the euclidean distance: \
+/// e = max(0, d(a, p) - d(a, neg) + margin)
+#[inline]
+#[allow(clippy::too_many_arguments)]
+pub fn triplet_margin(
+    a: &[f32],
+    p: &[f32],
+    neg: &[f32],
+    e: &mut [f32],
+    ga: &mut [f32],
+    gp: &mut [f32],
+    gn: &mut [f32],
+    dim: [usize; 2],
+    margin: f32,
+    sample_weights: Option<&[f32]>,
+    reduction: Reduction,
+) -> Result<(), BMLSError> {
+    let len = dim[0] * dim[1];
+    if a.len() != len {
+        return error::length_mismatch("A", a.len(), "Dim", len)
+    }
+
+    if p.len() != len {
+        return error::length_mismatch("P", p.len(), "Dim", len)
+    }
+
+    if neg.len() != len {
+        return error::length_mismatch("Neg", neg.len(), "Dim", len)
+    }
+
+    if ga.len() != len {
+        return error::length_mismatch("GA", ga.len(), "Dim", len)
+    }
+
+    if gp.len() != len {
+        return error::length_mismatch("GP", gp.len(), "Dim", len)
+    }
+
+    if gn.len() != len {
+        return error::length_mismatch("GN", gn.len(), "Dim", len)
+    }
+
+    let cols = dim[1];
+
+    reduce_rows_multi(e, &mut [ga, gp, gn], dim[0], sample_weights, reduction, |i, g| {
+        let a = &a[i * cols..(i + 1) * cols];
+        let p = &p[i * cols..(i + 1) * cols];
+        let n = &neg[i * cols..(i + 1) * cols];
+
+        let dp = distance(a, p);
+        let dn = distance(a, n);
+        let loss = dp - dn + margin;
+
+        if loss <= 0.0 {
+            for g in g.iter_mut() {
+                g.fill(0.0);
+            }
+            return Some(0.0)
+        }
+
+        // d(d(u, v)) / d(u) = (u - v) / d(u, v), taken as 0 where u == v
+        let sp = if dp > 0.0 { 1. / dp } else { 0.0 };
+        let sn = if dn > 0.0 { 1. / dn } else { 0.0 };
+
+        for (a, p, n, g) in izip!(a, p, n, g[0].iter_mut()) {
+            *g = (a - p) * sp - (a - n) * sn;
+        }
+
+        for (a, p, g) in izip!(a, p, g[1].iter_mut()) {
+            *g = (p - a) * sp;
+        }
+
+        for (a, n, g) in izip!(a, n, g[2].iter_mut()) {
+            *g = (a - n) * sn;
+        }
+
+        Some(loss)
+    })
+}
+
+#[inline]
+fn distance(u: &[f32], v: &[f32]) -> f32 {
+    izip!(u, v).map(|(u, v)| (u - v) * (u - v)).sum::<f32>().sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_triplet_margin_gradient() {
+        let dim = [2, 3];
+        let a = [1.0, 0.5, -0.2, 0.3, 0.8, 0.1];
+        let p = [0.4, -1.0, 0.7, 0.5, 0.9, -0.3];
+        let n = [0.9, 0.2, 0.1, 3.0, -2.0, 1.0];
+
+        let mut e = [0.0; 2];
+        let mut ga = [0.0; 6];
+        let mut gp = [0.0; 6];
+        let mut gn = [0.0; 6];
+        triplet_margin(&a, &p, &n, &mut e, &mut ga, &mut gp, &mut gn, dim, 1.0, None, Reduction::None).unwrap();
+
+        // only the first triplet violates the margin
+        assert!(e[0] > 0.0);
+        assert_eq!(e[1], 0.0);
+
+        let f = |a: &[f32], p: &[f32], n: &[f32]| {
+            let mut e = [0.0];
+            let g = || [0.0; 6];
+            triplet_margin(a, p, n, &mut e, &mut g(), &mut g(), &mut g(), dim, 1.0, None, Reduction::Sum).unwrap();
+            e[0]
+        };
+
+        for i in 0..6 {
+            let fd = |x: [f32; 6], which: usize| {
+                let (mut xp, mut xm) = (x, x);
+                xp[i] += 1e-2;
+                xm[i] -= 1e-2;
+                let (up, um) = match which {
+                    0 => (f(&xp, &p, &n), f(&xm, &p, &n)),
+                    1 => (f(&a, &xp, &n), f(&a, &xm, &n)),
+                    _ => (f(&a, &p, &xp), f(&a, &p, &xm)),
+                };
+                (up - um) / 2e-2
+            };
+            assert!((fd(a, 0) - ga[i]).abs() < 1e-3);
+            assert!((fd(p, 1) - gp[i]).abs() < 1e-3);
+            assert!((fd(n, 2) - gn[i]).abs() < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,35 @@
+/// # Weight Decay
+/// Regularization applied to the weights by the optimizers. 
+/// 
+/// To exclude a tensor (biases, normalization parameters) pass
+/// `WeightDecay::None` for it, or use `masked`.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum WeightDecay {
+    /// No weight decay.
+    #[default]
+    None,
+    /// Classic L2 regularization. `decay * w` is added to the gradient
+    /// before the update, so it passes through the optimizer's state.
+    L2(f32),
+    /// Decoupled weight decay (AdamW). The weights are shrunk by 
+    /// `lr * decay * w` directly, independent of the gradient.
+    Decoupled(f32),
+}
+
+impl WeightDecay {
+    /// Returns `self` if `apply` is true, otherwise `WeightDecay::None`.
+    #[inline]
+    pub fn masked(self, apply: bool) -> Self {
+        if apply { self } else { WeightDecay::None }
+    }
+
+    /// Splits the decay into its (L2, Decoupled) coefficients.
+    #[inline]
+    pub(crate) fn coefficients(self) -> (f32, f32) {
+        match self {
+            WeightDecay::None => (0.0, 0.0),
+            WeightDecay::L2(decay) => (decay, 0.0),
+            WeightDecay::Decoupled(decay) => (0.0, decay),
+        }
+    }
+}